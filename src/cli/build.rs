@@ -24,7 +24,7 @@ pub fn build_cli(hsm_group: Option<&String>) -> Command {
                 .aliases(["n", "node"])
                 .about("WIP - Add nodes to a cluster")
                 .arg(arg!(-c --cluster <CLUSTER_NAME> "Cluster name"))
-                .arg(arg!(<XNAMES> "Comma separated list of xnames to add to a cluster.\neg: x1003c1s7b0n0,x1003c1s7b0n1,x1003c1s7b1n0"))
+                .arg(arg!(<XNAMES> "Hostlist expression with the nodes to add to a cluster.\neg: x1003c1s7b[0-1]n0,nid[001000-001003],@zinal!x1003c1s7b0n0 or '-' to read from stdin"))
             )
         )
         .subcommand(Command::new("remove")
@@ -41,7 +41,7 @@ pub fn build_cli(hsm_group: Option<&String>) -> Command {
                 .aliases(["n", "node"])
                 .about("WIP - Remove nodes to a cluster")
                 .arg(arg!(-c --cluster <CLUSTER_NAME> "Cluster name"))
-                .arg(arg!(<XNAMES> "Comma separated list of xnames to add to a cluster.\neg: x1003c1s7b0n0,x1003c1s7b0n1,x1003c1s7b1n0"))
+                .arg(arg!(<XNAMES> "Hostlist expression with the nodes to remove from a cluster.\neg: x1003c1s7b[0-1]n0,nid[001000-001003],@zinal!x1003c1s7b0n0 or '-' to read from stdin"))
             )
         )
         .subcommand(
@@ -66,10 +66,14 @@ pub fn build_cli(hsm_group: Option<&String>) -> Command {
                 .subcommand(Command::new("ephemeral-environment")
                     .aliases(["ee", "eph", "ephemeral"])
                     .arg_required_else_help(true)
-                    .about("Returns a hostname use can ssh with the image ID provided. This call is async which means, the user will have to wait a few seconds for the environment to be ready, normally, this takes a few seconds.")
-                    // .arg(arg!(-b --block "Blocks this operation and won't return prompt until the ephemeral environment has been created."))
+                    .about("Returns a hostname use can ssh with the image ID provided. This call is async which means, the user will have to wait a few seconds for the environment to be ready, normally, this takes a few seconds. Use '--wait' to return once the environment accepts SSH connections")
                     // .arg(arg!(-p --"public-ssh-key-id" <PUBLIC_SSH_ID> "Public ssh key id stored in Alps"))
                     .arg(arg!(-i --"image-id" <IMAGE_ID> "Image ID to use as a container image").required(true))
+                    .arg(arg!(-n --name <NAME> "Ephemeral environment name, only letters, digits, '-' and '_'. Defaults to the creation date"))
+                    .arg(arg!(-t --ttl <HOURS> "Hours the ephemeral environment is kept. 'manta delete ephemeral-environment --expired' deletes ephemeral environments past their TTL").value_parser(value_parser!(u32)).default_value("24"))
+                    .arg(arg!(-w --wait "Blocks this operation until the ephemeral environment accepts SSH connections"))
+                    .arg(arg!(-c --connect "Waits for the ephemeral environment and opens an SSH session to it"))
+                    .arg(arg!(-k --"public-key" <FILE> "SSH public key to register in IMS under the user name if it is not registered yet. If missing and the user has no public key in IMS, manta offers to register '~/.ssh/id_*.pub'").value_parser(value_parser!(PathBuf)))
                 ),
         )
         .subcommand(
@@ -86,7 +90,8 @@ pub fn build_cli(hsm_group: Option<&String>) -> Command {
                 .arg_required_else_help(true)
                 .about("Update nodes power status or boot params")
                 .subcommand(subcommand_update_nodes(hsm_group))
-                .subcommand(subcommand_update_hsm_group(hsm_group)),
+                .subcommand(subcommand_update_hsm_group(hsm_group))
+                .subcommand(subcommand_update_boot_params()),
         )
         .subcommand(
             Command::new("log")
@@ -109,18 +114,72 @@ pub fn build_cli(hsm_group: Option<&String>) -> Command {
                     Command::new("node")
                         .alias("n")
                         .about("Connects to a node's console")
-                        .arg(arg!(<XNAME> "node xname").required(true)),
+                        .arg(arg!(<XNAME> "node xname, NID or hostname").required(true))
+                        .arg(arg!(-r --record [PATH] "Records the console output to a file. If PATH is missing or is a folder, then a timestamped file is created in that folder or in 'console_record_dir' (eg --record=/tmp/x1003c1s7b0n0.cast)").num_args(0..=1).require_equals(true))
+                        .arg(arg!(-s --send <TEXT> ... "Sends TEXT to the console without user interaction. Escape sequences \\r (enter), \\n, \\t, \\e (escape) and \\xHH are supported. Can be used multiple times, '--send' and '--expect' run in the order provided"))
+                        .arg(arg!(-e --expect <PATTERN> ... "Waits until the regex PATTERN shows up in the console output. Can be used multiple times, '--send' and '--expect' run in the order provided"))
+                        .arg(arg!(-t --timeout <SECONDS> "Seconds to wait for each '--expect' pattern. Exits with code 124 on timeout").value_parser(value_parser!(u64)).default_value("60"))
+                        .arg(arg!(--escape <CHAR> "Escape character, either a character (eg '~') or a control character (eg '^]'). Defaults to 'console_escape' in manta configuration or '&'. Type <CHAR>? in the console for help")),
+                )
+                .subcommand(
+                    Command::new("log")
+                        .alias("l")
+                        .arg_required_else_help(true)
+                        .about("Prints the console log conman keeps for a node, useful to see what a node printed before crashing")
+                        .arg(arg!(<XNAME> "node xname or NID"))
+                        .arg(arg!(-s --since <SINCE> "Prints the log from the last conman timestamp before SINCE, so nothing printed after SINCE is missed. SINCE is either a date (eg 2024-01-31 or 2024-01-31T22:00:00) or a duration (eg 30m, 12h or 2d)"))
+                        .arg(arg!(-n --tail <LINES> "Prints only the last LINES lines").value_parser(value_parser!(usize)))
+                        .arg(arg!(-g --grep <PATTERN> "Prints only lines matching the extended regex PATTERN, same as 'grep -E'")),
+                )
+                .subcommand(
+                    Command::new("tail")
+                        .alias("tl")
+                        .arg_required_else_help(true)
+                        .about("Prints the console output of many nodes at once. Read-only, input is not sent to the nodes")
+                        .arg(arg!(<XNAMES> "Hostlist expression with the nodes.\neg: x1003c1s7b[0-1]n0,nid[001000-001003],@zinal!x1003c1s7b0n0 or '-' to read from stdin"))
+                        .arg(arg!(-u --until <PATTERN> "Stops once the regex PATTERN shows up on the console of every node (eg 'login:')")),
+                )
+                .subcommand(
+                    Command::new("replay")
+                        .aliases(["r", "play"])
+                        .arg_required_else_help(true)
+                        .about("Prints a console recording at its original pace")
+                        .arg(arg!(<FILE> "Console recording file").value_parser(value_parser!(PathBuf)))
+                        .arg(arg!(-s --speed <SPEED> "Playback speed multiplier (eg 10 to replay 10 times faster)").value_parser(value_parser!(f64)).default_value("1"))
+                        .arg(arg!(-i --"max-idle" <SECONDS> "Limits pauses between console output to SECONDS seconds").value_parser(value_parser!(f64))),
                 )
                 .subcommand(
                     Command::new("target-ansible")
                         .aliases(["t", "ta", "target", "ansible"])
                         .arg_required_else_help(true)
-                        .about("Opens an interactive session to the ansible target container of a CFS session")
-                        .arg(arg!(<SESSION_NAME> "CFS session name").required(true)),
+                        .about("Opens an interactive session to the ansible target container of a CFS session target image. For CFS sessions target dynamic, the ansible output is followed read-only unless '--interactive' is provided")
+                        .arg(arg!(<SESSION_NAME> "CFS session name").required(true))
+                        .arg(arg!(-i --interactive "Opens a shell in the ansible container of a CFS session target dynamic instead of following its output read-only. CFS sessions target image are always interactive"))
+                        .arg(arg!(--escape <CHAR> "Escape character, either a character (eg '~') or a control character (eg '^]'). Defaults to 'console_escape' in manta configuration or '&'. Type <CHAR>? in the console for help")),
                 ),
         )
         .subcommand(subcommand_delete(hsm_group))
         .subcommand(subcommand_config())
+        .subcommand(subcommand_serve(hsm_group))
+}
+
+pub fn subcommand_serve(hsm_group: Option<&String>) -> Command {
+    let mut serve_metrics = Command::new("metrics")
+        .about("Exposes nodes and CFS sessions metrics in Prometheus text format on '/metrics'")
+        .arg(arg!(-l --listen <ADDRESS> "Address to listen on").default_value("127.0.0.1:9101"))
+        .arg(arg!(--"cache-ttl" <SECONDS> "Seconds between metrics collections, at least 10. Scrapes in between get the cached metrics").value_parser(value_parser!(u64).range(10..)).default_value("60"));
+
+    match hsm_group {
+        None => {
+            serve_metrics = serve_metrics.arg(arg!(-H --"hsm-group" <HSM_GROUP_NAME> "hsm group name. If missing, metrics are collected for all HSM groups the user has access to"))
+        }
+        Some(_) => {}
+    }
+
+    Command::new("serve")
+        .arg_required_else_help(true)
+        .about("Run manta as a long running service")
+        .subcommand(serve_metrics)
 }
 
 pub fn subcommand_config() -> Command {
@@ -185,6 +244,24 @@ pub fn subcommand_config() -> Command {
 }
 
 pub fn subcommand_delete(hsm_group: Option<&String>) -> Command {
+    let mut delete_image = Command::new("images")
+                .aliases(["i", "img", "image"])
+                .arg_required_else_help(true)
+                .about("Deletes IMS images and their artifacts in S3. Images booted by nodes or referenced by BOS sessiontemplates are not deleted unless '--force' is used")
+                .arg(arg!([IMAGE_IDS] ... "IMS image IDs"))
+                .arg(arg!(-n --name <REGEX> "Deletes images with name matching the regex\neg:\nmanta delete images --name '^zinal-cos-.*-2023'"))
+                .arg(arg!(-f --force "Deletes images even if nodes boot them or BOS sessiontemplates reference them"))
+                .arg(arg!(-y --"yes" "Automatic yes to prompts; assume 'yes' as answer to all prompts and run non-interactively"))
+                .group(ArgGroup::new("images-to-delete").args(["IMAGE_IDS", "name"]).multiple(true).required(true));
+
+    match hsm_group {
+        None => {
+            delete_image =
+                delete_image.arg(arg!(-H --"hsm-group" <HSM_GROUP_NAME> "hsm group name"))
+        }
+        Some(_) => {}
+    }
+
     let mut delete = Command::new("delete")
                 .arg_required_else_help(true)
                 .about("Deletes CFS configurations, CFS sessions, BOS sessiontemplates, BOS sessions and images related to CFS configuration/s.")
@@ -192,7 +269,24 @@ pub fn subcommand_delete(hsm_group: Option<&String>) -> Command {
                 .arg(arg!(-s --since <DATE> "Deletes CFS configurations, CFS sessions, BOS sessiontemplate, BOS sessions and images related to CFS configurations with 'last updated' after since date. Note: date format is %Y-%m-%d\neg:\nmanta delete --since 2023-01-01 --until 2023-10-01\nDeletes all data related to CFS configurations created or updated between 01/01/2023T00:00:00Z and 01/10/2023T00:00:00Z"))
                 .arg(arg!(-u --until <DATE> "Deletes CFS configuration, CFS sessions, BOS sessiontemplate, BOS sessions and images related to the CFS configuration with 'last updated' before until date. Note: date format is %Y-%m-%d\neg:\nmanta delete --until 2023-10-01\nDeletes all data related to CFS configurations created or updated before 01/10/2023T00:00:00Z"))
                 .arg(arg!(-y --"yes" "Automatic yes to prompts; assume 'yes' as answer to all prompts and run non-interactively. Image artifacts and configurations used by nodes will not be deleted"))
-                .group(ArgGroup::new("since_and_until").args(["since", "until"]).multiple(true).requires("until").conflicts_with("configuration-name"));
+                .group(ArgGroup::new("since_and_until").args(["since", "until"]).multiple(true).requires("until").conflicts_with("configuration-name"))
+                .args_conflicts_with_subcommands(true)
+                .subcommand_negates_reqs(true)
+                .subcommand(Command::new("ephemeral-environment")
+                    .aliases(["ee", "eph", "ephemeral"])
+                    .arg_required_else_help(true)
+                    .about("Deletes ephemeral environments")
+                    .arg(arg!([NAMES] ... "Ephemeral environment names or IMS job IDs"))
+                    .arg(arg!(-e --expired "Deletes all ephemeral environments past their TTL, only the ones created by the user running this command unless '--all-users' is set"))
+                    .arg(arg!(-a --"all-users" "Also deletes ephemeral environments of other users, by default only ephemeral environments created by the user running this command are deleted"))
+                    .arg(arg!(-y --"yes" "Automatic yes to prompts; assume 'yes' as answer to all prompts and run non-interactively")))
+                .subcommand(Command::new("public-keys")
+                    .aliases(["pk", "public-key"])
+                    .arg_required_else_help(true)
+                    .about("Deletes SSH public keys registered in IMS under the user name")
+                    .arg(arg!(<IDS> ... "IMS public key IDs"))
+                    .arg(arg!(-y --"yes" "Automatic yes to prompts; assume 'yes' as answer to all prompts and run non-interactively")))
+                .subcommand(delete_image);
 
     match hsm_group {
         None => {
@@ -216,8 +310,8 @@ pub fn subcommand_get_hw_components() -> Command {
     let command_get_hs_configuration_node = Command::new("node")
                 .alias("n")
                 .arg_required_else_help(true)
-                .about("Get hw components for some nodes")
-                .arg(arg!(<XNAMES> "List of xnames separated by commas").required(true))
+                .about("Get hw components for a node")
+                .arg(arg!(<XNAMES> "Node xname, NID or hostname. Hostlist expressions are accepted as long as they resolve to one node.\neg: x1003c1s7b0n0 or nid001000").required(true))
                 .arg(arg!(-t --type <TYPE> "Filters output to specific type").value_parser(ArtifactType::iter().map(|e| e.into()).collect::<Vec<&str>>()))
                 .arg(arg!(-o --output <FORMAT> "Output format. If missing it will print output data in human redeable (tabular) format").value_parser(["json"]));
 
@@ -312,7 +406,12 @@ pub fn subcommand_get_cluster_details(hsm_group: Option<&String>) -> Command {
         .arg(arg!(-n --"nids-only-one-line" "Prints nids in one line eg nidxxxxxx,nidyyyyyy,nidzzzzzz,..."))
         .arg(arg!(-x --"xnames-only-one-line" "Prints xnames in one line eg x1001c1s5b0n0,x1001c1s5b0n1,..."))
         .arg(arg!(-s --"status" "Get cluster status:\n - OK: All nodes are operational (booted and configured)\n - OFF: At least one node is OFF\n - ON: No nodes OFF and at least one is ON\n - STANDBY: At least one node's heartbeat is lost\n - UNCONFIGURED: All nodes are READY but at least one of them is being configured\n - FAILED: At least one node configuration failed"))
-        .arg(arg!(-o --output <FORMAT> "Output format. If missing it will print output data in human redeable (tabular) format").value_parser(["json"]));
+        .arg(arg!(-o --output <FORMAT> "Output format. If missing it will print output data in human redeable (tabular) format").value_parser(["json"]))
+        .arg(arg!(-w --watch [SECONDS] "Refresh the nodes table every SECONDS seconds (10 by default, eg --watch=30) highlighting power status, configuration status and error count changes").value_parser(value_parser!(u64)).num_args(0..=1).require_equals(true).default_missing_value("10").conflicts_with_all(["nids-only-one-line", "xnames-only-one-line", "output", "status"]))
+        .arg(arg!(--until <STATE> "Stop watching once all nodes reach this state. 'ready' means powered on and configured").value_parser(["on", "off", "configured", "ready"]).requires("watch"))
+        .arg(arg!(--health "Get cluster health report. Counts nodes per power status, configuration status and enabled flag and lists the unhealthy nodes (not powered on, configuration failed or disabled). Exit code follows Nagios plugins convention: 0 OK, 1 WARNING, 2 CRITICAL, 3 UNKNOWN").conflicts_with_all(["nids-only-one-line", "xnames-only-one-line", "status", "watch"]))
+        .arg(arg!(--warning <THRESHOLD> "Number (eg 2) or percentage (eg 10%) of unhealthy nodes from which cluster health is WARNING").default_value("1").requires("health"))
+        .arg(arg!(--critical <THRESHOLD> "Number (eg 4) or percentage (eg 25%) of unhealthy nodes from which cluster health is CRITICAL").default_value("25%").requires("health"));
 
     match hsm_group {
         None => {
@@ -332,7 +431,9 @@ pub fn subcommand_get_node(hsm_group: Option<&String>) -> Command {
         .about("DEPRECATED - Please use 'manta get cluster' instead\nThis command will be DEPRECATED in manta v1.15.0. the new command to use will be replaced by 'manta get cluster'. Get members of a HSM group")
         .arg(arg!(-n --"nids-only-one-line" "Prints nids in one line eg nidxxxxxx,nidyyyyyy,nidzzzzzz,..."))
         .arg(arg!(-x --"xnames-only-one-line" "Prints xnames in one line eg x1001c1s5b0n0,x1001c1s5b0n1,..."))
-        .arg(arg!(-o --output <FORMAT> "Output format. If missing it will print output data in human redeable (tabular) format").value_parser(["json"]));
+        .arg(arg!(-o --output <FORMAT> "Output format. If missing it will print output data in human redeable (tabular) format").value_parser(["json"]))
+        .arg(arg!(-w --watch [SECONDS] "Refresh the nodes table every SECONDS seconds (10 by default, eg --watch=30) highlighting power status, configuration status and error count changes").value_parser(value_parser!(u64)).num_args(0..=1).require_equals(true).default_missing_value("10").conflicts_with_all(["nids-only-one-line", "xnames-only-one-line", "output"]))
+        .arg(arg!(--until <STATE> "Stop watching once all nodes reach this state. 'ready' means powered on and configured").value_parser(["on", "off", "configured", "ready"]).requires("watch"));
 
     match hsm_group {
         None => {
@@ -372,7 +473,14 @@ pub fn subcommand_get_images(hsm_group: Option<&String>) -> Command {
         .arg(
             arg!(-l --limit <VALUE> "Filter records to the <VALUE> most common number of images created")
                 .value_parser(value_parser!(u8).range(1..)),
-        );
+        )
+        .arg(arg!(-n --name <REGEX> "Return only images with name matching the regex"))
+        .arg(arg!(-s --since <DATE> "Return only images created after since date. Note: date format is %Y-%m-%d"))
+        .arg(arg!(-u --"show-usage" "Show the nodes booting each image, the BOS sessiontemplates referencing it and the CFS sessions which built it"))
+        .arg(arg!(--unused "Return only images neither booted by any node nor referenced by any BOS sessiontemplate"))
+        .arg(arg!(--"in-use" "Return only images booted by nodes or referenced by BOS sessiontemplates"))
+        .group(ArgGroup::new("usage").args(["unused", "in-use"]))
+        .arg(arg!(-o --output <FORMAT> "Output format. If missing it will print output data in human redeable (tabular) format").value_parser(["json"]));
 
     match hsm_group {
         None => {
@@ -398,6 +506,53 @@ pub fn subcommand_get(hsm_group: Option<&String>) -> Command {
         .subcommand(subcommand_get_cluster_details(hsm_group))
         .subcommand(subcommand_get_hsm_groups_details(hsm_group))
         .subcommand(subcommand_get_images(hsm_group))
+        .subcommand(subcommand_get_node_history())
+        .subcommand(subcommand_get_node_map())
+        .subcommand(subcommand_get_boot_params())
+        .subcommand(subcommand_get_ephemeral_envs())
+        .subcommand(subcommand_get_public_keys())
+}
+
+pub fn subcommand_get_public_keys() -> Command {
+    Command::new("public-keys")
+        .aliases(["pk", "public-key"])
+        .about("Get the SSH public keys registered in IMS under the user name")
+        .arg(arg!(-o --output <FORMAT> "Output format. If missing it will print output data in human redeable (tabular) format").value_parser(["json"]))
+}
+
+pub fn subcommand_get_ephemeral_envs() -> Command {
+    Command::new("ephemeral-environments")
+        .aliases(["ee", "eph", "ephemeral"])
+        .about("Get ephemeral environments, their SSH host and when they expire")
+        .arg(arg!(-u --user <USER> "Return only ephemeral environments created by USER"))
+        .arg(arg!(-o --output <FORMAT> "Output format. If missing it will print output data in human redeable (tabular) format").value_parser(["json"]))
+}
+
+pub fn subcommand_get_boot_params() -> Command {
+    Command::new("boot-params")
+        .aliases(["bp", "bootparams"])
+        .arg_required_else_help(true)
+        .about("Get image, kernel, initrd and kernel parameters the nodes boot with. Nodes with same boot parameters are grouped")
+        .arg(arg!(<XNAMES> "Hostlist expression with the nodes.\neg: x1003c1s7b[0-1]n0,nid[001000-001003],@zinal!x1003c1s7b0n0 or '-' to read from stdin"))
+        .arg(arg!(-o --output <FORMAT> "Output format. If missing it will print output data in human redeable (tabular) format").value_parser(["json"]))
+}
+
+pub fn subcommand_get_node_history() -> Command {
+    Command::new("node-history")
+        .aliases(["nh", "history"])
+        .arg_required_else_help(true)
+        .about("Get a timeline of what happened to a list of nodes. It combines CFS sessions targeting the nodes, CFS component state, boot image and power and boot operations registered in manta's audit file")
+        .arg(arg!(<XNAMES> "Hostlist expression with the nodes.\neg: x1003c1s7b[0-1]n0,nid[001000-001003],@zinal!x1003c1s7b0n0 or '-' to read from stdin"))
+        .arg(arg!(-l --limit <VALUE> "Return only the most recent entries per node").value_parser(value_parser!(usize)))
+        .arg(arg!(-o --output <FORMAT> "Output format. If missing it will print output data in human redeable (tabular) format").value_parser(["json"]))
+}
+
+pub fn subcommand_get_node_map() -> Command {
+    Command::new("node-map")
+        .aliases(["nm", "nids"])
+        .about("Get xname, NID, hostname and role of the nodes. The node map is cached locally for a day")
+        .arg(arg!(-r --refresh "Fetch the node map from HSM instead of using the local cache"))
+        .arg(arg!(-o --output <FORMAT> "Output format. If missing it will print output data in human redeable (tabular) format").value_parser(["json", "csv"]))
 }
 
 pub fn subcommand_apply_hw_configuration() -> Command {
@@ -420,13 +575,13 @@ pub fn subcommand_apply_configuration(hsm_group: Option<&String>) -> Command {
         .aliases(["conf", "config"])
         .arg_required_else_help(true)
         .about("Create a CFS configuration")
-        .arg(arg!(-f --file <SAT_FILE> "SAT file with configuration details").value_parser(value_parser!(PathBuf)).required(true))
+        .arg(arg!(-f --file <SAT_FILE> "SAT file with configuration details").value_parser(value_parser!(PathBuf)))
+        .arg(arg!(-n --name <VALUE> "Configuration name. Mandatory when the configuration is created from '--layer' or '--layers-file'. '__DATE__' is replaced by '--tag', otherwise '--tag', if provided, is added as a suffix"))
+        .arg(arg!(--layer <LAYER> ... "CFS layer with format 'repo[:playbook][@ref]'. 'repo' is either a path to a local git repo (absolute or starting with './' or '../') or a Shasta VCS repo url/name, 'playbook' defaults to 'site.yml' and 'ref' is a branch, tag or commit id. Layers are applied in the same order they are provided").requires("name"))
+        .arg(arg!(--"layers-file" <LAYERS_FILE> "YAML file with a 'layers' list. Each layer is either a string with format 'repo[:playbook][@ref]' or a map with keys 'repo', 'playbook' and 'ref'. Layers are applied in the same order they are declared").value_parser(value_parser!(PathBuf)).requires("name"))
+        .group(ArgGroup::new("file_or_layers").args(["file", "layer", "layers-file"]).required(true))
         .arg(arg!(-t --tag <VALUE> "Tag added as a suffix in the CFS configuration name and CFS session name. If missing, then a default value will be used with timestamp"))
         .arg(arg!(-o --output <FORMAT> "Output format. If missing it will print output data in human redeable (tabular) format").value_parser(["json"]))
-        // .arg(arg!(-n --name <VALUE> "Configuration name"))
-        // .arg(arg!(-r --"repo-path" <REPO_PATH> ... "Repo path. The path with a git repo and an ansible-playbook to configure the CFS image").value_parser(value_parser!(PathBuf)))
-        // .group(ArgGroup::new("req_flags_name_repo-path").args(["name", "repo-path"]))
-        // .group(ArgGroup::new("req_flag_file").arg("file"))
         ;
 
     match hsm_group {
@@ -443,12 +598,22 @@ pub fn subcommand_apply_session(hsm_group: Option<&String>) -> Command {
     let mut apply_session = Command::new("session")
         .aliases(["s", "se", "ses", "sess", "sssn"])
         .arg_required_else_help(true)
-        .about("Runs the ansible script in local directory against HSM group or xnames.\nNote: the local repo must alrady exists in Shasta VCS")
-        .arg(arg!(-n --name <VALUE> "Session name").required(true))
+        .about("Runs the ansible script in local directory or in a Shasta VCS repo against HSM group or xnames.\nNote: the local repo must alrady exists in Shasta VCS")
+        .arg(arg!(-n --name <VALUE> "Session name").required_unless_present("from"))
         // .arg(arg!(-i --image "If set, creates a CFS sesison of target image, otherwise it will create a CFS session target dynamic").action(ArgAction::SetTrue))
-        .arg(arg!(-r --"repo-path" <REPO_PATH> ... "Repo path. The path with a git repo and an ansible-playbook to configure the CFS image").required(true)
+        .arg(arg!(-r --"repo-path" <REPO_PATH> ... "Repo path. The path with a git repo and an ansible-playbook to configure the CFS image")
             .value_parser(value_parser!(PathBuf)))
+        .arg(arg!(-u --"repo-url" <REPO_URL> ... "Gitea repo URL or name. Runs the ansible-playbook from a repo already in Shasta VCS, no local repo needed. Use with '--ref'").requires("ref"))
+        .arg(arg!(--"ref" <REF> ... "Branch, tag or commit id to use for each '--repo-url'. Either one ref per repo url (in the same order) or a single ref for all repo urls").requires("repo-url"))
+        .arg(arg!(--layer <LAYER> ... "CFS layer with format 'repo[:playbook][@ref]'. 'repo' is either a path to a local git repo (absolute or starting with './' or '../') or a Shasta VCS repo url/name, 'playbook' defaults to 'site.yml' and 'ref' is a branch, tag or commit id. Layers are applied in the same order they are provided"))
+        .arg(arg!(--"layers-file" <LAYERS_FILE> "YAML file with a 'layers' list. Each layer is either a string with format 'repo[:playbook][@ref]' or a map with keys 'repo', 'playbook' and 'ref'. Layers are applied in the same order they are declared").value_parser(value_parser!(PathBuf)))
+        .arg(arg!(--from <SESSION_NAME> "Creates a new CFS session reusing the configuration, ansible limit, verbosity, passthrough and target of an existing CFS session. Configuration, ansible limit, verbosity and passthrough can be overriden"))
+        .arg(arg!(-c --configuration <CONFIGURATION_NAME> "Overrides the configuration of the CFS session provided in '--from'").requires("from"))
+        .arg(arg!(--"failed-only" "Only runs against the nodes of the CFS session provided in '--from' which configuration status is 'failed'").action(ArgAction::SetTrue).requires("from"))
+        .group(ArgGroup::new("repo-path_or_repo-url_or_layers_or_from").args(["repo-path", "repo-url", "layer", "layers-file", "from"]).required(true))
         .arg(arg!(-w --"watch-logs" "Watch logs. Hooks stdout to see container running ansible scripts"))
+        .arg(arg!(--"wait-for-free" "If any node is targeted by a running or pending CFS session, waits until those CFS sessions finish before creating the new CFS session. By default, manta exits if any node is busy"))
+        .arg(arg!(--"wait-for-free-timeout" <SECONDS> "Seconds to wait for the CFS sessions targeting the nodes to finish. Exits with an error on timeout").value_parser(value_parser!(u64)).default_value("3600").requires("wait-for-free"))
         .arg(arg!(-v --"ansible-verbosity" <VALUE> "Ansible verbosity. The verbose mode to use in the call to the ansible-playbook command.\n1 = -v, 2 = -vv, etc. Valid values range from 0 to 4. See the ansible-playbook help for more information.")
             .value_parser(["0", "1", "2", "3", "4"])
             .num_args(1)
@@ -460,13 +625,13 @@ pub fn subcommand_apply_session(hsm_group: Option<&String>) -> Command {
     apply_session = match hsm_group {
         Some(_) => {
             apply_session
-                .arg(arg!(-l --"ansible-limit" <VALUE> "Ansible limit. Target xnames to the CFS session. Note: ansible-limit must be a subset of hsm-group if both parameters are provided").required(true))
+                .arg(arg!(-l --"ansible-limit" <VALUE> "Ansible limit. Hostlist expression with the target nodes to the CFS session.\neg: x1003c1s7b[0-1]n0,nid[001000-001003],@zinal!x1003c1s7b0n0\nNote: ansible-limit must be a subset of hsm-group if both parameters are provided").required_unless_present("from"))
         }
         None => {
             apply_session
-                .arg(arg!(-l --"ansible-limit" <VALUE> "Ansible limit. Target xnames to the CFS session. Note: ansible-limit must be a subset of hsm-group if both parameters are provided"))
+                .arg(arg!(-l --"ansible-limit" <VALUE> "Ansible limit. Hostlist expression with the target nodes to the CFS session.\neg: x1003c1s7b[0-1]n0,nid[001000-001003],@zinal!x1003c1s7b0n0\nNote: ansible-limit must be a subset of hsm-group if both parameters are provided"))
                 .arg(arg!(-H --"hsm-group" <HSM_GROUP_NAME> "hsm group name"))
-                .group(ArgGroup::new("hsm-group_or_ansible-limit").args(["hsm-group", "ansible-limit"]).required(true))
+                .group(ArgGroup::new("hsm-group_or_ansible-limit").args(["hsm-group", "ansible-limit"]))
         }
     };
 
@@ -521,7 +686,7 @@ pub fn subcommand_apply_node_on(hsm_group: Option<&String>) -> Command {
     let mut apply_node_on = Command::new("on")
         .about("DEPRECATED - Please use 'manta power on' instead\nStart nodes")
         .arg_required_else_help(true)
-        .arg(arg!(<XNAMES> "nodes' xnames"))
+        .arg(arg!(<XNAMES> "Hostlist expression with the nodes.\neg: x1003c1s7b[0-1]n0,nid[001000-001003],@zinal!x1003c1s7b0n0 or '-' to read from stdin"))
         .arg(arg!(-r --reason <TEXT> "reason to power on"));
 
     match hsm_group {
@@ -544,7 +709,7 @@ pub fn subcommand_apply_node_off(hsm_group: Option<&String>) -> Command {
     let mut apply_node_off = Command::new("off")
         .arg_required_else_help(true)
         .about("DEPRECATED - Please use 'manta power off' instead\nShutdown nodes")
-        .arg(arg!(<XNAMES> "nodes' xnames"))
+        .arg(arg!(<XNAMES> "Hostlist expression with the nodes.\neg: x1003c1s7b[0-1]n0,nid[001000-001003],@zinal!x1003c1s7b0n0 or '-' to read from stdin"))
         .arg(arg!(-f --force "force").action(ArgAction::SetTrue))
         .arg(arg!(-r --reason <TEXT> "reason to power off"));
 
@@ -569,7 +734,7 @@ pub fn subcommand_apply_node_reset(hsm_group: Option<&String>) -> Command {
         .aliases(["r", "res", "rst", "restart", "rstrt"])
         .arg_required_else_help(true)
         .about("DEPRECATED - Please use 'manta power reset' instead\nRestart nodes")
-        .arg(arg!(<XNAMES> "nodes' xnames"))
+        .arg(arg!(<XNAMES> "Hostlist expression with the nodes.\neg: x1003c1s7b[0-1]n0,nid[001000-001003],@zinal!x1003c1s7b0n0 or '-' to read from stdin"))
         .arg(arg!(-f --force "force").action(ArgAction::SetTrue))
         .arg(arg!(-r --reason <TEXT> "reason to reset"));
 
@@ -589,16 +754,39 @@ pub fn subcommand_apply_node_reset(hsm_group: Option<&String>) -> Command {
     apply_node_reset
 }
 
+pub fn subcommand_update_boot_params() -> Command {
+    Command::new("boot-params")
+        .aliases(["bp", "bootparams"])
+        .arg_required_else_help(true)
+        .about("Add, remove or replace kernel parameters, kernel or initrd of a list of nodes. Changes are shown before updating BSS. Nodes are not rebooted")
+        .arg(arg!(<XNAMES> "Hostlist expression with the nodes which boot parameters will be updated.\neg: x1003c1s7b[0-1]n0,nid[001000-001003],@zinal!x1003c1s7b0n0 or '-' to read from stdin"))
+        .arg(arg!(-a --add <PARAM> ... "Kernel parameter to append if missing (eg 'quiet' or 'hugepages=8'). Can be used multiple times"))
+        .arg(arg!(-r --remove <PARAM> ... "Kernel parameter to remove, either a key to remove all its values (eg 'console') or 'key=value'. Can be used multiple times"))
+        .arg(arg!(-s --set <PARAM> ... "Kernel parameter with format 'key=value' which value is replaced, or appended if missing. Can be used multiple times"))
+        .arg(arg!(--kernel <PATH> "Kernel path (eg s3://boot-images/<image id>/kernel)"))
+        .arg(arg!(--initrd <PATH> "Initrd path (eg s3://boot-images/<image id>/initrd)"))
+        .arg(arg!(-y --"yes" "Automatic yes to prompts; assume 'yes' as answer to all prompts and run non-interactively"))
+        .group(ArgGroup::new("boot-params-changes").args(["add", "remove", "set", "kernel", "initrd"]).multiple(true).required(true))
+}
+
 pub fn subcommand_update_nodes(hsm_group: Option<&String>) -> Command {
     let mut update_nodes = Command::new("nodes")
         .aliases(["n", "node", "nd"])
         .arg_required_else_help(true)
         .about("Updates boot and configuration of a group of nodes. Boot configuration means updating the image used to boot the machine. Configuration of a node means the CFS configuration with the ansible scripts running once a node has been rebooted.\neg:\nmanta update hsm-group --boot-image <boot cfs configuration name> --desired-configuration <desired cfs configuration name>")
         .arg(arg!(-b --"boot-image" <CFS_CONFIG> "CFS configuration name related to the image to boot the nodes"))
-        .arg(arg!(-d --"desired-configuration" <CFS_CONFIG> "CFS configuration name to configure the nodes after booting"));
+        .arg(arg!(-d --"desired-configuration" <CFS_CONFIG> "CFS configuration name to configure the nodes after booting"))
+        .arg(arg!(--"batch-size" <BATCH_SIZE> "Reboot nodes in batches of N nodes or N% of the nodes (eg 4 or 10%). Next batch starts once all nodes in current batch are powered on and configured by CFS without new errors. The rollout stops if a batch fails"))
+        .arg(arg!(--pause <SECONDS> "Seconds to wait between batches").value_parser(value_parser!(u64)).default_value("0").requires("batch-size"))
+        .arg(arg!(--"health-timeout" <SECONDS> "Seconds to wait for CFS to configure the nodes in a batch").value_parser(value_parser!(u64)).default_value("3600").requires("batch-size"))
+        .arg(arg!(--"enable-cfs" "Enable CFS batcher management of the nodes").conflicts_with("disable-cfs"))
+        .arg(arg!(--"disable-cfs" "Disable CFS batcher management of the nodes, CFS won't configure them until enabled again"))
+        .arg(arg!(--"clear-error-count" "Reset CFS error count of the nodes so CFS retries configuring them"))
+        .arg(arg!(--"clear-state" "Clear CFS state of the nodes so all layers are applied again"))
+        .arg(arg!(--"clear-desired-configuration" "Clear the CFS desired configuration of the nodes").conflicts_with("desired-configuration"));
 
     update_nodes = update_nodes
-        .arg(arg!(<XNAMES> "Comma separated list of xnames which boot image will be updated"));
+        .arg(arg!(<XNAMES> "Hostlist expression with the nodes which boot image will be updated.\neg: x1003c1s7b[0-1]n0,nid[001000-001003],@zinal!x1003c1s7b0n0 or '-' to read from stdin"));
 
     /* update_nodes = update_nodes
     .arg(arg!(<CFS_CONFIG> "CFS configuration name used to boot and configure the nodes")); */
@@ -638,7 +826,15 @@ pub fn subcommand_update_hsm_group(hsm_group: Option<&String>) -> Command {
         .arg_required_else_help(true)
         .about("Updates boot and configuration of all the nodes in a HSM group. Boot configuration means updating the image used to boot the machine. Configuration of a node means the CFS configuration with the ansible scripts running once a node has been rebooted.\neg:\nmanta update hsm-group --boot-image <boot cfs configuration name> --desired-configuration <desired cfs configuration name>")
         .arg(arg!(-b --"boot-image" <CFS_CONFIG> "CFS configuration name related to the image to boot the nodes"))
-        .arg(arg!(-d --"desired-configuration" <CFS_CONFIG> "CFS configuration name to configure the nodes after booting"));
+        .arg(arg!(-d --"desired-configuration" <CFS_CONFIG> "CFS configuration name to configure the nodes after booting"))
+        .arg(arg!(--"batch-size" <BATCH_SIZE> "Reboot nodes in batches of N nodes or N% of the nodes (eg 4 or 10%). Next batch starts once all nodes in current batch are powered on and configured by CFS without new errors. The rollout stops if a batch fails"))
+        .arg(arg!(--pause <SECONDS> "Seconds to wait between batches").value_parser(value_parser!(u64)).default_value("0").requires("batch-size"))
+        .arg(arg!(--"health-timeout" <SECONDS> "Seconds to wait for CFS to configure the nodes in a batch").value_parser(value_parser!(u64)).default_value("3600").requires("batch-size"))
+        .arg(arg!(--"enable-cfs" "Enable CFS batcher management of the nodes").conflicts_with("disable-cfs"))
+        .arg(arg!(--"disable-cfs" "Disable CFS batcher management of the nodes, CFS won't configure them until enabled again"))
+        .arg(arg!(--"clear-error-count" "Reset CFS error count of the nodes so CFS retries configuring them"))
+        .arg(arg!(--"clear-state" "Clear CFS state of the nodes so all layers are applied again"))
+        .arg(arg!(--"clear-desired-configuration" "Clear the CFS desired configuration of the nodes").conflicts_with("desired-configuration"));
 
     update_hsm_group = match hsm_group {
         Some(_) => update_hsm_group,
@@ -671,7 +867,6 @@ pub fn subcommand_migrate_restore() -> Command {
         .arg(arg!(-i --"image-dir" <IMAGE_path> "Path where the image files are stored."))
         .arg(arg!(-p --"pre-hook" <SCRIPT> "Script to run before restoring the backup."))
         .arg(arg!(-a --"post-hook" <SCRIPT> "Script to run immediately after the backup is successfully restored."))
-
 }
 
 pub fn subcommand_power() -> Command {
@@ -689,6 +884,8 @@ pub fn subcommand_power() -> Command {
                         .arg_required_else_help(true)
                         .about("Command to power on all nodes in a cluster")
                         .arg(arg!(-r --reason <TEXT> "reason to power on"))
+                        .arg(arg!(-t --timeout <SECONDS> "Seconds to wait for nodes to reach the target power state").value_parser(value_parser!(u64)).default_value("300"))
+                        .arg(arg!(-o --output <FORMAT> "Output format. If missing it will print output data in human redeable (tabular) format").value_parser(["json"]))
                         .arg(arg!(<CLUSTER_NAME> "Cluster name")),
                 )
                 .subcommand(
@@ -697,7 +894,9 @@ pub fn subcommand_power() -> Command {
                         .arg_required_else_help(true)
                         .about("Command to power on a group of nodes")
                         .arg(arg!(-r --reason <TEXT> "reason to power on"))
-                        .arg(arg!(<NODE_NAME> "Node name")),
+                        .arg(arg!(-t --timeout <SECONDS> "Seconds to wait for nodes to reach the target power state").value_parser(value_parser!(u64)).default_value("300"))
+                        .arg(arg!(-o --output <FORMAT> "Output format. If missing it will print output data in human redeable (tabular) format").value_parser(["json"]))
+                        .arg(arg!(<NODE_NAME> "Hostlist expression with the nodes.\neg: x1003c1s7b[0-1]n0,nid[001000-001003],@zinal!x1003c1s7b0n0 or '-' to read from stdin")),
                 ),
         )
         .subcommand(
@@ -711,6 +910,8 @@ pub fn subcommand_power() -> Command {
                         .about("Command to power off all nodes in a cluster")
                         .arg(arg!(-f --force "force").action(ArgAction::SetTrue))
                         .arg(arg!(-r --reason <TEXT> "reason to power off"))
+                        .arg(arg!(-t --timeout <SECONDS> "Seconds to wait for nodes to reach the target power state").value_parser(value_parser!(u64)).default_value("300"))
+                        .arg(arg!(-o --output <FORMAT> "Output format. If missing it will print output data in human redeable (tabular) format").value_parser(["json"]))
                         .arg(arg!(<CLUSTER_NAME> "Cluster name")),
                 )
                 .subcommand(
@@ -720,7 +921,9 @@ pub fn subcommand_power() -> Command {
                         .about("Command to power off a group of nodes")
                         .arg(arg!(-f --force "force").action(ArgAction::SetTrue))
                         .arg(arg!(-r --reason <TEXT> "reason to power off"))
-                        .arg(arg!(<NODE_NAME> "Node name")),
+                        .arg(arg!(-t --timeout <SECONDS> "Seconds to wait for nodes to reach the target power state").value_parser(value_parser!(u64)).default_value("300"))
+                        .arg(arg!(-o --output <FORMAT> "Output format. If missing it will print output data in human redeable (tabular) format").value_parser(["json"]))
+                        .arg(arg!(<NODE_NAME> "Hostlist expression with the nodes.\neg: x1003c1s7b[0-1]n0,nid[001000-001003],@zinal!x1003c1s7b0n0 or '-' to read from stdin")),
                 ),
         )
         .subcommand(
@@ -734,6 +937,11 @@ pub fn subcommand_power() -> Command {
                         .about("Command to power reset all nodes in a cluster")
                         .arg(arg!(-f --force "force").action(ArgAction::SetTrue))
                         .arg(arg!(-r --reason <TEXT> "reason to power reset"))
+                        .arg(arg!(-t --timeout <SECONDS> "Seconds to wait for nodes to reach the target power state").value_parser(value_parser!(u64)).default_value("300"))
+                        .arg(arg!(-o --output <FORMAT> "Output format. If missing it will print output data in human redeable (tabular) format").value_parser(["json"]))
+                        .arg(arg!(--"batch-size" <BATCH_SIZE> "Reboot nodes in batches of N nodes or N% of the nodes (eg 4 or 10%). Next batch starts once all nodes in current batch are powered on and configured by CFS without new errors. The rollout stops if a batch fails"))
+                        .arg(arg!(--pause <SECONDS> "Seconds to wait between batches").value_parser(value_parser!(u64)).default_value("0").requires("batch-size"))
+                        .arg(arg!(--"health-timeout" <SECONDS> "Seconds to wait for CFS to configure the nodes in a batch").value_parser(value_parser!(u64)).default_value("3600").requires("batch-size"))
                         .arg(arg!(<CLUSTER_NAME> "Cluster name")),
                 )
                 .subcommand(
@@ -743,7 +951,31 @@ pub fn subcommand_power() -> Command {
                         .about("Command to power reset a group of nodes")
                         .arg(arg!(-f --force "force").action(ArgAction::SetTrue))
                         .arg(arg!(-r --reason <TEXT> "reason to power reset"))
-                        .arg(arg!(<NODE_NAME> "Node name")),
+                        .arg(arg!(-t --timeout <SECONDS> "Seconds to wait for nodes to reach the target power state").value_parser(value_parser!(u64)).default_value("300"))
+                        .arg(arg!(-o --output <FORMAT> "Output format. If missing it will print output data in human redeable (tabular) format").value_parser(["json"]))
+                        .arg(arg!(<NODE_NAME> "Hostlist expression with the nodes.\neg: x1003c1s7b[0-1]n0,nid[001000-001003],@zinal!x1003c1s7b0n0 or '-' to read from stdin")),
+                ),
+        )
+        .subcommand(
+            Command::new("status")
+                .aliases(["s", "st"])
+                .arg_required_else_help(true)
+                .about("Command to get power status of cluster/node")
+                .subcommand(
+                    Command::new("cluster")
+                        .aliases(["c", "clstr"])
+                        .arg_required_else_help(true)
+                        .about("Command to get power status of all nodes in a cluster")
+                        .arg(arg!(-o --output <FORMAT> "Output format. If missing it will print output data in human redeable (tabular) format").value_parser(["json"]))
+                        .arg(arg!(<CLUSTER_NAME> "Cluster name")),
+                )
+                .subcommand(
+                    Command::new("node")
+                        .alias("n")
+                        .arg_required_else_help(true)
+                        .about("Command to get power status of a group of nodes")
+                        .arg(arg!(-o --output <FORMAT> "Output format. If missing it will print output data in human redeable (tabular) format").value_parser(["json"]))
+                        .arg(arg!(<NODE_NAME> "Hostlist expression with the nodes.\neg: x1003c1s7b[0-1]n0,nid[001000-001003],@zinal!x1003c1s7b0n0 or '-' to read from stdin")),
                 ),
         )
 }
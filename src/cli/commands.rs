@@ -9,6 +9,7 @@ pub mod apply_node_off;
 pub mod apply_node_on;
 pub mod apply_node_reset;
 pub mod apply_session;
+pub mod apply_session_from;
 pub mod config_set_hsm;
 pub mod config_set_log;
 pub mod config_set_site;
@@ -16,14 +17,25 @@ pub mod config_show;
 pub mod config_unset_auth;
 pub mod config_unset_hsm;
 pub mod console_cfs_session_image_target_ansible;
+pub mod console_log;
 pub mod console_node;
+pub mod console_replay;
+pub mod console_tail;
 pub mod delete_data_related_to_cfs_configuration;
+pub mod delete_ephemeral_env;
+pub mod delete_images;
+pub mod delete_public_keys;
+pub mod get_boot_params;
 pub mod get_configuration;
+pub mod get_ephemeral_env;
 pub mod get_hsm;
 pub mod get_hw_configuration_cluster;
 pub mod get_hw_configuration_node;
 pub mod get_images;
+pub mod get_node_history;
+pub mod get_node_map;
 pub mod get_nodes;
+pub mod get_public_keys;
 pub mod get_session;
 pub mod get_template;
 pub mod log;
@@ -35,7 +47,11 @@ pub mod power_on_cluster;
 pub mod power_on_nodes;
 pub mod power_reset_cluster;
 pub mod power_reset_nodes;
+pub mod power_status_cluster;
+pub mod power_status_nodes;
 pub mod remove_hw_component_cluster;
 pub mod remove_nodes;
+pub mod serve_metrics;
+pub mod update_boot_params;
 pub mod update_hsm_group;
 pub mod update_node;
@@ -13,18 +13,22 @@ use mesa::{
         session::mesa::r#struct::CfsSessionGetResponse,
     },
     common::kubernetes,
-    ims, {capmc, hsm},
+    hsm, ims,
 };
 use serde_yaml::Value;
 
 use crate::{
     cli::commands::apply_image::validate_sat_file_images_section,
     common::{
-        self, jwt_ops::get_claims_from_jwt_token, sat_file::import_images_section_in_sat_file,
+        self,
+        jwt_ops::get_claims_from_jwt_token,
+        power_backend::{PowerBackend, PowerOperation, SitePowerBackend},
+        sat_file::import_images_section_in_sat_file,
     },
 };
 
 pub async fn exec(
+    power_backend: &SitePowerBackend,
     shasta_token: &str,
     shasta_base_url: &str,
     shasta_root_cert: &[u8],
@@ -453,21 +457,20 @@ pub async fn process_session_template_section_in_sat_file(
                 .map(|node| node.as_str().unwrap().to_string())
                 .collect();
 
-            // Create CAPMC operation shutdown
-            let capmc_shutdown_nodes_resp = capmc::http_client::node_power_off::post_sync(
-                shasta_token,
-                shasta_base_url,
-                shasta_root_cert,
-                nodes.clone(),
-                Some("Shut down cluster to apply changes".to_string()),
-                true,
-            )
-            .await;
+            // Create power operation shutdown
+            let power_off_nodes_resp = power_backend
+                .transition_sync(
+                    shasta_token,
+                    shasta_base_url,
+                    shasta_root_cert,
+                    &nodes,
+                    PowerOperation::Off,
+                    true,
+                    Some(&"Shut down cluster to apply changes".to_string()),
+                )
+                .await;
 
-            log::debug!(
-                "CAPMC shutdown nodes response:\n{:#?}",
-                capmc_shutdown_nodes_resp
-            );
+            log::debug!("Power off nodes response:\n{:#?}", power_off_nodes_resp);
 
             // Create BOS session operation start
             let create_bos_boot_session_resp = mesa::bos::session::shasta::http_client::post(
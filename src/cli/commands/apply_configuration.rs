@@ -1,21 +1,31 @@
 use mesa::{
-    cfs::configuration::mesa::r#struct::cfs_configuration_response::{
-        ApiError, CfsConfigurationResponse,
+    cfs::{
+        self,
+        configuration::mesa::r#struct::cfs_configuration_response::{
+            ApiError, CfsConfigurationResponse,
+        },
     },
     common::kubernetes,
 };
 use serde_yaml::Value;
 use std::path::PathBuf;
 
-use crate::common::{self, cfs_configuration_utils};
+use crate::common::{
+    self, cfs_configuration_utils,
+    cfs_layer_ops::{self, LayerSpec},
+};
 
 /// Creates a configuration from a sat file
 /// NOTE: this method manages 2 types of methods [git, product]. For type product, the name must
 /// match with a git repo name after concatenating it with "-config-management" (eg: layer name
 /// "cos" becomes repo name "cos-config-management" which correlates with https://api-gw-service-nmn.local/vcs/api/v1/repos/cray/cos-config-management)
+/// Alternatively, the configuration can be created from a list of layers with format
+/// 'repo[:playbook][@ref]', in this case layers are applied in the same order they are provided
 /// Return CFS configuration name
 pub async fn exec(
-    path_file: &PathBuf,
+    path_file_opt: Option<&PathBuf>,
+    cfs_configuration_name_opt: Option<&String>,
+    layer_spec_vec: Vec<LayerSpec>,
     shasta_token: &str,
     shasta_base_url: &str,
     shasta_root_cert: &[u8],
@@ -24,9 +34,29 @@ pub async fn exec(
     vault_role_id: &str,
     k8s_api_url: &str,
     gitea_token: &str,
+    gitea_base_url: &str,
     tag: &str,
     output_opt: Option<&String>,
 ) -> anyhow::Result<Vec<String>> {
+    let path_file = match path_file_opt {
+        Some(path_file) => path_file,
+        None => {
+            let cfs_configuration = exec_from_layer_specs(
+                shasta_token,
+                shasta_base_url,
+                shasta_root_cert,
+                gitea_token,
+                gitea_base_url,
+                cfs_configuration_name_opt.unwrap(),
+                &layer_spec_vec,
+                output_opt,
+            )
+            .await;
+
+            return Ok(vec![cfs_configuration.name]);
+        }
+    };
+
     let file_content = std::fs::read_to_string(path_file).expect("SAT file not found. Exit");
     let sat_file_yaml: Value = serde_yaml::from_str(&file_content).unwrap();
 
@@ -116,3 +146,66 @@ pub async fn exec(
 
     Ok(cfs_configuration_name_vec)
 }
+
+/// Creates a configuration from a list of layers
+pub async fn exec_from_layer_specs(
+    shasta_token: &str,
+    shasta_base_url: &str,
+    shasta_root_cert: &[u8],
+    gitea_token: &str,
+    gitea_base_url: &str,
+    cfs_configuration_name: &str,
+    layer_spec_vec: &[LayerSpec],
+    output_opt: Option<&String>,
+) -> CfsConfigurationResponse {
+    let cfs_configuration = match cfs_layer_ops::create_cfs_configuration_from_layer_specs(
+        gitea_token,
+        gitea_base_url,
+        shasta_root_cert,
+        cfs_configuration_name,
+        layer_spec_vec,
+    )
+    .await
+    {
+        Ok(cfs_configuration) => cfs_configuration,
+        Err(error) => {
+            eprintln!("{}. Exit", error);
+            std::process::exit(1);
+        }
+    };
+
+    log::debug!(
+        "Create CFS configuration payload:\n{:#?}",
+        cfs_configuration
+    );
+
+    let cfs_configuration = match cfs::configuration::mesa::http_client::put(
+        shasta_token,
+        shasta_base_url,
+        shasta_root_cert,
+        &cfs_configuration,
+        cfs_configuration_name,
+    )
+    .await
+    {
+        Ok(cfs_configuration) => cfs_configuration,
+        Err(error) => {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+    };
+
+    log::info!("CFS configuration created: {}", cfs_configuration.name);
+
+    // Print output
+    if output_opt.is_some() && output_opt.unwrap().eq("json") {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&cfs_configuration).unwrap()
+        );
+    } else {
+        cfs_configuration_utils::print_table_struct(&vec![cfs_configuration.clone()]);
+    }
+
+    cfs_configuration
+}
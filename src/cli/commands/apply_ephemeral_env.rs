@@ -1,74 +1,289 @@
-use crate::common::jwt_ops::get_claims_from_jwt_token;
+use std::{io::IsTerminal, os::unix::process::CommandExt, path::PathBuf};
+
+use dialoguer::{theme::ColorfulTheme, Confirm};
+
+use crate::common::{ephemeral_env, ims_public_key, jwt_ops::get_claims_from_jwt_token};
 
 pub async fn exec(
     shasta_token: &str,
     shasta_base_url: &str,
     shasta_root_cert: &[u8],
-    /* block: Option<bool>, */ image_id: &str,
+    image_id: &str,
+    name_opt: Option<&String>,
+    ttl_hours: u32,
+    wait: bool,
+    connect: bool,
+    public_key_path_opt: Option<&PathBuf>,
 ) {
     // Take user name and check if there is an SSH public key with that name already in Alps
     let jwt_claims = get_claims_from_jwt_token(shasta_token).unwrap();
 
     let user_public_key_name = jwt_claims["preferred_username"].as_str();
 
+    // Default name is unique per user, eg 20240131T220000
+    let name = name_opt
+        .cloned()
+        .unwrap_or_else(|| chrono::Utc::now().format("%Y%m%dT%H%M%S").to_string());
+
+    if let Err(error) = ephemeral_env::validate_name(&name) {
+        eprintln!("{}. Exit", error);
+        std::process::exit(1);
+    }
+
+    // Names must be unique per user, 'manta delete ephemeral-environment <name>' relies on it
+    let owner = ephemeral_env::get_owner(user_public_key_name.unwrap_or_default());
+
+    if ephemeral_env::get_ephemeral_env_vec(shasta_token, shasta_base_url, shasta_root_cert)
+        .await
+        .iter()
+        .any(|ephemeral_env| ephemeral_env.user.eq(&owner) && ephemeral_env.name.eq(&name))
+    {
+        eprintln!(
+            "Ephemeral environment '{}' already exists for user {}. Exit",
+            name, owner
+        );
+        std::process::exit(1);
+    }
+
     log::info!(
         "Looking for user {} public SSH key",
         user_public_key_name.unwrap()
     );
 
-    let user_public_ssh_id_value = if let Some(user_public_ssh_value) =
-        mesa::ims::public_keys::http_client::get_single(
-            shasta_token,
-            shasta_base_url,
-            shasta_root_cert,
-            user_public_key_name,
-        )
-        .await
-    {
-        user_public_ssh_value["id"].clone()
-    } else {
-        eprintln!("User '{}' does not have an SSH public key in Alps, Please contact platform sys admins. Exit", user_public_key_name.unwrap());
-        std::process::exit(1);
-    };
+    let user_public_ssh_id_value = get_user_public_key_id(
+        shasta_token,
+        shasta_base_url,
+        shasta_root_cert,
+        user_public_key_name.unwrap(),
+        public_key_path_opt,
+    )
+    .await;
 
     log::info!("SSH key found with ID {}", user_public_ssh_id_value);
 
-    // If public ssh key not found, then pompt user to provide public key
-    // NOT YET. At this stage just throw an erro because the key was not found
+    // Create IMS Job. The IMS job has no metadata field, the owner and TTL go in the archive
+    // name so 'manta get ephemeral-environments' and 'manta delete ephemeral-environment
+    // --expired' can find them
+    let archive_name =
+        ephemeral_env::get_archive_name(user_public_key_name.unwrap(), &name, ttl_hours);
 
-    // Create IMS Job
     log::info!(
-        "Creating ephemeral environment baed on image ID {}",
+        "Creating ephemeral environment {} baed on image ID {}",
+        archive_name,
         image_id
     );
+
     let resp_json_rslt = mesa::ims::job::http_client::post_customize(
         shasta_token,
         shasta_base_url,
         shasta_root_cert,
-        "__test_image_to_delete",
+        &archive_name,
         image_id,
-        user_public_ssh_id_value.as_str().unwrap(),
+        &user_public_ssh_id_value,
     )
     .await;
 
-    let hostname_value = match resp_json_rslt {
-        Ok(resp_json) => resp_json
-            .pointer("/ssh_containers/0/connection_info/customer_access/host")
-            .cloned()
-            .unwrap(),
-        Err(_) => std::process::exit(1),
+    let resp_json = match resp_json_rslt {
+        Ok(resp_json) => resp_json,
+        Err(error) => {
+            eprintln!(
+                "ERROR - Could not create ephemeral environment. Reason:\n{}\nExit",
+                error
+            );
+            std::process::exit(1);
+        }
     };
 
-    // if block.unwrap() {
-    //     println!("Now block the call");
-    //
-    // } else {
-    //     println!("Do not block");
-    // }
+    log::info!(
+        target: "app::audit",
+        "User: {} ({}) ; Operation: Create ephemeral environment {} with image {} and TTL {}h",
+        jwt_claims["name"].as_str().unwrap_or_default(),
+        jwt_claims["preferred_username"].as_str().unwrap_or_default(),
+        archive_name,
+        image_id,
+        ttl_hours
+    );
+
+    let (hostname, port) = if wait || connect {
+        let ims_job_id = resp_json["id"].as_str().unwrap_or_default();
+
+        // stdout only gets the hostname so it can be used in scripts
+        eprintln!(
+            "Waiting for ephemeral environment {} to accept SSH connections",
+            name
+        );
+
+        match ephemeral_env::wait_for_ssh(
+            shasta_token,
+            shasta_base_url,
+            shasta_root_cert,
+            ims_job_id,
+        )
+        .await
+        {
+            Ok(ephemeral_env) => (
+                ephemeral_env.host_opt.unwrap(),
+                ephemeral_env.port_opt.unwrap_or(22),
+            ),
+            Err(error) => {
+                eprintln!("ERROR - {}. Exit", error);
+                std::process::exit(1);
+            }
+        }
+    } else {
+        (
+            resp_json
+                .pointer("/ssh_containers/0/connection_info/customer_access/host")
+                .and_then(|host| host.as_str())
+                .unwrap_or_default()
+                .to_string(),
+            resp_json
+                .pointer("/ssh_containers/0/connection_info/customer_access/port")
+                .and_then(|port| port.as_u64())
+                .unwrap_or(22),
+        )
+    };
 
     log::info!(
         "Ephemeral environment successfully created! hostname with ssh enabled: {}",
-        hostname_value.as_str().unwrap()
+        hostname
     );
-    println!("{}", hostname_value.as_str().unwrap());
+
+    if connect {
+        // Replaces manta process with ssh so the user gets a regular SSH session
+        let error = std::process::Command::new("ssh")
+            .arg("-p")
+            .arg(port.to_string())
+            .arg(format!(
+                "{}@{}",
+                ephemeral_env::EPHEMERAL_ENV_SSH_USER,
+                hostname
+            ))
+            .exec();
+
+        eprintln!("ERROR - Could not run ssh. Reason:\n{}\nExit", error);
+        std::process::exit(1);
+    }
+
+    println!("{}", hostname);
+}
+
+/// Returns the ID of the IMS public key of the user. If the user has no public key in IMS, then
+/// the key in 'public_key_path_opt' or, after asking the user, a key in '~/.ssh' is registered in
+/// IMS under the user name
+async fn get_user_public_key_id(
+    shasta_token: &str,
+    shasta_base_url: &str,
+    shasta_root_cert: &[u8],
+    user: &str,
+    public_key_path_opt: Option<&PathBuf>,
+) -> String {
+    let user_public_key_vec = ims_public_key::get_user_public_key_vec(
+        shasta_token,
+        shasta_base_url,
+        shasta_root_cert,
+        user,
+    )
+    .await;
+
+    let public_key_path = if let Some(public_key_path) = public_key_path_opt {
+        public_key_path.clone()
+    } else if let Some(user_public_key) = user_public_key_vec.first() {
+        return user_public_key["id"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string();
+    } else {
+        let public_key_path = match ims_public_key::get_local_public_key_path_vec().first() {
+            Some(public_key_path) => public_key_path.clone(),
+            None => {
+                eprintln!("User '{}' does not have an SSH public key in Alps and no public key found in '~/.ssh'. Use '--public-key' to provide one. Exit", user);
+                std::process::exit(1);
+            }
+        };
+
+        if !std::io::stdin().is_terminal()
+            || !Confirm::with_theme(&ColorfulTheme::default())
+                .with_prompt(format!(
+                    "User '{}' does not have an SSH public key in Alps. Do you want to register public key {}?",
+                    user,
+                    public_key_path.display()
+                ))
+                .interact()
+                .unwrap()
+        {
+            eprintln!("User '{}' does not have an SSH public key in Alps. Use '--public-key' to register one. Exit", user);
+            std::process::exit(1);
+        }
+
+        public_key_path
+    };
+
+    let public_key = match ims_public_key::read_public_key(&public_key_path) {
+        Ok(public_key) => public_key,
+        Err(error) => {
+            eprintln!("{}. Exit", error);
+            std::process::exit(1);
+        }
+    };
+
+    // Reuse the IMS public key if the user already registered this key
+    if let Some(user_public_key) = user_public_key_vec.iter().find(|user_public_key| {
+        ims_public_key::is_same_public_key(
+            user_public_key["public_key"].as_str().unwrap_or_default(),
+            &public_key,
+        )
+    }) {
+        return user_public_key["id"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string();
+    }
+
+    log::info!(
+        "Registering public key {} in IMS as '{}'",
+        public_key_path.display(),
+        user
+    );
+
+    let jwt_claims = get_claims_from_jwt_token(shasta_token).unwrap();
+
+    match ims_public_key::http_client::post_public_key(
+        shasta_token,
+        shasta_base_url,
+        shasta_root_cert,
+        user,
+        &public_key,
+    )
+    .await
+    {
+        Ok(user_public_key) => {
+            eprintln!(
+                "Public key {} registered in IMS as '{}'",
+                public_key_path.display(),
+                user
+            );
+
+            log::info!(
+                target: "app::audit",
+                "User: {} ({}) ; Operation: Register IMS public key {} ({})",
+                jwt_claims["name"].as_str().unwrap_or_default(),
+                user,
+                user_public_key["id"].as_str().unwrap_or_default(),
+                public_key_path.display()
+            );
+
+            user_public_key["id"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string()
+        }
+        Err(error) => {
+            eprintln!(
+                "ERROR - Could not register public key in IMS. Reason:\n{}\nExit",
+                error
+            );
+            std::process::exit(1);
+        }
+    }
 }
@@ -1,11 +1,13 @@
-use core::time;
-
-use mesa::capmc;
 use mesa::node::utils::validate_xnames;
 
-use crate::common::jwt_ops::get_claims_from_jwt_token;
+use crate::common::{
+    jwt_ops::get_claims_from_jwt_token,
+    power_backend::{PowerOperation, SitePowerBackend},
+    power_transition,
+};
 
 pub async fn exec(
+    power_backend: &SitePowerBackend,
     hsm_group: Option<&String>,
     shasta_token: &str,
     shasta_base_url: &str,
@@ -30,42 +32,30 @@ pub async fn exec(
 
     println!("Powering off servers: {:?}", xnames);
 
-    let mut i = 0;
-
-    loop {
-        capmc::http_client::node_power_off::post(
-            shasta_token,
-            shasta_base_url,
-            shasta_root_cert,
-            xnames.iter().map(|xname| xname.to_string()).collect(), // TODO: fix this HashSet --> Vec conversion. May need to specify lifespan for capmc struct
-            reason.clone(),
-            force,
-        )
-        .await
-        .unwrap();
-
-        tokio::time::sleep(time::Duration::from_secs(2)).await;
+    let xname_vec: Vec<String> = xnames.iter().map(|xname| xname.to_string()).collect();
 
-        let node_status = capmc::http_client::node_power_status::post(
-            shasta_token,
-            shasta_base_url,
-            shasta_root_cert,
-            &xnames.iter().map(|x| x.to_string()).collect(),
-        )
-        .await
-        .unwrap();
+    let node_power_transition_vec = power_transition::exec(
+        power_backend,
+        shasta_token,
+        shasta_base_url,
+        shasta_root_cert,
+        &xname_vec,
+        PowerOperation::Off,
+        force,
+        reason.as_ref(),
+        power_transition::DEFAULT_POWER_TRANSITION_TIMEOUT_SECS,
+    )
+    .await;
 
-        if i > 30 || node_status.get("off").is_none() {
-            println!("Node's status: {node_status}");
-            break;
-        } else {
-            println!("Node's status: {node_status}");
-            i += 1;
-        }
-    }
+    power_transition::print_result(&node_power_transition_vec, None);
 
     // Audit
     let jwt_claims = get_claims_from_jwt_token(shasta_token).unwrap();
 
     log::info!(target: "app::audit", "User: {} ({}) ; Operation: Apply nodes off {:?}", jwt_claims["name"].as_str().unwrap(), jwt_claims["preferred_username"].as_str().unwrap(), xnames);
+
+    if !power_transition::is_success(&node_power_transition_vec) {
+        eprintln!("Some nodes did not reach power state 'off'. Exit");
+        std::process::exit(1);
+    }
 }
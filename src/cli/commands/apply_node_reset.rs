@@ -1,8 +1,13 @@
-use mesa::{capmc, node::utils::validate_xnames};
+use mesa::node::utils::validate_xnames;
 
-use crate::common::jwt_ops::get_claims_from_jwt_token;
+use crate::common::{
+    jwt_ops::get_claims_from_jwt_token,
+    power_backend::{PowerOperation, SitePowerBackend},
+    power_transition,
+};
 
 pub async fn exec(
+    power_backend: &SitePowerBackend,
     hsm_group: Option<&String>,
     shasta_token: &str,
     shasta_base_url: &str,
@@ -29,28 +34,31 @@ pub async fn exec(
 
     log::info!("Resetting servers: {:?}", xnames);
 
-    capmc::http_client::node_power_off::post_sync(
+    let node_power_transition_vec = power_transition::exec(
+        power_backend,
         shasta_token,
         shasta_base_url,
         shasta_root_cert,
-        xnames.iter().map(|xname| xname.to_string()).collect(),
-        reason.cloned(),
+        &xnames
+            .iter()
+            .map(|xname| xname.to_string())
+            .collect::<Vec<String>>(),
+        PowerOperation::Reset,
         force,
-    )
-    .await
-    .unwrap(); // TODO: idk why power on does not seems to work when forced
-
-    let _node_reset_response = capmc::http_client::node_power_on::post(
-        shasta_token,
-        shasta_base_url,
-        shasta_root_cert,
-        xnames.iter().map(|xname| xname.to_string()).collect(),
-        reason.cloned(),
+        reason,
+        power_transition::DEFAULT_POWER_TRANSITION_TIMEOUT_SECS,
     )
     .await;
 
+    power_transition::print_result(&node_power_transition_vec, None);
+
     // Audit
     let jwt_claims = get_claims_from_jwt_token(shasta_token).unwrap();
 
     log::info!(target: "app::audit", "User: {} ({}) ; Operation: Apply nodes reset {:?}", jwt_claims["name"].as_str().unwrap(), jwt_claims["preferred_username"].as_str().unwrap(), xnames);
+
+    if !power_transition::is_success(&node_power_transition_vec) {
+        eprintln!("Some nodes did not reach power state 'on'. Exit");
+        std::process::exit(1);
+    }
 }
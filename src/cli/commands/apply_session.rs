@@ -1,4 +1,4 @@
-use std::path::{Path, PathBuf};
+use std::path::Path;
 
 use dialoguer::{theme::ColorfulTheme, Confirm};
 use futures::TryStreamExt;
@@ -11,12 +11,18 @@ use mesa::{
     node::utils::validate_xnames,
 };
 
-use crate::common::jwt_ops::get_claims_from_jwt_token;
+use crate::common::{
+    cfs_layer_ops::{self, LayerSpec},
+    cfs_session_utils, gitea,
+    jwt_ops::get_claims_from_jwt_token,
+};
 use k8s_openapi::chrono;
-use substring::Substring;
+use serde_json::json;
 
 use crate::common::local_git_repo;
 
+const CFS_SESSION_CONFLICT_CHECK_INTERVAL_SECS: u64 = 30;
+
 /// Creates a CFS session target dynamic
 /// Returns a tuple like (<cfs configuration name>, <cfs session name>)
 pub async fn exec(
@@ -31,11 +37,12 @@ pub async fn exec(
     k8s_api_url: &str,
     cfs_session_name: Option<String>,
     hsm_group: Option<&String>,
-    repos_paths: Vec<PathBuf>,
+    layer_spec_vec: Vec<LayerSpec>,
     ansible_limit: Option<String>,
     ansible_verbosity: Option<String>,
     ansible_passthrough: Option<String>,
     watch_logs: bool,
+    wait_for_free_timeout_opt: Option<u64>,
 ) -> (String, String) {
     /* let included: HashSet<String>;
     let excluded: HashSet<String>; */
@@ -137,7 +144,7 @@ pub async fn exec(
     // * Check nodes are ready to run, create CFS configuration and CFS session
     let cfs_session_name = check_nodes_are_ready_to_run_cfs_configuration_and_run_cfs_session(
         &cfs_configuration_name,
-        repos_paths,
+        layer_spec_vec,
         gitea_token,
         gitea_base_url,
         shasta_token,
@@ -151,6 +158,7 @@ pub async fn exec(
                 .unwrap_or(0),
         ),
         ansible_passthrough,
+        wait_for_free_timeout_opt,
     )
     .await
     .unwrap();
@@ -214,7 +222,7 @@ pub async fn exec(
 
 pub async fn check_nodes_are_ready_to_run_cfs_configuration_and_run_cfs_session(
     cfs_configuration_name: &str,
-    repos: Vec<PathBuf>,
+    layer_spec_vec: Vec<LayerSpec>,
     gitea_token: &str,
     gitea_base_url: &str,
     shasta_token: &str,
@@ -223,68 +231,20 @@ pub async fn check_nodes_are_ready_to_run_cfs_configuration_and_run_cfs_session(
     limit: Option<String>,
     ansible_verbosity: Option<u8>,
     ansible_passthrough: Option<String>,
+    wait_for_free_timeout_opt: Option<u64>,
 ) -> Result<String, Box<dyn std::error::Error>> {
-    // Get ALL sessions
-    let cfs_sessions = mesa::cfs::session::mesa::http_client::get(
+    // Convert limit (String with list of target nodes for new CFS session) into list of String
+    let limit_value = limit.clone().unwrap_or("".to_string());
+
+    check_nodes_not_in_running_or_pending_cfs_session(
         shasta_token,
         shasta_base_url,
         shasta_root_cert,
-        None,
-        None,
+        &limit_value,
+        wait_for_free_timeout_opt,
     )
     .await?;
 
-    let nodes_in_running_or_pending_cfs_session: Vec<&str> = cfs_sessions
-        .iter()
-        .filter(|cfs_session| {
-            ["running", "pending"].contains(
-                &cfs_session
-                    .status
-                    .as_ref()
-                    .unwrap()
-                    .session
-                    .as_ref()
-                    .unwrap()
-                    .status
-                    .as_ref()
-                    .unwrap()
-                    .as_str(),
-            )
-        })
-        .flat_map(|cfs_session| {
-            cfs_session
-                .ansible
-                .as_ref()
-                .unwrap()
-                .limit
-                .as_ref()
-                .unwrap()
-                .split(',')
-        })
-        .map(|xname| xname.trim())
-        .collect(); // TODO: remove duplicates... sort() + dedup() ???
-
-    log::info!(
-        "Nodes with cfs session running or pending: {:?}",
-        nodes_in_running_or_pending_cfs_session
-    );
-
-    // NOTE: nodes can be a list of xnames or hsm group name
-
-    // Convert limit (String with list of target nodes for new CFS session) into list of String
-    let limit_value = limit.clone().unwrap_or("".to_string());
-    let nodes_list: Vec<&str> = limit_value.split(',').map(|node| node.trim()).collect();
-
-    // Check each node if it has a CFS session already running
-    for node in nodes_list {
-        if nodes_in_running_or_pending_cfs_session.contains(&node) {
-            eprintln!(
-                "The node {} from the list provided is already assigned to a running/pending CFS session. Please try again latter. Exitting", node
-            );
-            std::process::exit(-1);
-        }
-    }
-
     // Check nodes are ready to run a CFS layer
     let xnames: Vec<String> = limit_value
         .split(',')
@@ -333,93 +293,101 @@ pub async fn check_nodes_are_ready_to_run_cfs_configuration_and_run_cfs_session(
         }
     }
 
-    // Check local repos
+    // Check local repos and resolve CFS layers
     let mut layers_summary = vec![];
+    let mut layer_vec = vec![];
 
-    for (i, repo_path) in repos.iter().enumerate() {
-        // log::debug!("Local repo: {} state: {:?}", repo.path().display(), repo.state());
-        // TODO: check each folder has a real git repo
-        // TODO: check each folder has expected file name manta/shasta expects to find the main ansible playbook
-        // TODO: a repo could param value could be a repo name, a filesystem path pointing to a repo or an url pointing to a git repo???
+    for (i, layer_spec) in layer_spec_vec.iter().enumerate() {
         // TODO: format logging on screen so it is more readable
+        let playbook = layer_spec.playbook.as_deref().unwrap_or("site.yml");
+
+        let local_changes_committed_opt = if layer_spec.is_local() {
+            // Get repo from path
+            let repo = match local_git_repo::get_repo(&layer_spec.repo) {
+                Ok(repo) => repo,
+                Err(_) => {
+                    eprintln!("Could not find a git repo in {}", layer_spec.repo);
+                    std::process::exit(1);
+                }
+            };
 
-        // Get repo from path
-        let repo = match local_git_repo::get_repo(&repo_path.to_string_lossy()) {
-            Ok(repo) => repo,
-            Err(_) => {
-                eprintln!(
-                    "Could not find a git repo in {}",
-                    repos[i].to_string_lossy()
-                );
-                std::process::exit(1);
-            }
-        };
-
-        // Get last (most recent) commit
-        let local_last_commit = local_git_repo::get_last_commit(&repo).unwrap();
+            // Get last (most recent) commit
+            let local_last_commit = local_git_repo::get_last_commit(&repo).unwrap();
 
-        log::info!("Checking local repo status ({})", &repo.path().display());
+            log::info!("Checking local repo status ({})", &repo.path().display());
 
-        // Check if all changes in local repo has been commited locally
-        if !local_git_repo::untracked_changed_local_files(&repo).unwrap() {
-            if Confirm::with_theme(&ColorfulTheme::default())
-                .with_prompt("Your local repo has changes not commited. Do you want to continue?")
-                .interact()
-                .unwrap()
-            {
-                println!(
-                    "Continue. Checking commit id {} against remote",
-                    local_last_commit.id()
-                );
-            } else {
-                println!("Cancelled by user. Aborting.");
-                std::process::exit(0);
+            // Check if all changes in local repo has been commited locally
+            if !local_git_repo::untracked_changed_local_files(&repo).unwrap() {
+                if Confirm::with_theme(&ColorfulTheme::default())
+                    .with_prompt(
+                        "Your local repo has changes not commited. Do you want to continue?",
+                    )
+                    .interact()
+                    .unwrap()
+                {
+                    println!(
+                        "Continue. Checking commit id {} against remote",
+                        local_last_commit.id()
+                    );
+                } else {
+                    println!("Cancelled by user. Aborting.");
+                    std::process::exit(0);
+                }
             }
-        }
 
-        // Check site.yml file exists inside repo folder
-        if !Path::new(repo.path()).exists() {
-            eprintln!(
-                "site.yaml file does not exists in {}",
-                repo.path().display()
-            );
-            std::process::exit(1);
-        }
+            // Check playbook file exists inside repo folder
+            if !Path::new(&layer_spec.repo).join(playbook).exists() {
+                eprintln!("{} file does not exists in {}", playbook, layer_spec.repo);
+                std::process::exit(1);
+            }
 
-        // Get repo name
-        let repo_ref_origin = repo.find_remote("origin").unwrap();
+            let timestamp = local_last_commit.time().seconds();
+            let tm = chrono::NaiveDateTime::from_timestamp_opt(timestamp, 0).unwrap();
+            log::debug!("\n\nCommit details to apply to CFS layer:\nCommit  {}\nAuthor: {}\nDate:   {}\n\n    {}\n", local_last_commit.id(), local_last_commit.author(), tm, local_last_commit.message().unwrap_or("no commit message"));
 
-        log::info!("Repo ref origin URL: {}", repo_ref_origin.url().unwrap());
+            Some(local_git_repo::untracked_changed_local_files(&repo).unwrap())
+        } else {
+            None
+        };
 
-        let repo_ref_origin_url = repo_ref_origin.url().unwrap();
+        let layer = match cfs_layer_ops::get_cfs_layer(
+            gitea_token,
+            gitea_base_url,
+            shasta_root_cert,
+            layer_spec,
+        )
+        .await
+        {
+            Ok(layer) => layer,
+            Err(error) => {
+                eprintln!("{}. Exit", error);
+                std::process::exit(1);
+            }
+        };
 
-        let repo_name = repo_ref_origin_url.substring(
-            repo_ref_origin_url.rfind(|c| c == '/').unwrap() + 1, // repo name should not include URI '/' separator
-            repo_ref_origin_url.len(), // repo_ref_origin_url.rfind(|c| c == '.').unwrap(),
+        let mut layer_summary = format!(
+            " - Layer-{}; repo name: {}; playbook: {}; commit: {}",
+            i,
+            gitea::get_owner_and_repo_name(layer["cloneUrl"].as_str().unwrap_or_default()).1,
+            layer["playbook"].as_str().unwrap_or_default(),
+            layer["commit"].as_str().unwrap_or_default(),
         );
 
-        let timestamp = local_last_commit.time().seconds();
-        let tm = chrono::NaiveDateTime::from_timestamp_opt(timestamp, 0).unwrap();
-        log::debug!("\n\nCommit details to apply to CFS layer:\nCommit  {}\nAuthor: {}\nDate:   {}\n\n    {}\n", local_last_commit.id(), local_last_commit.author(), tm, local_last_commit.message().unwrap_or("no commit message"));
-
-        let layer_summary = vec![
-            i.to_string(),
-            repo_name.to_string(),
-            local_git_repo::untracked_changed_local_files(&repo)
-                .unwrap()
-                .to_string(),
-        ];
+        if let Some(local_changes_committed) = local_changes_committed_opt {
+            layer_summary.push_str(&format!(
+                "; local changes committed: {}",
+                local_changes_committed
+            ));
+        }
 
         layers_summary.push(layer_summary);
+        layer_vec.push(layer);
     }
 
     // Print CFS session/configuration layers summary on screen
     println!("Please review the following CFS layers:",);
     for layer_summary in layers_summary {
-        println!(
-            " - Layer-{}; repo name: {}; local changes committed: {}",
-            layer_summary[0], layer_summary[1], layer_summary[2]
-        );
+        println!("{}", layer_summary);
     }
 
     if Confirm::with_theme(&ColorfulTheme::default())
@@ -439,14 +407,10 @@ pub async fn check_nodes_are_ready_to_run_cfs_configuration_and_run_cfs_session(
 
     log::info!("Creating CFS configuration {}", cfs_configuration_name);
 
-    let cfs_configuration = CfsConfigurationRequest::create_from_repos(
-        gitea_token,
-        gitea_base_url,
-        shasta_root_cert,
-        repos,
-        &cfs_configuration_name.to_string(),
-    )
-    .await;
+    let cfs_configuration = serde_json::from_value::<CfsConfigurationRequest>(json!({
+        "name": cfs_configuration_name,
+        "layers": layer_vec,
+    }))?;
 
     // Update/PUT CFS configuration
     log::debug!(
@@ -517,3 +481,74 @@ pub async fn check_nodes_are_ready_to_run_cfs_configuration_and_run_cfs_session(
 
     Ok(String::from(cfs_session_name))
 }
+
+/// Checks none of the nodes in the ansible limit (list of xnames comma separated) is already
+/// targeted by a running or pending CFS session, either through its ansible limit or its target
+/// groups. If 'wait_for_free_timeout_opt' is set, then it will wait up to that many seconds for
+/// the conflicting CFS sessions to finish, otherwise this method will exit if any node is busy
+pub async fn check_nodes_not_in_running_or_pending_cfs_session(
+    shasta_token: &str,
+    shasta_base_url: &str,
+    shasta_root_cert: &[u8],
+    limit: &str,
+    wait_for_free_timeout_opt: Option<u64>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // NOTE: nodes can be a list of xnames or hsm group name
+    let xname_vec: Vec<String> = limit
+        .split(',')
+        .map(|node| node.trim().to_string())
+        .filter(|node| !node.is_empty())
+        .collect();
+
+    let start = std::time::Instant::now();
+
+    loop {
+        let cfs_session_conflict_vec = cfs_session_utils::get_cfs_session_conflict_vec(
+            shasta_token,
+            shasta_base_url,
+            shasta_root_cert,
+            &xname_vec,
+        )
+        .await?;
+
+        if cfs_session_conflict_vec.is_empty() {
+            return Ok(());
+        }
+
+        // Report which CFS session blocks which nodes
+        for (cfs_session_name, xname_blocked_vec) in &cfs_session_conflict_vec {
+            eprintln!(
+                "CFS session {} running/pending blocks nodes: {}",
+                cfs_session_name,
+                xname_blocked_vec.join(", ")
+            );
+        }
+
+        let wait_for_free_timeout = match wait_for_free_timeout_opt {
+            Some(wait_for_free_timeout) => wait_for_free_timeout,
+            None => {
+                eprintln!("Nodes from the list provided are already assigned to a running/pending CFS session. Please try again latter or use '--wait-for-free'. Exitting");
+                std::process::exit(1);
+            }
+        };
+
+        let remaining_secs = wait_for_free_timeout.saturating_sub(start.elapsed().as_secs());
+
+        if remaining_secs == 0 {
+            eprintln!(
+                "Timeout. CFS sessions did not finish after {} seconds. Exitting",
+                wait_for_free_timeout
+            );
+            std::process::exit(1);
+        }
+
+        let check_interval_secs = remaining_secs.min(CFS_SESSION_CONFLICT_CHECK_INTERVAL_SECS);
+
+        println!(
+            "Waiting for CFS sessions to finish. Checking again in {} seconds",
+            check_interval_secs
+        );
+
+        tokio::time::sleep(std::time::Duration::from_secs(check_interval_secs)).await;
+    }
+}
@@ -0,0 +1,281 @@
+use k8s_openapi::chrono;
+use mesa::{
+    cfs::session::mesa::r#struct::CfsSessionPostRequest,
+    common::{kubernetes, vault::http_client::fetch_shasta_k8s_secrets},
+};
+
+use crate::common::jwt_ops::get_claims_from_jwt_token;
+
+use super::apply_session::check_nodes_not_in_running_or_pending_cfs_session;
+
+/// Creates a new CFS session based on an existing one. The new CFS session reuses the
+/// configuration, ansible limit, verbosity, passthrough and target of the original CFS session,
+/// any of these values can be overriden
+/// Returns a tuple like (<cfs configuration name>, <cfs session name>)
+pub async fn exec(
+    vault_base_url: &str,
+    vault_secret_path: &str,
+    vault_role_id: &str,
+    shasta_token: &str,
+    shasta_base_url: &str,
+    shasta_root_cert: &[u8],
+    k8s_api_url: &str,
+    hsm_group_name_vec: &[String],
+    cfs_session_name_from: &String,
+    cfs_session_name_opt: Option<&String>,
+    cfs_configuration_name_opt: Option<&String>,
+    ansible_limit_opt: Option<&String>,
+    ansible_verbosity_opt: Option<&String>,
+    ansible_passthrough_opt: Option<&String>,
+    failed_only: bool,
+    watch_logs: bool,
+    wait_for_free_timeout_opt: Option<u64>,
+) -> (String, String) {
+    // Get original CFS session
+    let mut cfs_session_vec = mesa::cfs::session::mesa::http_client::get(
+        shasta_token,
+        shasta_base_url,
+        shasta_root_cert,
+        Some(cfs_session_name_from),
+        None,
+    )
+    .await
+    .unwrap();
+
+    mesa::cfs::session::mesa::utils::filter_by_hsm(
+        shasta_token,
+        shasta_base_url,
+        shasta_root_cert,
+        &mut cfs_session_vec,
+        hsm_group_name_vec,
+        None,
+    )
+    .await;
+
+    let cfs_session_from = if let Some(cfs_session) = cfs_session_vec.first() {
+        serde_json::to_value(cfs_session).unwrap()
+    } else {
+        eprintln!("CFS session {} not found. Exit", cfs_session_name_from);
+        std::process::exit(1);
+    };
+
+    log::debug!("CFS session to rerun:\n{:#?}", cfs_session_from);
+
+    let is_target_definition_image = cfs_session_from
+        .pointer("/target/definition")
+        .and_then(|definition| definition.as_str())
+        .is_some_and(|definition| definition.eq("image"));
+
+    let target_group_name_vec: Vec<String> = cfs_session_from
+        .pointer("/target/groups")
+        .and_then(|groups| groups.as_array())
+        .unwrap_or(&Vec::new())
+        .iter()
+        .filter_map(|group| group["name"].as_str().map(str::to_string))
+        .collect();
+
+    // Override values from original CFS session
+    let cfs_configuration_name = cfs_configuration_name_opt.cloned().unwrap_or(
+        cfs_session_from
+            .pointer("/configuration/name")
+            .and_then(|name| name.as_str())
+            .unwrap_or_default()
+            .to_string(),
+    );
+
+    let mut ansible_limit_opt = ansible_limit_opt.cloned().or(cfs_session_from
+        .pointer("/ansible/limit")
+        .and_then(|limit| limit.as_str())
+        .filter(|limit| !limit.is_empty())
+        .map(str::to_string));
+
+    let ansible_verbosity_opt: Option<u8> = ansible_verbosity_opt
+        .and_then(|verbosity| verbosity.parse::<u8>().ok())
+        .or(cfs_session_from
+            .pointer("/ansible/verbosity")
+            .and_then(|verbosity| verbosity.as_u64())
+            .map(|verbosity| verbosity as u8));
+
+    let ansible_passthrough_opt = ansible_passthrough_opt.cloned().or(cfs_session_from
+        .pointer("/ansible/passthrough")
+        .and_then(|passthrough| passthrough.as_str())
+        .filter(|passthrough| !passthrough.is_empty())
+        .map(str::to_string));
+
+    // Nodes in the target groups, CFS sessions of target image have images as group members
+    let target_group_xname_vec: Vec<String> =
+        if is_target_definition_image || target_group_name_vec.is_empty() {
+            Vec::new()
+        } else {
+            mesa::hsm::group::shasta::utils::get_member_vec_from_hsm_name_vec(
+                shasta_token,
+                shasta_base_url,
+                shasta_root_cert,
+                &target_group_name_vec,
+            )
+            .await
+        };
+
+    let get_target_xname_vec = |ansible_limit_opt: &Option<String>| -> Vec<String> {
+        match ansible_limit_opt {
+            Some(ansible_limit) => ansible_limit
+                .split(',')
+                .map(|xname| xname.trim().to_string())
+                .collect(),
+            None => target_group_xname_vec.clone(),
+        }
+    };
+
+    if failed_only {
+        if is_target_definition_image {
+            eprintln!("'--failed-only' can't be used with CFS sessions of target image. Exit");
+            std::process::exit(1);
+        }
+
+        // Get nodes targeted by the original CFS session
+        let xname_vec = get_target_xname_vec(&ansible_limit_opt);
+
+        let mut failed_xname_vec = Vec::new();
+
+        for xname in xname_vec {
+            let component = mesa::cfs::component::shasta::http_client::get_single_component(
+                shasta_token,
+                shasta_base_url,
+                shasta_root_cert,
+                &xname,
+            )
+            .await
+            .unwrap();
+
+            if component["configurationStatus"].eq("failed") {
+                failed_xname_vec.push(xname);
+            }
+        }
+
+        if failed_xname_vec.is_empty() {
+            println!(
+                "No failed nodes found in CFS session {}. Nothing to do",
+                cfs_session_name_from
+            );
+            std::process::exit(0);
+        }
+
+        println!(
+            "Nodes with failed configuration status: {}",
+            failed_xname_vec.join(", ")
+        );
+
+        ansible_limit_opt = Some(failed_xname_vec.join(","));
+    }
+
+    // Check user has access to the nodes targeted by the new CFS session, either through the
+    // ansible limit or through the target groups
+    let target_xname_vec = get_target_xname_vec(&ansible_limit_opt);
+
+    if !target_xname_vec.is_empty() {
+        crate::cli::process::validate_target_hsm_members(
+            shasta_token,
+            shasta_base_url,
+            shasta_root_cert,
+            target_xname_vec.clone(),
+        )
+        .await;
+
+        if !is_target_definition_image {
+            check_nodes_not_in_running_or_pending_cfs_session(
+                shasta_token,
+                shasta_base_url,
+                shasta_root_cert,
+                &target_xname_vec.join(","),
+                wait_for_free_timeout_opt,
+            )
+            .await
+            .unwrap();
+        }
+    }
+
+    let cfs_session_name = cfs_session_name_opt.cloned().unwrap_or(format!(
+        "{}-{}",
+        cfs_configuration_name,
+        chrono::Utc::now().format("%Y%m%d%H%M%S")
+    ));
+
+    let session = if is_target_definition_image {
+        let base_image_id = cfs_session_from
+            .pointer("/target/groups/0/members/0")
+            .and_then(|image_id| image_id.as_str())
+            .unwrap_or_default()
+            .to_string();
+
+        CfsSessionPostRequest::new(
+            cfs_session_name,
+            cfs_configuration_name.clone(),
+            ansible_limit_opt,
+            ansible_verbosity_opt,
+            ansible_passthrough_opt,
+            true,
+            Some(target_group_name_vec),
+            Some(base_image_id),
+        )
+    } else {
+        CfsSessionPostRequest::new(
+            cfs_session_name,
+            cfs_configuration_name.clone(),
+            ansible_limit_opt,
+            ansible_verbosity_opt,
+            ansible_passthrough_opt,
+            false,
+            if target_group_name_vec.is_empty() {
+                None
+            } else {
+                Some(target_group_name_vec)
+            },
+            None,
+        )
+    };
+
+    log::debug!("Create CFS Session payload:\n{:#?}", session);
+
+    let cfs_session_resp = mesa::cfs::session::mesa::http_client::post(
+        shasta_token,
+        shasta_base_url,
+        shasta_root_cert,
+        &session,
+    )
+    .await;
+
+    log::debug!("Create CFS Session response:\n{:#?}", cfs_session_resp);
+
+    let cfs_session_name = match cfs_session_resp {
+        Ok(cfs_session) => cfs_session.name.unwrap(),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    println!(
+        "CFS session {} created from {}",
+        cfs_session_name, cfs_session_name_from
+    );
+
+    if watch_logs {
+        log::info!("Fetching logs ...");
+
+        let shasta_k8s_secrets =
+            fetch_shasta_k8s_secrets(vault_base_url, vault_secret_path, vault_role_id).await;
+
+        let client = kubernetes::get_k8s_client_programmatically(k8s_api_url, shasta_k8s_secrets)
+            .await
+            .unwrap();
+
+        kubernetes::print_cfs_session_logs(client, &cfs_session_name).await;
+    }
+
+    // Audit
+    let jwt_claims = get_claims_from_jwt_token(shasta_token).unwrap();
+
+    log::info!(target: "app::audit", "User: {} ({}) ; Operation: Apply session {} from {}", jwt_claims["name"].as_str().unwrap(), jwt_claims["preferred_username"].as_str().unwrap(), cfs_session_name, cfs_session_name_from);
+
+    (cfs_configuration_name, cfs_session_name)
+}
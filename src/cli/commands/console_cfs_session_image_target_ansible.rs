@@ -1,11 +1,31 @@
+use std::{collections::HashMap, io::IsTerminal};
+
 use futures::StreamExt;
 
-use mesa::node::console;
+use k8s_openapi::api::core::v1::Pod;
+use kube::{
+    api::{AttachParams, AttachedProcess, ListParams, LogParams},
+    Api,
+};
+use mesa::{
+    cfs::session::mesa::r#struct::CfsSessionGetResponse, common::kubernetes, node::console,
+};
 use termion::color;
 use tokio::{io::AsyncWriteExt, select};
 
-use crate::common::terminal_ops;
+use crate::common::{
+    cfs_session_utils,
+    console_escape::{self, ConsoleEscape},
+    terminal_ops,
+    vault::http_client::fetch_shasta_k8s_secrets,
+};
+
+const CFS_SESSION_NAMESPACE: &str = "services";
+const ANSIBLE_CONTAINER: &str = "ansible";
 
+/// Connects to the ansible container of a running CFS session. CFS sessions target image are
+/// interactive, CFS sessions target dynamic configure live nodes so the ansible output is followed
+/// read-only unless 'interactive' is true, then a shell is opened in the ansible container
 pub async fn exec(
     hsm_group_name_vec: &Vec<String>,
     shasta_token: &str,
@@ -16,6 +36,8 @@ pub async fn exec(
     vault_role_id: &str,
     k8s_api_url: &str,
     cfs_session_name: &str,
+    escape: u8,
+    interactive: bool,
 ) {
     let mut cfs_session_value_vec = mesa::cfs::session::mesa::http_client::get(
         shasta_token,
@@ -42,21 +64,14 @@ pub async fn exec(
         std::process::exit(1);
     }
     let cfs_session_details = cfs_session_value_vec.first().unwrap();
-    if cfs_session_details
+    let is_target_image = cfs_session_details
         .target
         .as_ref()
         .unwrap()
         .definition
         .as_ref()
         .unwrap()
-        .ne("image")
-    {
-        eprintln!(
-            "CFS session found {} is type dynamic. Exit",
-            cfs_session_details.name.as_ref().unwrap()
-        );
-        std::process::exit(1);
-    }
+        .eq("image");
     if cfs_session_details
         .status
         .as_ref()
@@ -73,53 +88,248 @@ pub async fn exec(
         );
         std::process::exit(1);
     }
-    if !cfs_session_details
+    let target_group_name_vec: Vec<String> = cfs_session_details
         .target
         .as_ref()
-        .unwrap()
-        .groups
+        .and_then(|target| target.groups.as_ref())
+        .map(|group_vec| {
+            group_vec
+                .iter()
+                .map(|group| group.name.to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+    let is_ansible_limit_set = cfs_session_details
+        .ansible
         .as_ref()
-        .unwrap()
-        .iter()
-        .any(|group| hsm_group_name_vec.contains(&group.name.to_string()))
-    {
+        .and_then(|ansible| ansible.limit.as_ref())
+        .is_some_and(|limit| !limit.trim().is_empty());
+    // CFS sessions target image need one of their target groups available. CFS sessions target
+    // dynamic may give shell access to the nodes, so all target groups and all nodes in ansible
+    // limit must belong to the available HSM groups
+    let is_cfs_session_available = if is_target_image {
+        target_group_name_vec
+            .iter()
+            .any(|group_name| hsm_group_name_vec.contains(group_name))
+    } else {
+        let is_target_group_available = target_group_name_vec
+            .iter()
+            .all(|group_name| hsm_group_name_vec.contains(group_name));
+
+        if is_ansible_limit_set || target_group_name_vec.is_empty() {
+            is_target_group_available
+                && is_ansible_limit_available(
+                    shasta_token,
+                    shasta_base_url,
+                    shasta_root_cert,
+                    cfs_session_details,
+                    hsm_group_name_vec,
+                )
+                .await
+        } else {
+            is_target_group_available
+        }
+    };
+    if !is_cfs_session_available {
         eprintln!(
-            "CFS session found {} is not related to any availble HSM groups {:?}",
+            "CFS session found {} targets nodes or groups not in the available HSM groups {:?}. Exit",
             cfs_session_details.name.as_ref().unwrap(),
             hsm_group_name_vec
         );
         std::process::exit(1);
     }
 
-    connect_to_console(
-        &cfs_session_name.to_string(),
-        vault_base_url,
-        vault_secret_path,
-        vault_role_id,
-        k8s_api_url,
+    if (is_target_image || interactive) && !std::io::stdout().is_terminal() {
+        eprintln!("This command needs to run in interactive mode. Exit");
+        std::process::exit(1);
+    }
+
+    if is_target_image {
+        let attached = console::get_container_attachment_to_cfs_session_image_target(
+            &cfs_session_name.to_string(),
+            vault_base_url,
+            vault_secret_path,
+            vault_role_id,
+            k8s_api_url,
+        )
+        .await;
+
+        connect_to_console(
+            &cfs_session_name.to_string(),
+            attached,
+            // Ansible target container is a shell, there is no break to send
+            ConsoleEscape::new(escape, &[escape], None),
+        )
+        .await
+        .unwrap();
+
+        return;
+    }
+
+    let shasta_k8s_secrets =
+        fetch_shasta_k8s_secrets(vault_base_url, vault_secret_path, vault_role_id).await;
+
+    let client = kubernetes::get_k8s_client_programmatically(k8s_api_url, shasta_k8s_secrets)
+        .await
+        .unwrap();
+
+    if interactive {
+        let attached = match get_ansible_container_shell(client, cfs_session_name).await {
+            Ok(attached) => attached,
+            Err(error) => {
+                eprintln!(
+                    "ERROR - Could not open a shell in the ansible container of CFS session {}. Reason:\n{}\nExit",
+                    cfs_session_name, error
+                );
+                std::process::exit(1);
+            }
+        };
+
+        connect_to_console(
+            &cfs_session_name.to_string(),
+            attached,
+            ConsoleEscape::new(escape, &[escape], None),
+        )
+        .await
+        .unwrap();
+    } else if let Err(error) = follow_ansible_container_output(client, cfs_session_name).await {
+        eprintln!(
+            "ERROR - Could not follow the ansible container of CFS session {}. Reason:\n{}\nExit",
+            cfs_session_name, error
+        );
+        std::process::exit(1);
+    }
+}
+
+/// Returns true if the CFS session ansible limit only contains nodes and HSM groups within the
+/// HSM groups available to the user
+async fn is_ansible_limit_available(
+    shasta_token: &str,
+    shasta_base_url: &str,
+    shasta_root_cert: &[u8],
+    cfs_session: &CfsSessionGetResponse,
+    hsm_group_name_vec: &[String],
+) -> bool {
+    // A CFS session target dynamic without ansible limit targets all nodes
+    let xname_vec_rslt = cfs_session_utils::get_xname_vec_targeted_by_cfs_session(
+        shasta_token,
+        shasta_base_url,
+        shasta_root_cert,
+        cfs_session,
+        &mut HashMap::new(),
     )
-    .await
-    .unwrap();
+    .await;
+
+    let xname_vec = match xname_vec_rslt {
+        Ok(Some(xname_vec)) => xname_vec,
+        Ok(None) => return false,
+        Err(error) => {
+            log::warn!("{}", error);
+            return false;
+        }
+    };
+
+    let hsm_member_vec = mesa::hsm::group::shasta::utils::get_member_vec_from_hsm_name_vec(
+        shasta_token,
+        shasta_base_url,
+        shasta_root_cert,
+        hsm_group_name_vec,
+    )
+    .await;
+
+    !xname_vec.is_empty() && xname_vec.iter().all(|xname| hsm_member_vec.contains(xname))
+}
+
+/// Returns the pod running a CFS session
+async fn get_cfs_session_pod_name(
+    client: kube::Client,
+    cfs_session_name: &str,
+) -> Result<String, anyhow::Error> {
+    let pod_api: Api<Pod> = Api::namespaced(client, CFS_SESSION_NAMESPACE);
+
+    pod_api
+        .list(&ListParams::default().labels(&format!("cfsession={}", cfs_session_name)))
+        .await?
+        .items
+        .first()
+        .and_then(|pod| pod.metadata.name.clone())
+        .ok_or_else(|| anyhow::anyhow!("Pod for CFS session {} not found", cfs_session_name))
+}
+
+/// Prints the ansible container output, from the beginning of the CFS session, until the
+/// container finishes. Nothing is sent to the container
+async fn follow_ansible_container_output(
+    client: kube::Client,
+    cfs_session_name: &str,
+) -> Result<(), anyhow::Error> {
+    let pod_name = get_cfs_session_pod_name(client.clone(), cfs_session_name).await?;
+
+    log::info!("Following ansible container in pod {}", pod_name);
+
+    let pod_api: Api<Pod> = Api::namespaced(client, CFS_SESSION_NAMESPACE);
+
+    let mut log_stream = pod_api
+        .log_stream(
+            &pod_name,
+            &LogParams {
+                container: Some(ANSIBLE_CONTAINER.to_string()),
+                follow: true,
+                ..Default::default()
+            },
+        )
+        .await?;
+
+    println!(
+        "Following {}{}{} read-only. Press Ctrl-C to exit or use '--interactive' to open a shell",
+        color::Fg(color::Blue),
+        cfs_session_name,
+        color::Fg(color::Reset)
+    );
+
+    let mut stdout = tokio::io::stdout();
+
+    while let Some(message) = log_stream.next().await {
+        stdout.write_all(&message?).await?;
+        stdout.flush().await?;
+    }
+
+    Ok(())
+}
+
+/// Opens a shell in the ansible container of a CFS session
+async fn get_ansible_container_shell(
+    client: kube::Client,
+    cfs_session_name: &str,
+) -> Result<AttachedProcess, anyhow::Error> {
+    let pod_name = get_cfs_session_pod_name(client.clone(), cfs_session_name).await?;
+
+    log::info!("Opening shell in ansible container in pod {}", pod_name);
+
+    let pod_api: Api<Pod> = Api::namespaced(client, CFS_SESSION_NAMESPACE);
+
+    let attached = pod_api
+        .exec(
+            &pod_name,
+            vec!["sh"],
+            &AttachParams::default()
+                .container(ANSIBLE_CONTAINER)
+                .stdin(true)
+                .stdout(true)
+                .stderr(false)
+                .tty(true),
+        )
+        .await?;
+
+    Ok(attached)
 }
 
 pub async fn connect_to_console(
     cfs_session_name: &String,
-    vault_base_url: &str,
-    vault_secret_path: &str,
-    vault_role_id: &str,
-    k8s_api_url: &str,
+    mut attached: AttachedProcess,
+    mut console_escape: ConsoleEscape,
 ) -> Result<(), anyhow::Error> {
     log::info!("CFS session name: {}", cfs_session_name);
 
-    let mut attached = console::get_container_attachment_to_cfs_session_image_target(
-        cfs_session_name,
-        vault_base_url,
-        vault_secret_path,
-        vault_role_id,
-        k8s_api_url,
-    )
-    .await;
-
     println!(
         "Connected to {}{}{}!",
         color::Fg(color::Blue),
@@ -127,8 +337,12 @@ pub async fn connect_to_console(
         color::Fg(color::Reset)
     );
     println!(
-        "Use {}&.{} key combination to exit the console.",
+        "Use {}{}.{} key combination to exit the console or {}{}?{} for help.",
         color::Fg(color::Green),
+        console_escape.escape_str(),
+        color::Fg(color::Reset),
+        color::Fg(color::Green),
+        console_escape.escape_str(),
         color::Fg(color::Reset)
     );
 
@@ -141,22 +355,30 @@ pub async fn connect_to_console(
     let term_tx = attached.terminal_size().unwrap();
 
     let mut handle_terminal_size_handle = tokio::spawn(terminal_ops::handle_terminal_size(term_tx));
+    let mut terminal_size_stream_closed = false;
+
+    let termination_signal = terminal_ops::wait_for_termination_signal();
+    tokio::pin!(termination_signal);
 
-    crossterm::terminal::enable_raw_mode()?;
+    // Terminal is restored when the guard goes out of scope, also on errors
+    let _raw_mode_guard = terminal_ops::RawModeGuard::new()?;
 
     loop {
         select! {
             message = stdin.next() => {
                 match message {
                     Some(Ok(message)) => {
-                        input.write_all(&message).await?;
+                        if console_escape::forward_input(&mut console_escape, &message, &mut input, &mut stdout).await? {
+                            log::info!("Detached from console");
+                            break
+                        }
                     },
                     Some(Err(message)) => {
-                       input.write_all(format!("#*#* stdin {:?}", &message).as_bytes()).await?;
+                       log::error!("ERROR: Console stdin {:?}", &message);
                        break
                     },
                     None => {
-                        input.write_all("stdin None".as_bytes()).await?;
+                        log::info!("NONE (No input): Console stdin");
                         break
                     },
                 }
@@ -168,26 +390,30 @@ pub async fn connect_to_console(
                         stdout.flush().await?;
                     },
                     Some(Err(message)) => {
-                       input.write_all(format!("#*#* stdout {:?}", &message).as_bytes()).await?;
+                       log::error!("ERROR: Console stdout: {:?}", &message);
                        break
                     },
                     None => {
-                        input.write_all("stdout None".as_bytes()).await?;
+                        log::info!("Exit console");
                         break
                     },
                 }
             },
-            result = &mut handle_terminal_size_handle => {
+            result = &mut handle_terminal_size_handle, if !terminal_size_stream_closed => {
+                terminal_size_stream_closed = true;
+
                 match result {
-                    Ok(_) => crossterm::terminal::disable_raw_mode()?,
-                    Err(e) => { crossterm::terminal::disable_raw_mode()?; println!("Error getting terminal size: {e:?}") }
+                    Ok(_) => log::info!("End of terminal size stream"),
+                    Err(e) => log::error!("Error getting terminal size: {e:?}"),
                 }
             },
+            _ = &mut termination_signal => {
+                log::info!("Console closed by signal");
+                break
+            },
         };
     }
 
-    crossterm::terminal::disable_raw_mode()?;
-
     Ok(())
 
     /* let mut stdin_writer = attached.stdin().unwrap();
@@ -0,0 +1,100 @@
+use crate::common::{conman, vault::http_client::fetch_shasta_k8s_secrets};
+
+/// Prints the console log conman keeps for a node. The log is read from the cray-console-node
+/// pod the console operator assigned the node to
+pub async fn exec(
+    vault_base_url: &str,
+    vault_secret_path: &str,
+    vault_role_id: &str,
+    k8s_api_url: &str,
+    xname: &str,
+    since_opt: Option<&String>,
+    tail_opt: Option<&usize>,
+    grep_opt: Option<&String>,
+) {
+    let since_opt =
+        since_opt.map(
+            |since| match conman::parse_since(since, chrono::Utc::now().naive_utc()) {
+                Ok(since) => since,
+                Err(error) => {
+                    eprintln!("{}. Exit", error);
+                    std::process::exit(1);
+                }
+            },
+        );
+
+    let shasta_k8s_secrets =
+        fetch_shasta_k8s_secrets(vault_base_url, vault_secret_path, vault_role_id).await;
+
+    let client = match mesa::common::kubernetes::get_k8s_client_programmatically(
+        k8s_api_url,
+        shasta_k8s_secrets,
+    )
+    .await
+    {
+        Ok(client) => client,
+        Err(error) => {
+            eprintln!("Could not connect to k8s: {}. Exit", error);
+            std::process::exit(1);
+        }
+    };
+
+    let console_node_pod_name = match conman::get_console_node_pod_name(client.clone(), xname).await
+    {
+        Ok(console_node_pod_name) => console_node_pod_name,
+        Err(error) => {
+            eprintln!("ERROR - {}. Exit", error);
+            std::process::exit(1);
+        }
+    };
+
+    log::info!(
+        "Console of {} is managed by pod {}",
+        xname,
+        console_node_pod_name
+    );
+
+    let first_line_number_opt = if let Some(since) = since_opt {
+        match conman::get_first_line_number_since(
+            client.clone(),
+            &console_node_pod_name,
+            xname,
+            since,
+        )
+        .await
+        {
+            Ok(first_line_number) => Some(first_line_number),
+            Err(error) => {
+                eprintln!(
+                    "ERROR - Could not read console log of {} in pod {}. Reason:\n{}\nExit",
+                    xname, console_node_pod_name, error
+                );
+                std::process::exit(1);
+            }
+        }
+    } else {
+        None
+    };
+
+    let console_log = match conman::get_console_log(
+        client,
+        &console_node_pod_name,
+        xname,
+        first_line_number_opt,
+        grep_opt.map(String::as_str),
+        tail_opt.copied(),
+    )
+    .await
+    {
+        Ok(console_log) => console_log,
+        Err(error) => {
+            eprintln!(
+                "ERROR - Could not read console log of {} in pod {}. Reason:\n{}\nExit",
+                xname, console_node_pod_name, error
+            );
+            std::process::exit(1);
+        }
+    };
+
+    print!("{}", console_log);
+}
@@ -1,10 +1,17 @@
+use std::path::Path;
+
 use futures::StreamExt;
 
 use mesa::node::{self, console};
 use termion::color;
 use tokio::{io::AsyncWriteExt, select};
 
-use crate::common::terminal_ops;
+use crate::common::{
+    console_escape::{self, ConsoleEscape},
+    console_recording::ConsoleRecorder,
+    console_script::{ConsoleScript, ConsoleStep, CONSOLE_EXPECT_TIMEOUT_EXIT_CODE},
+    terminal_ops,
+};
 
 pub async fn exec(
     hsm_group: Option<&String>,
@@ -16,6 +23,9 @@ pub async fn exec(
     vault_role_id: &str,
     k8s_api_url: &str,
     xname: &str,
+    record_file_path_opt: Option<&Path>,
+    console_script_opt: Option<&ConsoleScript>,
+    escape: u8,
 ) {
     if hsm_group.is_some() {
         // Check user has provided valid XNAMES
@@ -37,6 +47,49 @@ pub async fn exec(
         node::utils::validate_xname_format(xname);
     }
 
+    let recorder_opt = record_file_path_opt.map(|record_file_path| {
+        let (width, height) = crossterm::terminal::size().unwrap_or((80, 24));
+
+        match ConsoleRecorder::create(
+            record_file_path,
+            &format!("manta console {}", xname),
+            width,
+            height,
+        ) {
+            Ok(recorder) => {
+                println!("Recording console to {}", record_file_path.display());
+                recorder
+            }
+            Err(error) => {
+                eprintln!(
+                    "Could not create recording file {}: {}. Exit",
+                    record_file_path.display(),
+                    error
+                );
+                std::process::exit(1);
+            }
+        }
+    });
+
+    if let Some(console_script) = console_script_opt {
+        if let Err(error) = run_console_script(
+            &xname.to_string(),
+            vault_base_url,
+            vault_secret_path,
+            vault_role_id,
+            k8s_api_url,
+            console_script,
+            recorder_opt,
+        )
+        .await
+        {
+            eprintln!("ERROR - Console script failed. Reason:\n{}\nExit", error);
+            std::process::exit(1);
+        }
+
+        return;
+    }
+
     let console_rslt = connect_to_console(
         // included.iter().next().unwrap(),
         &xname.to_string(),
@@ -44,19 +97,112 @@ pub async fn exec(
         vault_secret_path,
         vault_role_id,
         k8s_api_url,
+        recorder_opt,
+        ConsoleEscape::for_conman(escape),
     )
     .await;
 
     match console_rslt {
-        Ok(_) => {
-            crossterm::terminal::disable_raw_mode().unwrap();
-            log::info!("Console closed");
-        }
-        Err(error) => {
-            crossterm::terminal::disable_raw_mode().unwrap();
-            log::error!("{:?}", error);
+        Ok(_) => log::info!("Console closed"),
+        Err(error) => log::error!("{:?}", error),
+    }
+}
+
+/// Sends text to the console and waits for patterns in the console output, in the order
+/// provided by the user. Works without raw mode and without a terminal so it can be used in
+/// scripts. Exits with 'CONSOLE_EXPECT_TIMEOUT_EXIT_CODE' if a pattern does not show up in time
+pub async fn run_console_script(
+    xname: &String,
+    vault_base_url: &str,
+    vault_secret_path: &str,
+    vault_role_id: &str,
+    k8s_api_url: &str,
+    console_script: &ConsoleScript,
+    mut recorder_opt: Option<ConsoleRecorder>,
+) -> Result<(), anyhow::Error> {
+    log::info!("xname: {}", xname);
+
+    let mut attached = console::get_container_attachment_to_conman(
+        xname,
+        vault_base_url,
+        vault_secret_path,
+        vault_role_id,
+        k8s_api_url,
+    )
+    .await;
+
+    let mut stdout = tokio::io::stdout();
+
+    let mut output = tokio_util::io::ReaderStream::new(attached.stdout().unwrap());
+    let mut input = attached.stdin().unwrap();
+
+    // Console output not matched yet by an expected pattern
+    let mut output_pending = String::new();
+
+    let ansi_escape_regex = terminal_ops::get_ansi_escape_regex();
+
+    for step in &console_script.step_vec {
+        match step {
+            ConsoleStep::Send(text) => {
+                log::info!(
+                    "Sending {:?} to console {}",
+                    String::from_utf8_lossy(text),
+                    xname
+                );
+                input.write_all(text).await?;
+                input.flush().await?;
+            }
+            ConsoleStep::Expect(expect_regex) => {
+                log::info!("Waiting for '{}' in console {}", expect_regex, xname);
+
+                let deadline = tokio::time::Instant::now()
+                    + std::time::Duration::from_secs(console_script.timeout_secs);
+
+                loop {
+                    if let Some(expect_match) = expect_regex.find(&output_pending) {
+                        output_pending.drain(..expect_match.end());
+                        break;
+                    }
+
+                    select! {
+                        message = output.next() => {
+                            match message {
+                                Some(Ok(message)) => {
+                                    stdout.write_all(&message).await?;
+                                    stdout.flush().await?;
+
+                                    if let Some(recorder) = recorder_opt.as_mut() {
+                                        recorder.record(&message)?;
+                                    }
+
+                                    output_pending.push_str(&terminal_ops::strip_ansi_escape_codes(
+                                        &ansi_escape_regex,
+                                        &String::from_utf8_lossy(&message),
+                                    ));
+                                }
+                                Some(Err(error)) => return Err(error.into()),
+                                None => {
+                                    return Err(anyhow::anyhow!(
+                                        "Console closed while waiting for '{}'",
+                                        expect_regex
+                                    ))
+                                }
+                            }
+                        },
+                        _ = tokio::time::sleep_until(deadline) => {
+                            eprintln!(
+                                "\nTimeout after {} seconds waiting for '{}' in console {}. Exit",
+                                console_script.timeout_secs, expect_regex, xname
+                            );
+                            std::process::exit(CONSOLE_EXPECT_TIMEOUT_EXIT_CODE);
+                        },
+                    }
+                }
+            }
         }
     }
+
+    Ok(())
 }
 
 pub async fn connect_to_console(
@@ -65,6 +211,8 @@ pub async fn connect_to_console(
     vault_secret_path: &str,
     vault_role_id: &str,
     k8s_api_url: &str,
+    mut recorder_opt: Option<ConsoleRecorder>,
+    mut console_escape: ConsoleEscape,
 ) -> Result<(), anyhow::Error> {
     log::info!("xname: {}", xname);
 
@@ -84,14 +232,15 @@ pub async fn connect_to_console(
         color::Fg(color::Reset)
     );
     println!(
-        "Use {}&.{} key combination to exit the console.",
+        "Use {}{}.{} key combination to exit the console or {}{}?{} for help.",
         color::Fg(color::Green),
+        console_escape.escape_str(),
+        color::Fg(color::Reset),
+        color::Fg(color::Green),
+        console_escape.escape_str(),
         color::Fg(color::Reset)
     );
 
-    println!("Connected to {}!", xname,);
-    println!("Use &. key combination to exit the console.",);
-
     let mut stdin = tokio_util::io::ReaderStream::new(tokio::io::stdin());
     let mut stdout = tokio::io::stdout();
 
@@ -101,23 +250,29 @@ pub async fn connect_to_console(
     let term_tx = attached.terminal_size().unwrap();
 
     let mut handle_terminal_size_handle = tokio::spawn(terminal_ops::handle_terminal_size(term_tx));
+    let mut terminal_size_stream_closed = false;
 
-    crossterm::terminal::enable_raw_mode()?;
+    let termination_signal = terminal_ops::wait_for_termination_signal();
+    tokio::pin!(termination_signal);
+
+    // Terminal is restored when the guard goes out of scope, also on errors
+    let _raw_mode_guard = terminal_ops::RawModeGuard::new()?;
 
     loop {
         select! {
             message = stdin.next() => {
                 match message {
                     Some(Ok(message)) => {
-                        input.write_all(&message).await?;
+                        if console_escape::forward_input(&mut console_escape, &message, &mut input, &mut stdout).await? {
+                            log::info!("Detached from console");
+                            break
+                        }
                     },
                     Some(Err(message)) => {
-                       crossterm::terminal::disable_raw_mode()?;
                        log::error!("ERROR: Console stdin {:?}", &message);
                        break
                     },
                     None => {
-                        crossterm::terminal::disable_raw_mode()?;
                         log::info!("NONE (No input): Console stdin");
                         break
                     },
@@ -128,33 +283,40 @@ pub async fn connect_to_console(
                     Some(Ok(message)) => {
                         stdout.write_all(&message).await?;
                         stdout.flush().await?;
+
+                        if let Some(recorder) = recorder_opt.as_mut() {
+                            if let Err(error) = recorder.record(&message) {
+                                // Keep the console open even if the recording fails
+                                log::error!("Could not record console output: {:?}", error);
+                                recorder_opt = None;
+                            }
+                        }
                     },
                     Some(Err(message)) => {
-                       crossterm::terminal::disable_raw_mode()?;
                        log::error!("ERROR: Console stdout: {:?}", &message);
                        break
                     },
                     None => {
-                        crossterm::terminal::disable_raw_mode()?;
                         log::info!("Exit console");
                         break
                     },
                 }
             },
-            result = &mut handle_terminal_size_handle => {
+            result = &mut handle_terminal_size_handle, if !terminal_size_stream_closed => {
+                terminal_size_stream_closed = true;
+
                 match result {
                     Ok(_) => log::info!("End of terminal size stream"),
-                    Err(e) => {
-                        crossterm::terminal::disable_raw_mode()?;
-                        log::error!("Error getting terminal size: {e:?}")
-                    }
+                    Err(e) => log::error!("Error getting terminal size: {e:?}"),
                 }
             },
+            _ = &mut termination_signal => {
+                log::info!("Console closed by signal");
+                break
+            },
         };
     }
 
-    crossterm::terminal::disable_raw_mode()?;
-
     Ok(())
 
     /* let mut stdin_writer = attached.stdin().unwrap();
@@ -0,0 +1,62 @@
+use std::{io::Write, path::Path, time::Duration};
+
+use crate::common::console_recording;
+
+/// Prints a console recording back at its original pace divided by 'speed'. Pauses longer than
+/// 'max_idle_secs_opt' are shortened
+pub async fn exec(recording_path: &Path, speed: f64, max_idle_secs_opt: Option<f64>) {
+    if !speed.is_finite() || speed <= 0.0 {
+        eprintln!("Speed must be a number greater than 0. Exit");
+        std::process::exit(1);
+    }
+
+    if max_idle_secs_opt
+        .is_some_and(|max_idle_secs| !max_idle_secs.is_finite() || max_idle_secs < 0.0)
+    {
+        eprintln!("Max idle must be a number of seconds equal or greater than 0. Exit");
+        std::process::exit(1);
+    }
+
+    let recording = match std::fs::read_to_string(recording_path) {
+        Ok(recording) => recording,
+        Err(error) => {
+            eprintln!(
+                "Could not read recording {}: {}. Exit",
+                recording_path.display(),
+                error
+            );
+            std::process::exit(1);
+        }
+    };
+
+    let event_vec = match console_recording::parse_recording(&recording) {
+        Ok(event_vec) => event_vec,
+        Err(error) => {
+            eprintln!(
+                "Recording {} not valid: {}. Exit",
+                recording_path.display(),
+                error
+            );
+            std::process::exit(1);
+        }
+    };
+
+    let mut stdout = std::io::stdout();
+    let mut previous_elapsed_secs = 0.0;
+
+    for (elapsed_secs, data) in event_vec {
+        let mut delay_secs = (elapsed_secs - previous_elapsed_secs).max(0.0) / speed;
+
+        if let Some(max_idle_secs) = max_idle_secs_opt {
+            delay_secs = delay_secs.min(max_idle_secs);
+        }
+
+        // Recording timestamps are not trusted, a delay that does not fit a Duration is skipped
+        tokio::time::sleep(Duration::try_from_secs_f64(delay_secs).unwrap_or_default()).await;
+
+        print!("{}", data);
+        stdout.flush().unwrap();
+
+        previous_elapsed_secs = elapsed_secs;
+    }
+}
@@ -0,0 +1,136 @@
+use std::collections::HashSet;
+
+use crossterm::style::{Color, Stylize};
+use futures::{future, stream, StreamExt};
+use mesa::node::console;
+use regex::Regex;
+
+use crate::common::terminal_ops::{self, LineBuffer};
+
+const XNAME_COLOR_VEC: [Color; 6] = [
+    Color::Cyan,
+    Color::Green,
+    Color::Yellow,
+    Color::Magenta,
+    Color::Blue,
+    Color::Red,
+];
+
+/// Attaches read-only to the console of many nodes and prints their output line by line, each
+/// line prefixed with the node xname. If 'until_opt' is provided, then it stops once the pattern
+/// shows up on the console of every node
+pub async fn exec(
+    vault_base_url: &str,
+    vault_secret_path: &str,
+    vault_role_id: &str,
+    k8s_api_url: &str,
+    xname_vec: &[String],
+    until_opt: Option<&String>,
+) {
+    let until_regex_opt = until_opt.map(|until| match Regex::new(until) {
+        Ok(until_regex) => until_regex,
+        Err(error) => {
+            eprintln!("Pattern '{}' not valid: {}. Exit", until, error);
+            std::process::exit(1);
+        }
+    });
+
+    // Attach to all consoles at once, otherwise we would miss the output of the first nodes
+    // while attaching to the others
+    let mut attached_vec = future::join_all(xname_vec.iter().map(|xname| {
+        console::get_container_attachment_to_conman(
+            xname,
+            vault_base_url,
+            vault_secret_path,
+            vault_role_id,
+            k8s_api_url,
+        )
+    }))
+    .await;
+
+    // Console input is never used so the nodes can't be modified
+    let mut output = stream::select_all(attached_vec.iter_mut().enumerate().map(
+        |(index, attached)| {
+            tokio_util::io::ReaderStream::new(attached.stdout().unwrap())
+                .map(move |message| (index, message))
+        },
+    ));
+
+    println!(
+        "Tailing console of {} nodes. Press Ctrl-C to exit",
+        xname_vec.len()
+    );
+
+    let mut line_buffer_vec: Vec<LineBuffer> =
+        xname_vec.iter().map(|_| LineBuffer::default()).collect();
+
+    // Prompt printed before its line completed, so it is not printed twice
+    let mut pending_printed_vec: Vec<Option<String>> = xname_vec.iter().map(|_| None).collect();
+
+    let mut xname_matched_index_set: HashSet<usize> = HashSet::new();
+
+    let ansi_escape_regex = terminal_ops::get_ansi_escape_regex();
+
+    while let Some((index, message)) = output.next().await {
+        let xname = &xname_vec[index];
+
+        let message = match message {
+            Ok(message) => message,
+            Err(error) => {
+                log::error!("Console {} stream error: {:?}", xname, error);
+                continue;
+            }
+        };
+
+        let prefix = format!("[{}]", xname).with(XNAME_COLOR_VEC[index % XNAME_COLOR_VEC.len()]);
+
+        let line_vec = line_buffer_vec[index].push(&message);
+
+        for (line_index, line) in line_vec.iter().enumerate() {
+            let pending_printed_opt = if line_index == 0 {
+                pending_printed_vec[index].take()
+            } else {
+                None
+            };
+
+            match pending_printed_opt
+                .as_deref()
+                .and_then(|pending_printed| line.strip_prefix(pending_printed))
+            {
+                Some(line_rest) if line_rest.is_empty() => {}
+                Some(line_rest) => println!("{} {}", prefix, line_rest),
+                None => println!("{} {}", prefix, line),
+            }
+        }
+
+        if let Some(until_regex) = &until_regex_opt {
+            let is_match = line_vec.iter().any(|line| {
+                until_regex.is_match(&terminal_ops::strip_ansi_escape_codes(
+                    &ansi_escape_regex,
+                    line,
+                ))
+            });
+
+            // Prompts, eg 'login:', do not end with new line
+            let pending = line_buffer_vec[index].pending();
+            let is_pending_match = until_regex.is_match(&terminal_ops::strip_ansi_escape_codes(
+                &ansi_escape_regex,
+                &pending,
+            ));
+
+            if (is_match || is_pending_match) && xname_matched_index_set.insert(index) {
+                if is_pending_match {
+                    println!("{} {}", prefix, pending);
+                    pending_printed_vec[index] = Some(pending);
+                }
+
+                log::info!("Pattern found in console of {}", xname);
+            }
+
+            if xname_matched_index_set.len() == xname_vec.len() {
+                println!("Pattern '{}' found on all nodes", until_regex);
+                break;
+            }
+        }
+    }
+}
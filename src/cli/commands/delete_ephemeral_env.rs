@@ -0,0 +1,123 @@
+use dialoguer::{theme::ColorfulTheme, Confirm};
+
+use crate::common::{ephemeral_env, jwt_ops::get_claims_from_jwt_token};
+
+/// Deletes ephemeral environments by name or IMS job ID. If 'expired' is true, then all
+/// ephemeral environments past their TTL are deleted too. Only ephemeral environments owned by
+/// the user are deleted unless 'all_users' is true
+pub async fn exec(
+    shasta_token: &str,
+    shasta_base_url: &str,
+    shasta_root_cert: &[u8],
+    name_vec: &[String],
+    expired: bool,
+    all_users: bool,
+    yes: bool,
+) {
+    let jwt_claims = get_claims_from_jwt_token(shasta_token).unwrap();
+
+    let owner = ephemeral_env::get_owner(
+        jwt_claims["preferred_username"]
+            .as_str()
+            .unwrap_or_default(),
+    );
+
+    let mut ephemeral_env_vec =
+        ephemeral_env::get_ephemeral_env_vec(shasta_token, shasta_base_url, shasta_root_cert).await;
+
+    if !all_users {
+        ephemeral_env_vec.retain(|ephemeral_env| ephemeral_env.user.eq(&owner));
+    }
+
+    let now = chrono::Utc::now();
+
+    for name in name_vec {
+        if !ephemeral_env_vec
+            .iter()
+            .any(|ephemeral_env| ephemeral_env.name.eq(name) || ephemeral_env.id.eq(name))
+        {
+            if all_users {
+                eprintln!("Ephemeral environment '{}' not found. Exit", name);
+            } else {
+                eprintln!(
+                    "Ephemeral environment '{}' not found for user {}, use '--all-users' to delete ephemeral environments of other users. Exit",
+                    name, owner
+                );
+            }
+            std::process::exit(1);
+        }
+    }
+
+    // Names are unique per user, with 'all_users' a name may match ephemeral environments of
+    // other users so the confirmation shows the owner
+    let ephemeral_env_to_delete_vec: Vec<&ephemeral_env::EphemeralEnv> = ephemeral_env_vec
+        .iter()
+        .filter(|ephemeral_env| {
+            name_vec
+                .iter()
+                .any(|name| ephemeral_env.name.eq(name) || ephemeral_env.id.eq(name))
+                || (expired && ephemeral_env.is_expired(now))
+        })
+        .collect();
+
+    if ephemeral_env_to_delete_vec.is_empty() {
+        println!("No ephemeral environments to delete");
+        return;
+    }
+
+    let ephemeral_env_summary_vec: Vec<String> = ephemeral_env_to_delete_vec
+        .iter()
+        .map(|ephemeral_env| {
+            format!(
+                "{} (user {}, IMS job {})",
+                ephemeral_env.name, ephemeral_env.user, ephemeral_env.id
+            )
+        })
+        .collect();
+
+    if !yes {
+        if Confirm::with_theme(&ColorfulTheme::default())
+            .with_prompt(format!(
+                "This operation will delete the following ephemeral environments:\n{}\nDo you want to continue?",
+                ephemeral_env_summary_vec.join("\n")
+            ))
+            .interact()
+            .unwrap()
+        {
+            log::info!("Continue");
+        } else {
+            println!("Cancelled by user. Aborting.");
+            std::process::exit(0);
+        }
+    }
+
+    for (ephemeral_env, ephemeral_env_summary) in ephemeral_env_to_delete_vec
+        .iter()
+        .zip(ephemeral_env_summary_vec.iter())
+    {
+        match ephemeral_env::http_client::delete_job(
+            shasta_token,
+            shasta_base_url,
+            shasta_root_cert,
+            &ephemeral_env.id,
+        )
+        .await
+        {
+            Ok(_) => {
+                println!("Ephemeral environment {} deleted", ephemeral_env_summary);
+
+                log::info!(
+                    target: "app::audit",
+                    "User: {} ({}) ; Operation: Delete ephemeral environment {}",
+                    jwt_claims["name"].as_str().unwrap_or_default(),
+                    jwt_claims["preferred_username"].as_str().unwrap_or_default(),
+                    ephemeral_env_summary
+                );
+            }
+            Err(error) => eprintln!(
+                "ERROR - Could not delete ephemeral environment {}. Reason:\n{}",
+                ephemeral_env_summary, error
+            ),
+        }
+    }
+}
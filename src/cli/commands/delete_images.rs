@@ -0,0 +1,206 @@
+use comfy_table::Table;
+use dialoguer::{theme::ColorfulTheme, Confirm};
+use mesa::ims::image::{self, r#struct::Image};
+use regex::Regex;
+
+use crate::common::{image_usage, ims_image, jwt_ops::get_claims_from_jwt_token};
+
+/// Deletes IMS images, and their S3 artifacts, by ID or by name matching a regex. Same as
+/// 'delete --configuration-name', images booted by nodes or referenced by BOS sessiontemplates
+/// are not deleted unless 'force' is true
+pub async fn exec(
+    shasta_token: &str,
+    shasta_base_url: &str,
+    shasta_root_cert: &[u8],
+    hsm_group_name_vec: &[String],
+    image_id_vec: &[String],
+    name_opt: Option<&String>,
+    force: bool,
+    yes: bool,
+) {
+    let name_regex_opt = name_opt.map(|name| match Regex::new(name) {
+        Ok(name_regex) => name_regex,
+        Err(error) => {
+            eprintln!("Pattern '{}' not valid: {}. Exit", name, error);
+            std::process::exit(1);
+        }
+    });
+
+    let mut image_vec: Vec<Image> =
+        image::mesa::http_client::get(shasta_token, shasta_base_url, shasta_root_cert, None)
+            .await
+            .unwrap();
+
+    // Only images related to the HSM groups available to the user can be deleted
+    let image_available_vec: Vec<Image> = image::utils::filter(
+        shasta_token,
+        shasta_base_url,
+        shasta_root_cert,
+        &mut image_vec,
+        hsm_group_name_vec,
+        None,
+    )
+    .await
+    .into_iter()
+    .map(|(image, _, _)| image)
+    .collect();
+
+    for image_id in image_id_vec {
+        if !image_available_vec
+            .iter()
+            .any(|image| image.id.as_ref() == Some(image_id))
+        {
+            eprintln!(
+                "Image '{}' not found or not related to HSM groups {:?}. Exit",
+                image_id, hsm_group_name_vec
+            );
+            std::process::exit(1);
+        }
+    }
+
+    let image_to_delete_vec: Vec<&Image> = image_available_vec
+        .iter()
+        .filter(|image| {
+            image
+                .id
+                .as_ref()
+                .is_some_and(|image_id| image_id_vec.contains(image_id))
+                || name_regex_opt
+                    .as_ref()
+                    .is_some_and(|name_regex| name_regex.is_match(&image.name))
+        })
+        .collect();
+
+    if image_to_delete_vec.is_empty() {
+        println!("No images to delete");
+        return;
+    }
+
+    let image_usage_map = image_usage::get_image_usage_map_from_csm(
+        shasta_token,
+        shasta_base_url,
+        shasta_root_cert,
+        &image_to_delete_vec
+            .iter()
+            .filter_map(|image| image.id.clone())
+            .collect::<Vec<String>>(),
+    )
+    .await;
+
+    // Print summary
+    let mut table = Table::new();
+
+    table.set_header(vec![
+        "Image ID",
+        "Name",
+        "Creation time",
+        "Nodes",
+        "BOS sessiontemplates",
+        "Action",
+    ]);
+
+    let mut image_id_in_use_vec: Vec<&str> = Vec::new();
+
+    for image in &image_to_delete_vec {
+        let image_id = image.id.as_deref().unwrap_or_default();
+        let image_usage = image_usage_map.get(image_id).cloned().unwrap_or_default();
+
+        let action = if !image_usage.is_in_use() {
+            "delete"
+        } else if force {
+            image_id_in_use_vec.push(image_id);
+            "delete (in use)"
+        } else {
+            image_id_in_use_vec.push(image_id);
+            "keep (in use)"
+        };
+
+        table.add_row(vec![
+            image_id.to_string(),
+            image.name.clone(),
+            image.created.clone().unwrap_or_default(),
+            image_usage.nodes.join(", "),
+            image_usage.bos_sessiontemplates.join("\n"),
+            action.to_string(),
+        ]);
+    }
+
+    println!("{table}");
+
+    let image_id_to_delete_vec: Vec<&str> = image_to_delete_vec
+        .iter()
+        .filter_map(|image| image.id.as_deref())
+        .filter(|image_id| force || !image_id_in_use_vec.contains(image_id))
+        .collect();
+
+    if !image_id_in_use_vec.is_empty() && !force {
+        eprintln!(
+            "Images booted by nodes or referenced by BOS sessiontemplates are not deleted, use '--force' to delete them: {}",
+            image_id_in_use_vec.join(", ")
+        );
+    }
+
+    if image_id_to_delete_vec.is_empty() {
+        println!("No images to delete");
+        return;
+    }
+
+    if !yes {
+        if Confirm::with_theme(&ColorfulTheme::default())
+            .with_prompt(format!(
+                "This operation will delete {} images and their artifacts in S3. Do you want to continue?",
+                image_id_to_delete_vec.len()
+            ))
+            .interact()
+            .unwrap()
+        {
+            log::info!("Continue");
+        } else {
+            println!("Cancelled by user. Aborting.");
+            std::process::exit(0);
+        }
+    }
+
+    let jwt_claims = get_claims_from_jwt_token(shasta_token).unwrap();
+
+    let mut image_deleted_counter = 0;
+
+    for image_id in &image_id_to_delete_vec {
+        match ims_image::http_client::delete_image_and_artifacts(
+            shasta_token,
+            shasta_base_url,
+            shasta_root_cert,
+            image_id,
+        )
+        .await
+        {
+            Ok(_) => {
+                image_deleted_counter += 1;
+
+                println!("Image deleted: {}", image_id);
+
+                log::info!(
+                    target: "app::audit",
+                    "User: {} ({}) ; Operation: Delete image {}",
+                    jwt_claims["name"].as_str().unwrap_or_default(),
+                    jwt_claims["preferred_username"].as_str().unwrap_or_default(),
+                    image_id
+                );
+            }
+            Err(error) => eprintln!(
+                "ERROR - Could not delete image {}. Reason:\n{}",
+                image_id, error
+            ),
+        }
+    }
+
+    println!(
+        "{} of {} images deleted",
+        image_deleted_counter,
+        image_id_to_delete_vec.len()
+    );
+
+    if image_deleted_counter < image_id_to_delete_vec.len() {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,80 @@
+use dialoguer::{theme::ColorfulTheme, Confirm};
+
+use crate::common::{ims_public_key, jwt_ops::get_claims_from_jwt_token};
+
+/// Deletes IMS public keys. Only keys registered under the user name can be deleted
+pub async fn exec(
+    shasta_token: &str,
+    shasta_base_url: &str,
+    shasta_root_cert: &[u8],
+    public_key_id_vec: &[String],
+    yes: bool,
+) {
+    let jwt_claims = get_claims_from_jwt_token(shasta_token).unwrap();
+
+    let user = jwt_claims["preferred_username"].as_str().unwrap();
+
+    let user_public_key_vec = ims_public_key::get_user_public_key_vec(
+        shasta_token,
+        shasta_base_url,
+        shasta_root_cert,
+        user,
+    )
+    .await;
+
+    for public_key_id in public_key_id_vec {
+        if !user_public_key_vec
+            .iter()
+            .any(|user_public_key| user_public_key["id"].as_str() == Some(public_key_id))
+        {
+            eprintln!(
+                "Public key '{}' not found or does not belong to user '{}'. Exit",
+                public_key_id, user
+            );
+            std::process::exit(1);
+        }
+    }
+
+    if !yes {
+        if Confirm::with_theme(&ColorfulTheme::default())
+            .with_prompt(format!(
+                "This operation will delete the following public keys:\n{}\nDo you want to continue?",
+                public_key_id_vec.join("\n")
+            ))
+            .interact()
+            .unwrap()
+        {
+            log::info!("Continue");
+        } else {
+            println!("Cancelled by user. Aborting.");
+            std::process::exit(0);
+        }
+    }
+
+    for public_key_id in public_key_id_vec {
+        match ims_public_key::http_client::delete_public_key(
+            shasta_token,
+            shasta_base_url,
+            shasta_root_cert,
+            public_key_id,
+        )
+        .await
+        {
+            Ok(_) => {
+                println!("Public key {} deleted", public_key_id);
+
+                log::info!(
+                    target: "app::audit",
+                    "User: {} ({}) ; Operation: Delete IMS public key {}",
+                    jwt_claims["name"].as_str().unwrap_or_default(),
+                    jwt_claims["preferred_username"].as_str().unwrap_or_default(),
+                    public_key_id
+                );
+            }
+            Err(error) => eprintln!(
+                "ERROR - Could not delete public key {}. Reason:\n{}",
+                public_key_id, error
+            ),
+        }
+    }
+}
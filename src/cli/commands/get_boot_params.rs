@@ -0,0 +1,49 @@
+use comfy_table::Table;
+
+use crate::common::boot_params;
+
+/// Prints the image, kernel, initrd and kernel parameters the nodes boot with. Nodes with the
+/// same boot parameters are grouped
+pub async fn exec(
+    shasta_token: &str,
+    shasta_base_url: &str,
+    shasta_root_cert: &[u8],
+    xname_vec: &[String],
+    output_opt: Option<&String>,
+) {
+    let boot_params_group_vec = boot_params::get_boot_params_group_vec(
+        shasta_token,
+        shasta_base_url,
+        shasta_root_cert,
+        xname_vec,
+    )
+    .await;
+
+    if output_opt.is_some() && output_opt.unwrap().eq("json") {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&boot_params_group_vec).unwrap()
+        );
+    } else {
+        let mut table = Table::new();
+
+        table.set_header(vec!["XNAMES", "Image ID", "Kernel", "Initrd", "Params"]);
+
+        for boot_params_group in boot_params_group_vec {
+            table.add_row(vec![
+                boot_params_group.xnames.join("\n"),
+                boot_params_group.image_id,
+                boot_params_group.kernel,
+                boot_params_group.initrd,
+                // One kernel parameter per line so the table stays readable
+                boot_params_group
+                    .params
+                    .split_whitespace()
+                    .collect::<Vec<&str>>()
+                    .join("\n"),
+            ]);
+        }
+
+        println!("{table}");
+    }
+}
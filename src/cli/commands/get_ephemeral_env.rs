@@ -0,0 +1,88 @@
+use comfy_table::Table;
+use serde_json::json;
+
+use crate::common::ephemeral_env;
+
+/// Prints the ephemeral environments, when they expire and how to connect to them
+pub async fn exec(
+    shasta_token: &str,
+    shasta_base_url: &str,
+    shasta_root_cert: &[u8],
+    user_opt: Option<&String>,
+    output_opt: Option<&String>,
+) {
+    let mut ephemeral_env_vec =
+        ephemeral_env::get_ephemeral_env_vec(shasta_token, shasta_base_url, shasta_root_cert).await;
+
+    if let Some(user) = user_opt {
+        let owner = ephemeral_env::get_owner(user);
+
+        ephemeral_env_vec.retain(|ephemeral_env| ephemeral_env.user.eq(&owner));
+    }
+
+    let now = chrono::Utc::now();
+
+    if output_opt.is_some() && output_opt.unwrap().eq("json") {
+        let ephemeral_env_value_vec: Vec<serde_json::Value> = ephemeral_env_vec
+            .iter()
+            .map(|ephemeral_env| {
+                json!({
+                    "id": ephemeral_env.id,
+                    "name": ephemeral_env.name,
+                    "user": ephemeral_env.user,
+                    "image_id": ephemeral_env.image_id,
+                    "status": ephemeral_env.status,
+                    "host": ephemeral_env.host_opt,
+                    "port": ephemeral_env.port_opt,
+                    "created": ephemeral_env.created_opt.map(|created| created.to_rfc3339()),
+                    "expires": ephemeral_env.expires_opt().map(|expires| expires.to_rfc3339()),
+                    "expired": ephemeral_env.is_expired(now),
+                })
+            })
+            .collect();
+
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&ephemeral_env_value_vec).unwrap()
+        );
+    } else {
+        let mut table = Table::new();
+
+        table.set_header(vec![
+            "Name",
+            "User",
+            "IMS job ID",
+            "Image ID",
+            "Status",
+            "Host",
+            "Created",
+            "Expires",
+        ]);
+
+        for ephemeral_env in ephemeral_env_vec {
+            let expires = match ephemeral_env.expires_opt() {
+                Some(expires) if ephemeral_env.is_expired(now) => {
+                    format!("{} (expired)", expires.format("%Y-%m-%dT%H:%M:%S"))
+                }
+                Some(expires) => expires.format("%Y-%m-%dT%H:%M:%S").to_string(),
+                None => "".to_string(),
+            };
+
+            table.add_row(vec![
+                ephemeral_env.name.clone(),
+                ephemeral_env.user.clone(),
+                ephemeral_env.id.clone(),
+                ephemeral_env.image_id.clone(),
+                ephemeral_env.status.clone(),
+                ephemeral_env.host_opt.clone().unwrap_or_default(),
+                ephemeral_env
+                    .created_opt
+                    .map(|created| created.format("%Y-%m-%dT%H:%M:%S").to_string())
+                    .unwrap_or_default(),
+                expires,
+            ]);
+        }
+
+        println!("{table}");
+    }
+}
@@ -1,35 +1,145 @@
+use chrono::NaiveDateTime;
 use comfy_table::Table;
 use mesa::ims::image::{self, r#struct::Image};
+use regex::Regex;
+use serde_json::{json, Value};
+
+use crate::common::{image_usage, node_ops};
 
 /// If filtering by HSM group, then image name must include HSM group name (It assumms each image
 /// is built for a specific cluster based on ansible vars used by the CFS session). The reason
 /// for this is because CSCS staff deletes all CFS sessions every now and then...
+///
+/// If 'show_usage' is true, then the nodes booting each image, the BOS sessiontemplates
+/// referencing it and the CFS sessions which built it are printed. 'in_use_opt' keeps only images
+/// in use (Some(true)) or not in use (Some(false))
 pub async fn exec(
     shasta_token: &str,
     shasta_base_url: &str,
     shasta_root_cert: &[u8],
     hsm_group_name_vec: &[String],
     limit_number: Option<&u8>,
+    name_opt: Option<&String>,
+    since_opt: Option<NaiveDateTime>,
+    show_usage: bool,
+    in_use_opt: Option<bool>,
+    output_opt: Option<&String>,
 ) {
+    let name_regex_opt = name_opt.map(|name| match Regex::new(name) {
+        Ok(name_regex) => name_regex,
+        Err(error) => {
+            eprintln!("Pattern '{}' not valid: {}. Exit", name, error);
+            std::process::exit(1);
+        }
+    });
+
     let mut image_vec: Vec<Image> =
         image::mesa::http_client::get(shasta_token, shasta_base_url, shasta_root_cert, None)
             .await
             .unwrap();
 
-    let image_detail_vec: Vec<(Image, String, String)> = image::utils::filter(
+    // Filter before applying the limit so the limit returns the most recent images matching
+    image_vec.retain(|image| {
+        name_regex_opt
+            .as_ref()
+            .map_or(true, |name_regex| name_regex.is_match(&image.name))
+            && since_opt.map_or(true, |since| {
+                image
+                    .created
+                    .as_ref()
+                    .and_then(|created| chrono::DateTime::parse_from_rfc3339(created).ok())
+                    .is_some_and(|created| created.naive_utc() >= since)
+            })
+    });
+
+    // Usage is only known after filtering by HSM group, if filtering by usage then the limit is
+    // applied afterwards so it returns the most recent images in use or not in use
+    let mut image_detail_vec: Vec<(Image, String, String)> = image::utils::filter(
         shasta_token,
         shasta_base_url,
         shasta_root_cert,
         &mut image_vec,
         hsm_group_name_vec,
-        limit_number,
+        if in_use_opt.is_some() {
+            None
+        } else {
+            limit_number
+        },
     )
     .await;
 
+    let image_usage_map = if show_usage || in_use_opt.is_some() {
+        let image_id_vec: Vec<String> = image_detail_vec
+            .iter()
+            .filter_map(|(image, _, _)| image.id.clone())
+            .collect();
+
+        image_usage::get_image_usage_map_from_csm(
+            shasta_token,
+            shasta_base_url,
+            shasta_root_cert,
+            &image_id_vec,
+        )
+        .await
+    } else {
+        Default::default()
+    };
+
+    if let Some(in_use) = in_use_opt {
+        image_detail_vec.retain(|(image, _, _)| {
+            image_usage_map
+                .get(image.id.as_deref().unwrap_or_default())
+                .is_some_and(|image_usage| image_usage.is_in_use() == in_use)
+        });
+
+        if let Some(limit_number) = limit_number {
+            // Keep the most recent images, oldest first
+            image_detail_vec
+                .sort_by(|(image_a, _, _), (image_b, _, _)| image_a.created.cmp(&image_b.created));
+
+            let image_older_count = image_detail_vec
+                .len()
+                .saturating_sub(usize::from(*limit_number));
+
+            image_detail_vec.drain(..image_older_count);
+        }
+    }
+
+    if output_opt.is_some() && output_opt.unwrap().eq("json") {
+        let image_value_vec: Vec<Value> = image_detail_vec
+            .iter()
+            .map(|(image, cfs_configuration, hsm_groups)| {
+                let mut image_value = json!({
+                    "id": image.id,
+                    "name": image.name,
+                    "created": image.created,
+                    "configuration": cfs_configuration,
+                    "hsm_groups": hsm_groups,
+                });
+
+                if show_usage {
+                    image_value["usage"] = json!(image_usage_map
+                        .get(image.id.as_deref().unwrap_or_default())
+                        .cloned()
+                        .unwrap_or_default());
+                }
+
+                image_value
+            })
+            .collect();
+
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&image_value_vec).unwrap()
+        );
+
+        return;
+    }
+
     // Print data
     let mut table = Table::new();
 
-    table.set_header(vec![
+    let mut header_vec = vec![
         "Image ID",
         "Name",
         "Creation time",
@@ -37,16 +147,40 @@ pub async fn exec(
         "HSM groups",
         // "BOS sessiontemplate",
         // "CFS session name",
-    ]);
+    ];
+
+    if show_usage {
+        header_vec.extend(["Nodes", "BOS sessiontemplates", "CFS sessions"]);
+    }
+
+    table.set_header(header_vec);
 
     for image_details in image_detail_vec {
-        table.add_row(vec![
-            image_details.0.id.as_ref().unwrap(),
-            &image_details.0.name,
-            image_details.0.created.as_ref().unwrap(),
-            &image_details.1,
-            &image_details.2,
-        ]);
+        let mut row = vec![
+            image_details.0.id.clone().unwrap(),
+            image_details.0.name.clone(),
+            image_details.0.created.clone().unwrap(),
+            image_details.1.clone(),
+            image_details.2.clone(),
+        ];
+
+        if show_usage {
+            let image_usage = image_usage_map
+                .get(image_details.0.id.as_deref().unwrap_or_default())
+                .cloned()
+                .unwrap_or_default();
+
+            let node_value_vec: Vec<Value> =
+                image_usage.nodes.iter().map(|node| json!(node)).collect();
+
+            row.extend([
+                node_ops::nodes_to_string_format_discrete_columns(Some(&node_value_vec), 4),
+                image_usage.bos_sessiontemplates.join("\n"),
+                image_usage.cfs_sessions.join("\n"),
+            ]);
+        }
+
+        table.add_row(row);
     }
 
     println!("{table}");
@@ -0,0 +1,383 @@
+use std::collections::HashMap;
+
+use chrono::{NaiveDateTime, TimeZone};
+use comfy_table::Table;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::common::{log_ops, node_ops};
+
+/// Entry in the timeline of a node
+#[derive(Serialize, Debug, Clone)]
+pub struct NodeHistoryEntry {
+    pub xname: String,
+    pub date: String,
+    pub source: String,
+    pub event: String,
+    pub session: String,
+    pub configuration: String,
+    pub result: String,
+    pub duration: String,
+    pub boot_image: String,
+}
+
+/// Builds a timeline of what happened to a list of nodes combining CFS sessions, CFS component
+/// state, boot parameters and power operations registered in the audit file
+pub async fn exec(
+    shasta_token: &str,
+    shasta_base_url: &str,
+    shasta_root_cert: &[u8],
+    xname_vec: &[String],
+    limit_opt: Option<&usize>,
+    output_opt: Option<&String>,
+) {
+    // Get all CFS sessions
+    let cfs_session_vec = mesa::cfs::session::mesa::http_client::get(
+        shasta_token,
+        shasta_base_url,
+        shasta_root_cert,
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+
+    // Get boot params for the nodes
+    let boot_param_vec = mesa::bss::http_client::get_boot_params(
+        shasta_token,
+        shasta_base_url,
+        shasta_root_cert,
+        xname_vec,
+    )
+    .await
+    .unwrap_or_default();
+
+    // Get audit file entries
+    let audit_line_vec: Vec<String> = std::fs::read_to_string(log_ops::get_audit_file_path())
+        .unwrap_or_default()
+        .lines()
+        .map(str::to_string)
+        .collect();
+
+    // Cache HSM group members so each group is only fetched once
+    let mut hsm_group_member_map: HashMap<String, Vec<String>> = HashMap::new();
+
+    let mut node_history_entry_vec = Vec::new();
+
+    for xname in xname_vec {
+        let boot_image_id = boot_param_vec
+            .iter()
+            .find(|boot_param| {
+                boot_param["hosts"]
+                    .as_array()
+                    .is_some_and(|host_vec| host_vec.iter().any(|host| host.eq(xname)))
+            })
+            .and_then(node_ops::get_image_id_from_boot_param)
+            .unwrap_or_default();
+
+        let mut xname_history_entry_vec = Vec::new();
+
+        // CFS sessions targeting the node
+        for cfs_session in &cfs_session_vec {
+            let cfs_session_value = serde_json::to_value(cfs_session).unwrap();
+
+            let is_target_definition_image = cfs_session_value
+                .pointer("/target/definition")
+                .is_some_and(|definition| definition.eq("image"));
+
+            let result_image_id = cfs_session_value
+                .pointer("/status/artifacts/0/result_id")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string();
+
+            let is_node_targeted = if is_target_definition_image {
+                // Image CFS sessions only relate to the node if the node boots the image
+                !result_image_id.is_empty() && result_image_id.eq(&boot_image_id)
+            } else {
+                let is_in_ansible_limit = cfs_session_value
+                    .pointer("/ansible/limit")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .split(',')
+                    .any(|limit_xname| limit_xname.trim().eq(xname));
+
+                let mut is_in_target_groups = false;
+
+                for group_name in cfs_session_value
+                    .pointer("/target/groups")
+                    .and_then(Value::as_array)
+                    .unwrap_or(&Vec::new())
+                    .iter()
+                    .filter_map(|group| group["name"].as_str())
+                {
+                    if !hsm_group_member_map.contains_key(group_name) {
+                        let member_vec =
+                            mesa::hsm::group::shasta::utils::get_member_vec_from_hsm_group_name(
+                                shasta_token,
+                                shasta_base_url,
+                                shasta_root_cert,
+                                group_name,
+                            )
+                            .await;
+
+                        hsm_group_member_map.insert(group_name.to_string(), member_vec);
+                    }
+
+                    if hsm_group_member_map[group_name].contains(xname) {
+                        is_in_target_groups = true;
+                        break;
+                    }
+                }
+
+                is_in_ansible_limit || is_in_target_groups
+            };
+
+            if !is_node_targeted {
+                continue;
+            }
+
+            let start_time = cfs_session_value
+                .pointer("/status/session/startTime")
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+
+            let completion_time = cfs_session_value
+                .pointer("/status/session/completionTime")
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+
+            let status = cfs_session_value
+                .pointer("/status/session/status")
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+
+            let succeeded = cfs_session_value
+                .pointer("/status/session/succeeded")
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+
+            xname_history_entry_vec.push(NodeHistoryEntry {
+                xname: xname.to_string(),
+                date: start_time.to_string(),
+                source: "CFS session".to_string(),
+                event: format!(
+                    "CFS session target {}",
+                    if is_target_definition_image {
+                        "image"
+                    } else {
+                        "dynamic"
+                    }
+                ),
+                session: cfs_session.name.clone().unwrap_or_default(),
+                configuration: cfs_session_value
+                    .pointer("/configuration/name")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .to_string(),
+                result: if status.eq("complete") {
+                    format!("{} (succeeded: {})", status, succeeded)
+                } else {
+                    status.to_string()
+                },
+                duration: get_duration(start_time, completion_time),
+                boot_image: result_image_id,
+            });
+        }
+
+        // CFS component state (layers applied to the node)
+        let cfs_component = mesa::cfs::component::shasta::http_client::get_single_component(
+            shasta_token,
+            shasta_base_url,
+            shasta_root_cert,
+            xname,
+        )
+        .await
+        .unwrap_or_default();
+
+        for layer_state in cfs_component["state"].as_array().unwrap_or(&Vec::new()) {
+            xname_history_entry_vec.push(NodeHistoryEntry {
+                xname: xname.to_string(),
+                date: layer_state["lastUpdated"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+                source: "CFS component".to_string(),
+                event: format!(
+                    "Layer applied: {} {} ({})",
+                    layer_state["cloneUrl"]
+                        .as_str()
+                        .unwrap_or_default()
+                        .rsplit('/')
+                        .next()
+                        .unwrap_or_default(),
+                    layer_state["playbook"].as_str().unwrap_or_default(),
+                    layer_state["commit"].as_str().unwrap_or_default(),
+                ),
+                session: layer_state["sessionName"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+                configuration: cfs_component["desiredConfig"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+                result: cfs_component["configurationStatus"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+                duration: "".to_string(),
+                boot_image: "".to_string(),
+            });
+        }
+
+        // Power operations and boot parameter changes registered in audit file
+        for audit_line in audit_line_vec
+            .iter()
+            .filter(|audit_line| is_xname_in_audit_line(audit_line, xname))
+        {
+            let date = audit_line.split(" | ").next().unwrap_or_default();
+            let message = audit_line
+                .split_once(" — ")
+                .map(|(_, message)| message)
+                .unwrap_or(audit_line);
+
+            // Audit file dates are in local time while CSM API dates are in UTC
+            let date = get_utc_date_from_audit_date(date).unwrap_or(date.to_string());
+
+            let (user, operation) = message.split_once(" ; ").unwrap_or(("", message));
+
+            xname_history_entry_vec.push(NodeHistoryEntry {
+                xname: xname.to_string(),
+                date,
+                source: "Audit".to_string(),
+                event: operation
+                    .trim_start_matches("Operation: ")
+                    .split(" [")
+                    .next()
+                    .unwrap_or_default()
+                    .to_string(),
+                session: "".to_string(),
+                configuration: "".to_string(),
+                result: user.trim_start_matches("User: ").to_string(),
+                duration: "".to_string(),
+                boot_image: "".to_string(),
+            });
+        }
+
+        // Current boot image
+        xname_history_entry_vec.push(NodeHistoryEntry {
+            xname: xname.to_string(),
+            date: "".to_string(),
+            source: "BSS".to_string(),
+            event: "Current boot image".to_string(),
+            session: "".to_string(),
+            configuration: "".to_string(),
+            result: "".to_string(),
+            duration: "".to_string(),
+            boot_image: boot_image_id,
+        });
+
+        // Sort entries by date, entries without date go last
+        xname_history_entry_vec
+            .sort_by_key(|entry| parse_date(&entry.date).unwrap_or(NaiveDateTime::MAX));
+
+        if let Some(limit) = limit_opt {
+            // Keep most recent entries
+            let skip = xname_history_entry_vec.len().saturating_sub(*limit);
+            xname_history_entry_vec.drain(..skip);
+        }
+
+        node_history_entry_vec.extend(xname_history_entry_vec);
+    }
+
+    if output_opt.is_some() && output_opt.unwrap().eq("json") {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&node_history_entry_vec).unwrap()
+        );
+    } else {
+        print_table(&node_history_entry_vec);
+    }
+}
+
+pub fn print_table(node_history_entry_vec: &[NodeHistoryEntry]) {
+    let mut table = Table::new();
+
+    table.set_header(vec![
+        "XNAME",
+        "Date",
+        "Source",
+        "Event",
+        "Session",
+        "Configuration",
+        "Result",
+        "Duration",
+        "Boot image",
+    ]);
+
+    for entry in node_history_entry_vec {
+        table.add_row(vec![
+            entry.xname.clone(),
+            entry.date.clone(),
+            entry.source.clone(),
+            entry.event.clone(),
+            entry.session.clone(),
+            entry.configuration.clone(),
+            entry.result.clone(),
+            entry.duration.clone(),
+            entry.boot_image.clone(),
+        ]);
+    }
+
+    println!("{table}");
+}
+
+/// Checks if an xname is one of the words in an audit line, so 'x1000c0s0b0n1' does not match
+/// an audit line about 'x1000c0s0b0n10'
+fn is_xname_in_audit_line(audit_line: &str, xname: &str) -> bool {
+    audit_line
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .any(|word| word.eq(xname))
+}
+
+/// Converts an audit file date (local time with format '%Y-%m-%d %H:%M:%S') to UTC with the
+/// same format CSM APIs use
+fn get_utc_date_from_audit_date(date: &str) -> Option<String> {
+    let naive_date_time = NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M:%S").ok()?;
+
+    chrono::Local
+        .from_local_datetime(&naive_date_time)
+        .earliest()
+        .map(|date_time| {
+            date_time
+                .with_timezone(&chrono::Utc)
+                .format("%Y-%m-%dT%H:%M:%SZ")
+                .to_string()
+        })
+}
+
+/// Parses dates returned by CSM APIs (RFC3339 or without timezone) and dates in the audit file
+fn parse_date(date: &str) -> Option<NaiveDateTime> {
+    chrono::DateTime::parse_from_rfc3339(date)
+        .map(|date_time| date_time.naive_utc())
+        .or_else(|_| NaiveDateTime::parse_from_str(date, "%Y-%m-%dT%H:%M:%S"))
+        .or_else(|_| NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M:%S"))
+        .ok()
+}
+
+/// Returns the time between 2 dates with format HH:MM:SS
+fn get_duration(start_time: &str, completion_time: &str) -> String {
+    match (parse_date(start_time), parse_date(completion_time)) {
+        (Some(start_time), Some(completion_time)) => {
+            let duration = completion_time - start_time;
+            format!(
+                "{:02}:{:02}:{:02}",
+                duration.num_hours(),
+                duration.num_minutes() % 60,
+                duration.num_seconds() % 60
+            )
+        }
+        _ => "".to_string(),
+    }
+}
@@ -0,0 +1,80 @@
+use std::collections::HashSet;
+
+use comfy_table::Table;
+
+use crate::common::node_map;
+
+use super::config_show::get_hsm_name_available_from_jwt_or_all;
+
+/// Prints the xname, NID, hostname and role of the nodes the user has access to
+pub async fn exec(
+    shasta_token: &str,
+    shasta_base_url: &str,
+    shasta_root_cert: &[u8],
+    refresh: bool,
+    output_opt: Option<&String>,
+) {
+    let node_vec = match node_map::get_node_map(
+        shasta_token,
+        shasta_base_url,
+        shasta_root_cert,
+        refresh,
+    )
+    .await
+    {
+        Ok(node_vec) => node_vec,
+        Err(error) => {
+            eprintln!("ERROR - {}. Exit", error);
+            std::process::exit(1);
+        }
+    };
+
+    let hsm_name_available_vec =
+        get_hsm_name_available_from_jwt_or_all(shasta_token, shasta_base_url, shasta_root_cert)
+            .await;
+
+    let xname_available_set: HashSet<String> =
+        mesa::hsm::group::shasta::utils::get_member_vec_from_hsm_name_vec(
+            shasta_token,
+            shasta_base_url,
+            shasta_root_cert,
+            &hsm_name_available_vec,
+        )
+        .await
+        .into_iter()
+        .collect();
+
+    let node_vec: Vec<node_map::NodeMapEntry> = node_vec
+        .into_iter()
+        .filter(|node| xname_available_set.contains(&node.xname))
+        .collect();
+
+    if output_opt.is_some() && output_opt.unwrap().eq("json") {
+        println!("{}", serde_json::to_string_pretty(&node_vec).unwrap());
+    } else if output_opt.is_some() && output_opt.unwrap().eq("csv") {
+        println!("xname,nid,hostname,role,sub_role");
+
+        for node in node_vec {
+            println!(
+                "{},{},{},{},{}",
+                node.xname, node.nid, node.hostname, node.role, node.sub_role
+            );
+        }
+    } else {
+        let mut table = Table::new();
+
+        table.set_header(vec!["XNAME", "NID", "Hostname", "Role", "SubRole"]);
+
+        for node in node_vec {
+            table.add_row(vec![
+                node.xname,
+                node.nid.to_string(),
+                node.hostname,
+                node.role,
+                node.sub_role,
+            ]);
+        }
+
+        println!("{table}");
+    }
+}
@@ -1,6 +1,11 @@
-use mesa::hsm;
+use std::{collections::HashMap, io::Write, time::Duration};
 
-use crate::common::node_ops;
+use mesa::{hsm, node::r#struct::NodeDetails};
+
+use crate::common::{
+    cluster_health::{self, NodeHealthState, Threshold},
+    node_ops::{self, NodeWatchState},
+};
 
 /// Get nodes status/configuration for some nodes filtered by a HSM group.
 pub async fn exec(
@@ -13,23 +18,11 @@ pub async fn exec(
     output_opt: Option<&String>,
     status: bool,
 ) {
-    // Take all nodes for all hsm_groups found and put them in a Vec
-    let mut hsm_groups_node_list: Vec<String> =
-        hsm::group::shasta::utils::get_member_vec_from_hsm_name_vec(
-            shasta_token,
-            shasta_base_url,
-            shasta_root_cert,
-            hsm_name_vec,
-        )
-        .await;
-
-    hsm_groups_node_list.sort();
-
-    let node_details_list = mesa::node::utils::get_node_details(
+    let node_details_list = get_node_details_vec(
         shasta_token,
         shasta_base_url,
         shasta_root_cert,
-        hsm_groups_node_list,
+        hsm_name_vec,
     )
     .await;
 
@@ -97,3 +90,180 @@ pub async fn exec(
         node_ops::print_table(node_details_list);
     }
 }
+
+/// Redraws the nodes table every 'interval_secs' seconds. Power status, configuration status and
+/// error count cells which changed since the previous refresh are highlighted. If 'until_opt' is
+/// provided, then returns once all nodes reach that state ('on', 'off', 'configured' or 'ready')
+pub async fn exec_watch(
+    shasta_token: &str,
+    shasta_base_url: &str,
+    shasta_root_cert: &[u8],
+    hsm_name_vec: &[String],
+    interval_secs: u64,
+    until_opt: Option<&String>,
+) {
+    let mut stdout = std::io::stdout();
+
+    let mut previous_node_state_map_opt: Option<HashMap<String, NodeWatchState>> = None;
+
+    loop {
+        let node_details_list = get_node_details_vec(
+            shasta_token,
+            shasta_base_url,
+            shasta_root_cert,
+            hsm_name_vec,
+        )
+        .await;
+
+        let table = node_ops::get_table(&node_details_list, previous_node_state_map_opt.as_ref());
+
+        let _ = crossterm::execute!(
+            stdout,
+            crossterm::terminal::Clear(crossterm::terminal::ClearType::All),
+            crossterm::cursor::MoveTo(0, 0)
+        );
+
+        println!(
+            "Every {}s: {}    {}\n",
+            interval_secs,
+            hsm_name_vec.join(", "),
+            chrono::Local::now().format("%Y-%m-%d %H:%M:%S")
+        );
+        println!("{table}");
+
+        let _ = stdout.flush();
+
+        if let Some(until) = until_opt {
+            if !node_details_list.is_empty()
+                && node_details_list
+                    .iter()
+                    .all(|node_details| is_node_in_state(node_details, until))
+            {
+                println!("All nodes are {}", until);
+                return;
+            }
+        }
+
+        previous_node_state_map_opt = Some(
+            node_details_list
+                .iter()
+                .map(|node_details| {
+                    (
+                        node_details.xname.clone(),
+                        node_ops::get_node_watch_state(node_details),
+                    )
+                })
+                .collect(),
+        );
+
+        tokio::time::sleep(Duration::from_secs(interval_secs)).await;
+    }
+}
+
+/// Prints a health report of the nodes in a list of HSM groups and exits with a Nagios plugin
+/// exit code (0 OK, 1 WARNING, 2 CRITICAL, 3 UNKNOWN)
+pub async fn exec_health(
+    shasta_token: &str,
+    shasta_base_url: &str,
+    shasta_root_cert: &[u8],
+    hsm_name_vec: &[String],
+    warning: &Threshold,
+    critical: &Threshold,
+    output_opt: Option<&String>,
+) {
+    let cluster_name = hsm_name_vec.join(",");
+
+    // CSM API helpers panic on errors, collection runs in its own task so a failure is reported
+    // as UNKNOWN instead of crashing the plugin
+    let node_details_list_handle = tokio::spawn({
+        let shasta_token = shasta_token.to_string();
+        let shasta_base_url = shasta_base_url.to_string();
+        let shasta_root_cert = shasta_root_cert.to_vec();
+        let hsm_name_vec = hsm_name_vec.to_vec();
+
+        async move {
+            get_node_details_vec(
+                &shasta_token,
+                &shasta_base_url,
+                &shasta_root_cert,
+                &hsm_name_vec,
+            )
+            .await
+        }
+    });
+
+    let node_details_list = match node_details_list_handle.await {
+        Ok(node_details_list) => node_details_list,
+        Err(error) => {
+            let reason = match error.try_into_panic() {
+                Ok(panic) => panic
+                    .downcast_ref::<String>()
+                    .cloned()
+                    .or_else(|| {
+                        panic
+                            .downcast_ref::<&str>()
+                            .map(|reason| reason.to_string())
+                    })
+                    .unwrap_or_default(),
+                Err(error) => error.to_string(),
+            };
+
+            cluster_health::exit_unknown(
+                &cluster_name,
+                &format!("Could not get nodes details: {}", reason),
+                output_opt,
+            );
+        }
+    };
+
+    let node_health_state_vec: Vec<NodeHealthState> = node_details_list
+        .iter()
+        .map(NodeHealthState::from)
+        .collect();
+
+    let report = cluster_health::get_report(&node_health_state_vec, warning, critical);
+
+    cluster_health::print_report(&cluster_name, &report, output_opt);
+
+    std::process::exit(report.status.exit_code());
+}
+
+/// Returns the details of all nodes in a list of HSM groups sorted by xname
+async fn get_node_details_vec(
+    shasta_token: &str,
+    shasta_base_url: &str,
+    shasta_root_cert: &[u8],
+    hsm_name_vec: &[String],
+) -> Vec<NodeDetails> {
+    // Take all nodes for all hsm_groups found and put them in a Vec
+    let mut hsm_groups_node_list: Vec<String> =
+        hsm::group::shasta::utils::get_member_vec_from_hsm_name_vec(
+            shasta_token,
+            shasta_base_url,
+            shasta_root_cert,
+            hsm_name_vec,
+        )
+        .await;
+
+    hsm_groups_node_list.sort();
+
+    mesa::node::utils::get_node_details(
+        shasta_token,
+        shasta_base_url,
+        shasta_root_cert,
+        hsm_groups_node_list,
+    )
+    .await
+}
+
+fn is_node_in_state(node_details: &NodeDetails, state: &str) -> bool {
+    let is_configured = node_details
+        .configuration_status
+        .eq_ignore_ascii_case("configured");
+
+    match state {
+        "configured" => is_configured,
+        "ready" => node_details.power_status.eq_ignore_ascii_case("on") && is_configured,
+        power_status => node_details.power_status.eq_ignore_ascii_case(power_status),
+    }
+}
@@ -0,0 +1,45 @@
+use comfy_table::Table;
+
+use crate::common::{ims_public_key, jwt_ops::get_claims_from_jwt_token};
+
+/// Prints the IMS public keys registered under the user name
+pub async fn exec(
+    shasta_token: &str,
+    shasta_base_url: &str,
+    shasta_root_cert: &[u8],
+    output_opt: Option<&String>,
+) {
+    let jwt_claims = get_claims_from_jwt_token(shasta_token).unwrap();
+
+    let user = jwt_claims["preferred_username"].as_str().unwrap();
+
+    let user_public_key_vec = ims_public_key::get_user_public_key_vec(
+        shasta_token,
+        shasta_base_url,
+        shasta_root_cert,
+        user,
+    )
+    .await;
+
+    if output_opt.is_some() && output_opt.unwrap().eq("json") {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&user_public_key_vec).unwrap()
+        );
+    } else {
+        let mut table = Table::new();
+
+        table.set_header(vec!["ID", "Name", "Created", "Public key"]);
+
+        for user_public_key in user_public_key_vec {
+            table.add_row(vec![
+                user_public_key["id"].as_str().unwrap_or_default(),
+                user_public_key["name"].as_str().unwrap_or_default(),
+                user_public_key["created"].as_str().unwrap_or_default(),
+                user_public_key["public_key"].as_str().unwrap_or_default(),
+            ]);
+        }
+
+        println!("{table}");
+    }
+}
@@ -1,8 +1,18 @@
+use crate::common::{
+    jwt_ops::get_claims_from_jwt_token,
+    power_backend::{PowerOperation, SitePowerBackend},
+    power_transition,
+};
+
 pub async fn exec(
+    power_backend: &SitePowerBackend,
     shasta_token: &str,
     shasta_base_url: &str,
     shasta_root_cert: &[u8],
     hsm_group_name_arg_opt: Option<&String>,
+    reason_opt: Option<String>,
+    timeout_secs: u64,
+    output_opt: Option<&String>,
 ) {
     let xname_vec = mesa::hsm::group::shasta::utils::get_member_vec_from_hsm_group_name(
         shasta_token,
@@ -12,12 +22,28 @@ pub async fn exec(
     )
     .await;
 
-    let _ = mesa::capmc::http_client::node_power_on::post_sync(
+    let node_power_transition_vec = power_transition::exec(
+        power_backend,
         shasta_token,
         shasta_base_url,
         shasta_root_cert,
-        xname_vec,
-        None,
+        &xname_vec,
+        PowerOperation::On,
+        false,
+        reason_opt.as_ref(),
+        timeout_secs,
     )
     .await;
+
+    power_transition::print_result(&node_power_transition_vec, output_opt);
+
+    // Audit
+    let jwt_claims = get_claims_from_jwt_token(shasta_token).unwrap();
+
+    log::info!(target: "app::audit", "User: {} ({}) ; Operation: Power on cluster {:?}", jwt_claims["name"].as_str().unwrap(), jwt_claims["preferred_username"].as_str().unwrap(), xname_vec);
+
+    if !power_transition::is_success(&node_power_transition_vec) {
+        eprintln!("Some nodes did not reach power state 'on'. Exit");
+        std::process::exit(1);
+    }
 }
@@ -1,8 +1,21 @@
+use crate::common::{
+    jwt_ops::get_claims_from_jwt_token,
+    power_backend::{PowerOperation, SitePowerBackend},
+    power_transition,
+    rolling_reboot::{self, RollingOptions},
+};
+
 pub async fn exec(
+    power_backend: &SitePowerBackend,
     shasta_token: &str,
     shasta_base_url: &str,
     shasta_root_cert: &[u8],
     hsm_group_name_arg_opt: Option<&String>,
+    reason_opt: Option<String>,
+    force: bool,
+    timeout_secs: u64,
+    output_opt: Option<&String>,
+    rolling_options_opt: Option<&RollingOptions>,
 ) {
     let xname_vec = mesa::hsm::group::shasta::utils::get_member_vec_from_hsm_group_name(
         shasta_token,
@@ -12,13 +25,50 @@ pub async fn exec(
     )
     .await;
 
-    let _ = mesa::capmc::http_client::node_power_reset::post_sync_vec(
-        shasta_token,
-        shasta_base_url,
-        shasta_root_cert,
-        xname_vec,
-        None,
-        true,
-    )
-    .await;
+    let result = if let Some(rolling_options) = rolling_options_opt {
+        // Reset nodes in batches, stop if any batch fails
+        rolling_reboot::exec(
+            power_backend,
+            shasta_token,
+            shasta_base_url,
+            shasta_root_cert,
+            &xname_vec,
+            force,
+            reason_opt.as_ref(),
+            rolling_options,
+        )
+        .await
+        .map_err(|error| format!("{}\nStopping rollout", error))
+    } else {
+        let node_power_transition_vec = power_transition::exec(
+            power_backend,
+            shasta_token,
+            shasta_base_url,
+            shasta_root_cert,
+            &xname_vec,
+            PowerOperation::Reset,
+            force,
+            reason_opt.as_ref(),
+            timeout_secs,
+        )
+        .await;
+
+        power_transition::print_result(&node_power_transition_vec, output_opt);
+
+        if power_transition::is_success(&node_power_transition_vec) {
+            Ok(())
+        } else {
+            Err("Some nodes did not reach power state 'on'".to_string())
+        }
+    };
+
+    // Audit
+    let jwt_claims = get_claims_from_jwt_token(shasta_token).unwrap();
+
+    log::info!(target: "app::audit", "User: {} ({}) ; Operation: Power reset cluster {:?}", jwt_claims["name"].as_str().unwrap(), jwt_claims["preferred_username"].as_str().unwrap(), xname_vec);
+
+    if let Err(error) = result {
+        eprintln!("{}. Exit", error);
+        std::process::exit(1);
+    }
 }
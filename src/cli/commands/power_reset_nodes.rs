@@ -1,18 +1,42 @@
+use crate::common::{
+    jwt_ops::get_claims_from_jwt_token,
+    power_backend::{PowerOperation, SitePowerBackend},
+    power_transition,
+};
+
 pub async fn exec(
+    power_backend: &SitePowerBackend,
     shasta_token: &str,
     shasta_base_url: &str,
     shasta_root_cert: &[u8],
     xname_vec: Vec<String>,
     reason_opt: Option<String>,
     force: bool,
+    timeout_secs: u64,
+    output_opt: Option<&String>,
 ) {
-    let _ = mesa::capmc::http_client::node_power_reset::post_sync_vec(
+    let node_power_transition_vec = power_transition::exec(
+        power_backend,
         shasta_token,
         shasta_base_url,
         shasta_root_cert,
-        xname_vec,
-        reason_opt,
+        &xname_vec,
+        PowerOperation::Reset,
         force,
+        reason_opt.as_ref(),
+        timeout_secs,
     )
     .await;
+
+    power_transition::print_result(&node_power_transition_vec, output_opt);
+
+    // Audit
+    let jwt_claims = get_claims_from_jwt_token(shasta_token).unwrap();
+
+    log::info!(target: "app::audit", "User: {} ({}) ; Operation: Power reset nodes {:?}", jwt_claims["name"].as_str().unwrap(), jwt_claims["preferred_username"].as_str().unwrap(), xname_vec);
+
+    if !power_transition::is_success(&node_power_transition_vec) {
+        eprintln!("Some nodes did not reach power state 'on'. Exit");
+        std::process::exit(1);
+    }
 }
@@ -0,0 +1,31 @@
+use crate::common::power_backend::SitePowerBackend;
+
+use super::power_status_nodes;
+
+/// Prints the power state of all nodes in a cluster
+pub async fn exec(
+    power_backend: &SitePowerBackend,
+    shasta_token: &str,
+    shasta_base_url: &str,
+    shasta_root_cert: &[u8],
+    hsm_group_name_arg_opt: Option<&String>,
+    output_opt: Option<&String>,
+) {
+    let xname_vec = mesa::hsm::group::shasta::utils::get_member_vec_from_hsm_group_name(
+        shasta_token,
+        shasta_base_url,
+        shasta_root_cert,
+        hsm_group_name_arg_opt.unwrap(),
+    )
+    .await;
+
+    power_status_nodes::exec(
+        power_backend,
+        shasta_token,
+        shasta_base_url,
+        shasta_root_cert,
+        xname_vec,
+        output_opt,
+    )
+    .await;
+}
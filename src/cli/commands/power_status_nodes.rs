@@ -0,0 +1,74 @@
+use comfy_table::Table;
+
+use crate::common::{
+    node_map,
+    power_backend::{PowerBackend, SitePowerBackend},
+};
+
+/// Prints the power state of a list of nodes
+pub async fn exec(
+    power_backend: &SitePowerBackend,
+    shasta_token: &str,
+    shasta_base_url: &str,
+    shasta_root_cert: &[u8],
+    xname_vec: Vec<String>,
+    output_opt: Option<&String>,
+) {
+    let power_state_map = match power_backend
+        .status(shasta_token, shasta_base_url, shasta_root_cert, &xname_vec)
+        .await
+    {
+        Ok(power_state_map) => power_state_map,
+        Err(error) => {
+            eprintln!(
+                "ERROR - Could not get nodes power status. Reason:\n{}\nExit",
+                error
+            );
+            std::process::exit(1);
+        }
+    };
+
+    let xname_hostname_map =
+        node_map::get_xname_hostname_map(shasta_token, shasta_base_url, shasta_root_cert).await;
+
+    let mut power_state_vec: Vec<(String, String, String)> = xname_vec
+        .iter()
+        .map(|xname| {
+            (
+                xname.clone(),
+                xname_hostname_map.get(xname).cloned().unwrap_or_default(),
+                power_state_map
+                    .get(xname)
+                    .cloned()
+                    .unwrap_or("undefined".to_string()),
+            )
+        })
+        .collect();
+
+    power_state_vec.sort();
+
+    if output_opt.is_some() && output_opt.unwrap().eq("json") {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(
+                &power_state_vec
+                    .iter()
+                    .map(|(xname, hostname, power_state)| {
+                        serde_json::json!({ "xname": xname, "hostname": hostname, "power_state": power_state })
+                    })
+                    .collect::<Vec<serde_json::Value>>()
+            )
+            .unwrap()
+        );
+    } else {
+        let mut table = Table::new();
+
+        table.set_header(vec!["XNAME", "Hostname", "Power State"]);
+
+        for (xname, hostname, power_state) in power_state_vec {
+            table.add_row(vec![xname, hostname, power_state]);
+        }
+
+        println!("{table}");
+    }
+}
@@ -0,0 +1,332 @@
+use std::{
+    collections::BTreeMap,
+    convert::Infallible,
+    net::SocketAddr,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server,
+};
+use mesa::common::authentication;
+
+use crate::common::prometheus::{self, Gauge};
+
+/// Serves nodes and CFS sessions metrics in Prometheus text format on '/metrics'. Metrics are
+/// collected every 'cache_ttl_secs' seconds and scrapes in between get the cached metrics, this
+/// way scraping manta does not add load to the CSM APIs. If a collection fails, then the metrics
+/// of the last successful collection are served and 'manta_up' is 0
+pub async fn exec(
+    keycloak_base_url: &str,
+    shasta_base_url: &str,
+    shasta_root_cert: &[u8],
+    hsm_name_vec: &[String],
+    listen: &str,
+    cache_ttl_secs: u64,
+) {
+    let addr: SocketAddr = match listen.parse() {
+        Ok(addr) => addr,
+        Err(error) => {
+            eprintln!("Listen address '{}' not valid: {}. Exit", listen, error);
+            std::process::exit(1);
+        }
+    };
+
+    let metrics: Arc<RwLock<String>> = Arc::new(RwLock::new(String::new()));
+
+    let metrics_server = metrics.clone();
+
+    let make_service = make_service_fn(move |_| {
+        let metrics = metrics_server.clone();
+
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| {
+                let metrics = metrics.clone();
+                async move { handle_request(req, metrics) }
+            }))
+        }
+    });
+
+    let server = match Server::try_bind(&addr) {
+        Ok(server) => server.serve(make_service),
+        Err(error) => {
+            eprintln!("Could not listen on {}: {}. Exit", addr, error);
+            std::process::exit(1);
+        }
+    };
+
+    println!(
+        "Serving metrics for HSM groups {} on http://{}/metrics",
+        hsm_name_vec.join(", "),
+        addr
+    );
+
+    tokio::spawn(async move {
+        if let Err(error) = server.await {
+            eprintln!("Metrics server error: {}. Exit", error);
+            std::process::exit(1);
+        }
+    });
+
+    let mut last_metrics = String::new();
+    let mut collect_error_count = 0;
+
+    loop {
+        let start = Instant::now();
+
+        // Collection runs in its own task so a panic in the CSM API helpers does not stop the
+        // server
+        let collect_metrics_handle = tokio::spawn({
+            let keycloak_base_url = keycloak_base_url.to_string();
+            let shasta_base_url = shasta_base_url.to_string();
+            let shasta_root_cert = shasta_root_cert.to_vec();
+            let hsm_name_vec = hsm_name_vec.to_vec();
+
+            async move {
+                collect_metrics(
+                    &keycloak_base_url,
+                    &shasta_base_url,
+                    &shasta_root_cert,
+                    &hsm_name_vec,
+                )
+                .await
+            }
+        });
+
+        let collect_metrics_rslt = match collect_metrics_handle.await {
+            Ok(collect_metrics_rslt) => collect_metrics_rslt,
+            Err(error) => Err(format!("Metrics collection failed: {}", error)),
+        };
+
+        let up = match collect_metrics_rslt {
+            Ok(collected_metrics) => {
+                log::info!("Metrics collected in {:?}", start.elapsed());
+                last_metrics = collected_metrics;
+                collect_error_count = 0;
+                true
+            }
+            Err(error) => {
+                log::error!("{}. Serving metrics of the last collection", error);
+                collect_error_count += 1;
+                false
+            }
+        };
+
+        let status_metrics = render_status_metrics(up, collect_error_count, start.elapsed());
+
+        *metrics.write().unwrap() = status_metrics + &last_metrics;
+
+        tokio::time::sleep(Duration::from_secs(cache_ttl_secs)).await;
+    }
+}
+
+/// Renders the metrics about the collection itself. 'collect_error_count' is the number of
+/// collections failed since the last successful one
+fn render_status_metrics(up: bool, collect_error_count: u64, collect_duration: Duration) -> String {
+    let mut up_gauge = Gauge::new("manta_up", "Whether the last metrics collection succeeded");
+    up_gauge.add_sample(&[], if up { 1.0 } else { 0.0 });
+
+    let mut collect_errors_gauge = Gauge::new(
+        "manta_collect_errors",
+        "Number of metrics collections failed since the last successful one",
+    );
+    collect_errors_gauge.add_sample(&[], collect_error_count as f64);
+
+    let mut collect_duration_gauge = Gauge::new(
+        "manta_collect_duration_seconds",
+        "Time spent collecting metrics",
+    );
+    collect_duration_gauge.add_sample(&[], collect_duration.as_secs_f64());
+
+    prometheus::render(&[up_gauge, collect_errors_gauge, collect_duration_gauge])
+}
+
+fn handle_request(
+    req: Request<Body>,
+    metrics: Arc<RwLock<String>>,
+) -> Result<Response<Body>, Infallible> {
+    let response = match (req.method(), req.uri().path()) {
+        (&Method::GET, "/metrics") => {
+            let metrics = metrics.read().unwrap().clone();
+
+            if metrics.is_empty() {
+                // First collection did not finish yet
+                Response::builder()
+                    .status(503)
+                    .body(Body::from("Metrics not collected yet"))
+            } else {
+                Response::builder()
+                    .header("Content-Type", "text/plain; version=0.0.4")
+                    .body(Body::from(metrics))
+            }
+        }
+        (&Method::GET, "/") => Response::builder().body(Body::from(
+            "<html><body><a href=\"/metrics\">Metrics</a></body></html>",
+        )),
+        _ => Response::builder()
+            .status(404)
+            .body(Body::from("Not found")),
+    };
+
+    Ok(response.unwrap())
+}
+
+/// Collects metrics for each HSM group. The API token is requested on each collection since the
+/// server runs longer than the token lifetime
+async fn collect_metrics(
+    keycloak_base_url: &str,
+    shasta_base_url: &str,
+    shasta_root_cert: &[u8],
+    hsm_name_vec: &[String],
+) -> Result<String, String> {
+    let shasta_token =
+        authentication::get_api_token(shasta_base_url, shasta_root_cert, keycloak_base_url)
+            .await
+            .map_err(|error| format!("Could not get API token: {}", error))?;
+
+    let mut hsm_group_nodes =
+        Gauge::new("manta_hsm_group_nodes", "Number of nodes in the HSM group");
+    let mut nodes_power_status = Gauge::new(
+        "manta_nodes_power_status",
+        "Number of nodes per power status",
+    );
+    let mut nodes_configuration_status = Gauge::new(
+        "manta_nodes_configuration_status",
+        "Number of nodes per CFS configuration status",
+    );
+    let mut node_error_count =
+        Gauge::new("manta_node_cfs_error_count", "CFS error count of the node");
+    let mut cfs_sessions = Gauge::new(
+        "manta_cfs_sessions",
+        "Number of CFS sessions per status and result",
+    );
+
+    for hsm_group_name in hsm_name_vec {
+        let hsm_group_member_vec =
+            mesa::hsm::group::shasta::utils::get_member_vec_from_hsm_group_name(
+                &shasta_token,
+                shasta_base_url,
+                shasta_root_cert,
+                hsm_group_name,
+            )
+            .await;
+
+        hsm_group_nodes.add_sample(
+            &[("hsm_group", hsm_group_name.as_str())],
+            hsm_group_member_vec.len() as f64,
+        );
+
+        let node_details_vec = mesa::node::utils::get_node_details(
+            &shasta_token,
+            shasta_base_url,
+            shasta_root_cert,
+            hsm_group_member_vec,
+        )
+        .await;
+
+        let mut power_status_count_map: BTreeMap<String, usize> = BTreeMap::new();
+        let mut configuration_status_count_map: BTreeMap<String, usize> = BTreeMap::new();
+
+        for node_details in &node_details_vec {
+            *power_status_count_map
+                .entry(node_details.power_status.to_lowercase())
+                .or_default() += 1;
+            *configuration_status_count_map
+                .entry(node_details.configuration_status.to_lowercase())
+                .or_default() += 1;
+
+            node_error_count.add_sample(
+                &[
+                    ("hsm_group", hsm_group_name.as_str()),
+                    ("xname", node_details.xname.as_str()),
+                ],
+                node_details.error_count.parse().unwrap_or(0.0),
+            );
+        }
+
+        for (power_status, count) in power_status_count_map {
+            nodes_power_status.add_sample(
+                &[
+                    ("hsm_group", hsm_group_name.as_str()),
+                    ("power_status", power_status.as_str()),
+                ],
+                count as f64,
+            );
+        }
+
+        for (configuration_status, count) in configuration_status_count_map {
+            nodes_configuration_status.add_sample(
+                &[
+                    ("hsm_group", hsm_group_name.as_str()),
+                    ("configuration_status", configuration_status.as_str()),
+                ],
+                count as f64,
+            );
+        }
+    }
+
+    let cfs_session_vec = mesa::cfs::session::mesa::http_client::get(
+        &shasta_token,
+        shasta_base_url,
+        shasta_root_cert,
+        None,
+        None,
+    )
+    .await
+    .map_err(|error| format!("Could not get CFS sessions: {}", error))?;
+
+    for hsm_group_name in hsm_name_vec {
+        let mut hsm_group_cfs_session_vec = cfs_session_vec.clone();
+
+        mesa::cfs::session::mesa::utils::filter_by_hsm(
+            &shasta_token,
+            shasta_base_url,
+            shasta_root_cert,
+            &mut hsm_group_cfs_session_vec,
+            &[hsm_group_name.to_string()],
+            None,
+        )
+        .await;
+
+        let mut cfs_session_count_map: BTreeMap<(String, String), usize> = BTreeMap::new();
+
+        for cfs_session in hsm_group_cfs_session_vec {
+            let session_status = cfs_session
+                .status
+                .as_ref()
+                .and_then(|status| status.session.as_ref());
+
+            let status = session_status
+                .and_then(|session| session.status.clone())
+                .unwrap_or_default();
+            let succeeded = session_status
+                .and_then(|session| session.succeeded.clone())
+                .unwrap_or_default();
+
+            *cfs_session_count_map
+                .entry((status, succeeded))
+                .or_default() += 1;
+        }
+
+        for ((status, succeeded), count) in cfs_session_count_map {
+            cfs_sessions.add_sample(
+                &[
+                    ("hsm_group", hsm_group_name.as_str()),
+                    ("status", status.as_str()),
+                    ("succeeded", succeeded.as_str()),
+                ],
+                count as f64,
+            );
+        }
+    }
+
+    Ok(prometheus::render(&[
+        hsm_group_nodes,
+        nodes_power_status,
+        nodes_configuration_status,
+        node_error_count,
+        cfs_sessions,
+    ]))
+}
@@ -0,0 +1,105 @@
+use crossterm::style::Stylize;
+use dialoguer::{theme::ColorfulTheme, Confirm};
+
+use crate::common::{
+    boot_params::{self, BootParamsEdit, BootParamsGroup},
+    jwt_ops::get_claims_from_jwt_token,
+};
+
+/// Adds, removes or replaces kernel parameters, kernel and initrd of a list of nodes. The changes
+/// are shown for each group of nodes sharing the same boot parameters and the user is asked for
+/// confirmation before updating BSS. Nodes are not rebooted
+pub async fn exec(
+    shasta_token: &str,
+    shasta_base_url: &str,
+    shasta_root_cert: &[u8],
+    xname_vec: &[String],
+    boot_params_edit: &BootParamsEdit,
+    assume_yes: bool,
+) {
+    let boot_params_group_vec = boot_params::get_boot_params_group_vec(
+        shasta_token,
+        shasta_base_url,
+        shasta_root_cert,
+        xname_vec,
+    )
+    .await;
+
+    // Keep only the groups of nodes which boot parameters change
+    let boot_params_change_vec: Vec<(BootParamsGroup, Vec<String>)> = boot_params_group_vec
+        .iter()
+        .filter_map(|boot_params_group| {
+            let new_boot_params_group = boot_params_edit.apply(boot_params_group);
+            let diff_vec = boot_params::get_diff(boot_params_group, &new_boot_params_group);
+
+            if diff_vec.is_empty() {
+                None
+            } else {
+                Some((new_boot_params_group, diff_vec))
+            }
+        })
+        .collect();
+
+    if boot_params_change_vec.is_empty() {
+        println!("Boot parameters already up to date. Nothing to do");
+        return;
+    }
+
+    for (new_boot_params_group, diff_vec) in &boot_params_change_vec {
+        println!("Nodes: {}", new_boot_params_group.xnames.join(", "));
+
+        for diff in diff_vec {
+            if diff.starts_with('-') {
+                println!("  {}", diff.as_str().red());
+            } else {
+                println!("  {}", diff.as_str().green());
+            }
+        }
+
+        println!();
+    }
+
+    if !assume_yes
+        && !Confirm::with_theme(&ColorfulTheme::default())
+            .with_prompt("Update boot parameters in BSS?")
+            .interact()
+            .unwrap()
+    {
+        println!("Cancelled by user. Aborting.");
+        std::process::exit(0);
+    }
+
+    for (new_boot_params_group, _) in &boot_params_change_vec {
+        let boot_params_put_rslt = mesa::bss::http_client::put(
+            shasta_base_url,
+            shasta_token,
+            shasta_root_cert,
+            &new_boot_params_group.xnames,
+            &new_boot_params_group.params,
+            &new_boot_params_group.kernel,
+            &new_boot_params_group.initrd,
+        )
+        .await;
+
+        match boot_params_put_rslt {
+            Ok(_) => println!(
+                "Boot parameters updated: {}",
+                new_boot_params_group.xnames.join(", ")
+            ),
+            Err(error) => {
+                eprintln!(
+                    "ERROR - Could not update boot parameters of {:?}. Reason:\n{}\nExit",
+                    new_boot_params_group.xnames, error
+                );
+                std::process::exit(1);
+            }
+        }
+    }
+
+    println!("Nodes need to be rebooted for the new boot parameters to take effect");
+
+    // Audit
+    let jwt_claims = get_claims_from_jwt_token(shasta_token).unwrap();
+
+    log::info!(target: "app::audit", "User: {} ({}) ; Operation: Update boot parameters {:?} {:?}", jwt_claims["name"].as_str().unwrap(), jwt_claims["preferred_username"].as_str().unwrap(), boot_params_edit, xname_vec);
+}
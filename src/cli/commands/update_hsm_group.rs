@@ -1,54 +1,56 @@
 use dialoguer::{theme::ColorfulTheme, Confirm};
-use mesa::{capmc, cfs, hsm};
+use mesa::{cfs, hsm};
 
-use crate::common::ims_ops::get_image_id_from_cfs_configuration_name;
+use crate::common::{
+    cfs_component::{self, CfsComponentPatch},
+    ims_ops::get_image_id_from_cfs_configuration_name,
+    jwt_ops::get_claims_from_jwt_token,
+    power_backend::{PowerBackend, PowerOperation, SitePowerBackend},
+    rolling_reboot::{self, RollingOptions},
+};
 
 /// Updates boot params and desired configuration for all nodes that belongs to a HSM group
 /// If boot params defined, then nodes in HSM group will be rebooted
 pub async fn exec(
+    power_backend: &SitePowerBackend,
     shasta_token: &str,
     shasta_base_url: &str,
     shasta_root_cert: &[u8],
     boot_image_configuration_opt: Option<&String>,
     desired_configuration_opt: Option<&String>,
     hsm_group_name: &String,
+    cfs_component_patch: &CfsComponentPatch,
+    rolling_options_opt: Option<&RollingOptions>,
 ) {
     let need_restart = boot_image_configuration_opt.is_some();
 
-    let desired_configuration_detail_list_rslt = cfs::configuration::mesa::http_client::get(
-        shasta_token,
-        shasta_base_url,
-        shasta_root_cert,
-        desired_configuration_opt.map(|elem| elem.as_str()),
-    )
-    .await;
-
-    // Check desired configuration exists
-    if desired_configuration_detail_list_rslt.is_ok()
-        && !desired_configuration_detail_list_rslt
-            .as_ref()
-            .unwrap()
-            .is_empty()
-    {
-        let desired_configuration_detail_list = desired_configuration_detail_list_rslt.unwrap();
-
-        log::debug!(
-            "CFS configuration resp:\n{:#?}",
-            desired_configuration_detail_list
-        );
-
-        let _ = desired_configuration_detail_list
-            .first()
-            .unwrap()
-            .name
-            .clone();
-    } else {
-        eprintln!(
-            "Desired configuration {} does not exists. Exit",
-            desired_configuration_opt.unwrap()
-        );
-        std::process::exit(1);
-    };
+    // Check desired configuration exists, only if the user asked to change it
+    if let Some(desired_configuration) = desired_configuration_opt {
+        match cfs::configuration::mesa::http_client::get(
+            shasta_token,
+            shasta_base_url,
+            shasta_root_cert,
+            Some(desired_configuration.as_str()),
+        )
+        .await
+        {
+            Ok(desired_configuration_detail_list)
+                if !desired_configuration_detail_list.is_empty() =>
+            {
+                log::debug!(
+                    "CFS configuration resp:\n{:#?}",
+                    desired_configuration_detail_list
+                );
+            }
+            _ => {
+                eprintln!(
+                    "Desired configuration {} does not exists. Exit",
+                    desired_configuration
+                );
+                std::process::exit(1);
+            }
+        }
+    }
 
     // Get nodes members of HSM group
     // Get HSM group details
@@ -165,42 +167,77 @@ pub async fn exec(
         .await;
     }
 
-    // Check if need to reboot
-    if need_restart {
-        // Create BOS session. Note: reboot operation shuts down the nodes and don't bring them back
-        // up... hence we will split the reboot into 2 operations shutdown and start
-
-        log::info!("Restarting nodes");
-
-        // Create CAPMC operation shutdown
-        let capmc_shutdown_nodes_resp = capmc::http_client::node_power_off::post_sync(
+    // Update CFS components
+    if !cfs_component_patch.is_empty() {
+        cfs_component::apply_patch(
             shasta_token,
             shasta_base_url,
             shasta_root_cert,
-            nodes.clone(),
-            Some("Update node boot params and/or desired configuration".to_string()),
-            true,
+            &nodes,
+            cfs_component_patch,
         )
         .await;
+    }
 
-        log::debug!(
-            "CAPMC shutdown nodes response:\n{:#?}",
-            capmc_shutdown_nodes_resp
-        );
+    // Audit
+    let jwt_claims = get_claims_from_jwt_token(shasta_token).unwrap();
 
-        // Create CAPMC operation to start
-        let capmc_start_nodes_resp = capmc::http_client::node_power_on::post(
-            shasta_token,
-            shasta_base_url,
-            shasta_root_cert,
-            nodes,
-            Some("Update node boot params and/or desired configuration".to_string()),
-        )
-        .await;
+    log::info!(target: "app::audit", "User: {} ({}) ; Operation: Update HSM group {} boot image {:?} desired configuration {:?} CFS components {} {:?}", jwt_claims["name"].as_str().unwrap(), jwt_claims["preferred_username"].as_str().unwrap(), hsm_group_name, boot_image_configuration_opt, desired_configuration_opt, cfs_component_patch.to_value(), nodes);
 
-        log::debug!(
-            "CAPMC starting nodes response:\n{:#?}",
-            capmc_start_nodes_resp
-        );
+    // Check if need to reboot
+    if need_restart {
+        // Create BOS session. Note: reboot operation shuts down the nodes and don't bring them back
+        // up... hence we will split the reboot into 2 operations shutdown and start
+
+        log::info!("Restarting nodes");
+
+        if let Some(rolling_options) = rolling_options_opt {
+            // Reboot nodes in batches, stop if any batch fails
+            if let Err(error) = rolling_reboot::exec(
+                power_backend,
+                shasta_token,
+                shasta_base_url,
+                shasta_root_cert,
+                &nodes,
+                true,
+                Some(&"Update node boot params and/or desired configuration".to_string()),
+                rolling_options,
+            )
+            .await
+            {
+                eprintln!("{}\nStopping rollout. Exit", error);
+                std::process::exit(1);
+            }
+        } else {
+            // Create power operation shutdown
+            let power_off_nodes_resp = power_backend
+                .transition_sync(
+                    shasta_token,
+                    shasta_base_url,
+                    shasta_root_cert,
+                    &nodes,
+                    PowerOperation::Off,
+                    true,
+                    Some(&"Update node boot params and/or desired configuration".to_string()),
+                )
+                .await;
+
+            log::debug!("Power off nodes response:\n{:#?}", power_off_nodes_resp);
+
+            // Create power operation to start
+            let power_on_nodes_resp = power_backend
+                .transition(
+                    shasta_token,
+                    shasta_base_url,
+                    shasta_root_cert,
+                    &nodes,
+                    PowerOperation::On,
+                    false,
+                    Some(&"Update node boot params and/or desired configuration".to_string()),
+                )
+                .await;
+
+            log::debug!("Power on nodes response:\n{:#?}", power_on_nodes_resp);
+        }
     }
 }
@@ -1,9 +1,16 @@
-use crate::common::ims_ops::get_image_id_from_cfs_configuration_name;
+use crate::common::{
+    cfs_component::{self, CfsComponentPatch},
+    ims_ops::get_image_id_from_cfs_configuration_name,
+    jwt_ops::get_claims_from_jwt_token,
+    power_backend::{PowerBackend, PowerOperation, SitePowerBackend},
+    rolling_reboot::{self, RollingOptions},
+};
 
 use dialoguer::{theme::ColorfulTheme, Confirm};
-use mesa::{capmc, cfs, node::utils::validate_xnames};
+use mesa::{cfs, node::utils::validate_xnames};
 
 pub async fn exec(
+    power_backend: &SitePowerBackend,
     shasta_token: &str,
     shasta_base_url: &str,
     shasta_root_cert: &[u8],
@@ -11,43 +18,38 @@ pub async fn exec(
     boot_image_configuration_opt: Option<&String>,
     desired_configuration_opt: Option<&String>,
     xnames: Vec<&str>,
+    cfs_component_patch: &CfsComponentPatch,
+    rolling_options_opt: Option<&RollingOptions>,
 ) {
     let need_restart = boot_image_configuration_opt.is_some();
 
-    let desired_configuration_detail_list_rslt = cfs::configuration::mesa::http_client::get(
-        shasta_token,
-        shasta_base_url,
-        shasta_root_cert,
-        desired_configuration_opt.map(|elem| elem.as_str()),
-    )
-    .await;
-
-    // Check desired configuration exists
-    if desired_configuration_detail_list_rslt.is_ok()
-        && !desired_configuration_detail_list_rslt
-            .as_ref()
-            .unwrap()
-            .is_empty()
-    {
-        let desired_configuration_detail_list = desired_configuration_detail_list_rslt.unwrap();
-
-        log::debug!(
-            "CFS configuration resp:\n{:#?}",
-            desired_configuration_detail_list
-        );
-
-        desired_configuration_detail_list
-            .first()
-            .unwrap()
-            .name
-            .clone()
-    } else {
-        eprintln!(
-            "Desired configuration {} does not exists. Exit",
-            desired_configuration_opt.unwrap()
-        );
-        std::process::exit(1);
-    };
+    // Check desired configuration exists, only if the user asked to change it
+    if let Some(desired_configuration) = desired_configuration_opt {
+        match cfs::configuration::mesa::http_client::get(
+            shasta_token,
+            shasta_base_url,
+            shasta_root_cert,
+            Some(desired_configuration.as_str()),
+        )
+        .await
+        {
+            Ok(desired_configuration_detail_list)
+                if !desired_configuration_detail_list.is_empty() =>
+            {
+                log::debug!(
+                    "CFS configuration resp:\n{:#?}",
+                    desired_configuration_detail_list
+                );
+            }
+            _ => {
+                eprintln!(
+                    "Desired configuration {} does not exists. Exit",
+                    desired_configuration
+                );
+                std::process::exit(1);
+            }
+        }
+    }
 
     // Check user has provided valid XNAMES
     if hsm_group_name.is_some()
@@ -160,41 +162,79 @@ pub async fn exec(
         .await;
     }
 
-    // Check if need to reboot
-    if need_restart {
-        log::info!("Restarting nodes");
-
-        let nodes: Vec<String> = xnames.into_iter().map(|xname| xname.to_string()).collect();
-
-        // Create CAPMC operation shutdown
-        let capmc_shutdown_nodes_resp = capmc::http_client::node_power_off::post_sync(
+    // Update CFS components
+    if !cfs_component_patch.is_empty() {
+        cfs_component::apply_patch(
             shasta_token,
             shasta_base_url,
             shasta_root_cert,
-            nodes.clone(),
-            Some("Update node boot params and/or desired configuration".to_string()),
-            true,
+            &xnames
+                .iter()
+                .map(|xname| xname.to_string())
+                .collect::<Vec<String>>(),
+            cfs_component_patch,
         )
         .await;
+    }
 
-        log::debug!(
-            "CAPMC shutdown nodes response:\n{:#?}",
-            capmc_shutdown_nodes_resp
-        );
+    // Audit
+    let jwt_claims = get_claims_from_jwt_token(shasta_token).unwrap();
 
-        // Create CAPMC operation to start
-        let capmc_start_nodes_resp = capmc::http_client::node_power_on::post(
-            shasta_token,
-            shasta_base_url,
-            shasta_root_cert,
-            nodes,
-            Some("Update node boot params and/or desired configuration".to_string()),
-        )
-        .await;
+    log::info!(target: "app::audit", "User: {} ({}) ; Operation: Update nodes boot image {:?} desired configuration {:?} CFS components {} {:?}", jwt_claims["name"].as_str().unwrap(), jwt_claims["preferred_username"].as_str().unwrap(), boot_image_configuration_opt, desired_configuration_opt, cfs_component_patch.to_value(), xnames);
 
-        log::debug!(
-            "CAPMC starting nodes response:\n{:#?}",
-            capmc_start_nodes_resp
-        );
+    // Check if need to reboot
+    if need_restart {
+        log::info!("Restarting nodes");
+
+        let nodes: Vec<String> = xnames.into_iter().map(|xname| xname.to_string()).collect();
+
+        if let Some(rolling_options) = rolling_options_opt {
+            // Reboot nodes in batches, stop if any batch fails
+            if let Err(error) = rolling_reboot::exec(
+                power_backend,
+                shasta_token,
+                shasta_base_url,
+                shasta_root_cert,
+                &nodes,
+                true,
+                Some(&"Update node boot params and/or desired configuration".to_string()),
+                rolling_options,
+            )
+            .await
+            {
+                eprintln!("{}\nStopping rollout. Exit", error);
+                std::process::exit(1);
+            }
+        } else {
+            // Create power operation shutdown
+            let power_off_nodes_resp = power_backend
+                .transition_sync(
+                    shasta_token,
+                    shasta_base_url,
+                    shasta_root_cert,
+                    &nodes,
+                    PowerOperation::Off,
+                    true,
+                    Some(&"Update node boot params and/or desired configuration".to_string()),
+                )
+                .await;
+
+            log::debug!("Power off nodes response:\n{:#?}", power_off_nodes_resp);
+
+            // Create power operation to start
+            let power_on_nodes_resp = power_backend
+                .transition(
+                    shasta_token,
+                    shasta_base_url,
+                    shasta_root_cert,
+                    &nodes,
+                    PowerOperation::On,
+                    false,
+                    Some(&"Update node boot params and/or desired configuration".to_string()),
+                )
+                .await;
+
+            log::debug!("Power on nodes response:\n{:#?}", power_on_nodes_resp);
+        }
     }
 }
@@ -1,21 +1,37 @@
-use std::io::IsTerminal;
+use std::{io::IsTerminal, path::PathBuf};
 
-use clap::ArgMatches;
+use clap::{parser::ValueSource, ArgMatches};
 use config::Config;
 use k8s_openapi::chrono;
 use mesa::common::authentication;
 
+use crate::common::{
+    boot_params::BootParamsEdit,
+    cfs_component::CfsComponentPatch,
+    cfs_layer_ops::{self, LayerSpec},
+    cluster_health::{self, Threshold},
+    console_escape, console_recording,
+    console_script::{self, ConsoleScript},
+    hostlist,
+    power_backend::SitePowerBackend,
+    power_transition,
+    rolling_reboot::{self, RollingOptions},
+};
+
 use super::commands::{
     self, add_hw_component_cluster, add_nodes, apply_cluster, apply_configuration,
-    apply_ephemeral_env, apply_hw_cluster, apply_image, apply_session, config_set_hsm,
-    config_set_log, config_set_site,
+    apply_ephemeral_env, apply_hw_cluster, apply_image, apply_session, apply_session_from,
+    config_set_hsm, config_set_log, config_set_site,
     config_show::{self, get_hsm_name_available_from_jwt_or_all},
-    config_unset_auth, config_unset_hsm, console_cfs_session_image_target_ansible, console_node,
+    config_unset_auth, config_unset_hsm, console_cfs_session_image_target_ansible, console_log,
+    console_node, console_replay, console_tail,
     delete_data_related_to_cfs_configuration::delete_data_related_cfs_configuration,
-    get_configuration, get_hsm, get_hw_configuration_node, get_images, get_nodes, get_session,
-    get_template, migrate_backup, power_off_cluster, power_off_nodes, power_on_cluster,
-    power_on_nodes, power_reset_cluster, power_reset_nodes, remove_hw_component_cluster,
-    remove_nodes, update_hsm_group, update_node,
+    delete_ephemeral_env, delete_images, delete_public_keys, get_boot_params, get_configuration,
+    get_ephemeral_env, get_hsm, get_hw_configuration_node, get_images, get_node_history,
+    get_node_map, get_nodes, get_public_keys, get_session, get_template, migrate_backup,
+    power_off_cluster, power_off_nodes, power_on_cluster, power_on_nodes, power_reset_cluster,
+    power_reset_nodes, power_status_cluster, power_status_nodes, remove_hw_component_cluster,
+    remove_nodes, serve_metrics, update_boot_params, update_hsm_group, update_node,
 };
 
 pub async fn process_cli(
@@ -84,11 +100,24 @@ pub async fn process_cli(
                 config_unset_auth::exec().await;
             }
         }
+    } else if let Some(cli_console_replay) = cli_root
+        .subcommand_matches("console")
+        .and_then(|cli_console| cli_console.subcommand_matches("replay"))
+    {
+        // Replaying a recording does not need access to CSM
+        console_replay::exec(
+            cli_console_replay.get_one::<PathBuf>("FILE").unwrap(),
+            *cli_console_replay.get_one::<f64>("speed").unwrap(),
+            cli_console_replay.get_one::<f64>("max-idle").copied(),
+        )
+        .await;
     } else {
         let shasta_token =
             &authentication::get_api_token(shasta_base_url, shasta_root_cert, keycloak_base_url)
                 .await?;
 
+        let power_backend = SitePowerBackend::from_settings(settings);
+
         /* let hsm_name_available_vec = config_show::get_hsm_name_available_from_jwt(
             shasta_token,
             shasta_base_url,
@@ -121,19 +150,24 @@ pub async fn process_cli(
                     .await;
 
                     power_on_cluster::exec(
+                        &power_backend,
                         shasta_token,
                         shasta_base_url,
                         shasta_root_cert,
                         target_hsm_group_vec.first(),
+                        cli_power_on_cluster.get_one::<String>("reason").cloned(),
+                        *cli_power_on_cluster.get_one::<u64>("timeout").unwrap(),
+                        cli_power_on_cluster.get_one::<String>("output"),
                     )
                     .await;
                 } else if let Some(cli_power_on_node) = cli_power_on.subcommand_matches("node") {
-                    let xname_vec: Vec<String> = cli_power_on_node
-                        .get_one::<String>("NODE_NAME")
-                        .unwrap()
-                        .split(',')
-                        .map(|xname| xname.trim().to_string())
-                        .collect();
+                    let xname_vec = get_xname_vec_from_hostlist(
+                        shasta_token,
+                        shasta_base_url,
+                        shasta_root_cert,
+                        cli_power_on_node.get_one::<String>("NODE_NAME").unwrap(),
+                    )
+                    .await;
 
                     let _ = validate_target_hsm_members(
                         shasta_token,
@@ -144,11 +178,14 @@ pub async fn process_cli(
                     .await;
 
                     power_on_nodes::exec(
+                        &power_backend,
                         shasta_token,
                         shasta_base_url,
                         shasta_root_cert,
                         xname_vec,
                         cli_power_on_node.get_one::<String>("reason").cloned(),
+                        *cli_power_on_node.get_one::<u64>("timeout").unwrap(),
+                        cli_power_on_node.get_one::<String>("output"),
                     )
                     .await;
                 }
@@ -167,19 +204,25 @@ pub async fn process_cli(
                     .await;
 
                     power_off_cluster::exec(
+                        &power_backend,
                         shasta_token,
                         shasta_base_url,
                         shasta_root_cert,
                         target_hsm_group_vec.first(),
+                        cli_power_off_cluster.get_one::<String>("reason").cloned(),
+                        *cli_power_off_cluster.get_one::<bool>("force").unwrap(),
+                        *cli_power_off_cluster.get_one::<u64>("timeout").unwrap(),
+                        cli_power_off_cluster.get_one::<String>("output"),
                     )
                     .await;
                 } else if let Some(cli_power_off_node) = cli_power_off.subcommand_matches("node") {
-                    let xname_vec: Vec<String> = cli_power_off_node
-                        .get_one::<String>("NODE_NAME")
-                        .unwrap()
-                        .split(',')
-                        .map(|xname| xname.trim().to_string())
-                        .collect();
+                    let xname_vec = get_xname_vec_from_hostlist(
+                        shasta_token,
+                        shasta_base_url,
+                        shasta_root_cert,
+                        cli_power_off_node.get_one::<String>("NODE_NAME").unwrap(),
+                    )
+                    .await;
 
                     let _ = validate_target_hsm_members(
                         shasta_token,
@@ -190,12 +233,15 @@ pub async fn process_cli(
                     .await;
 
                     power_off_nodes::exec(
+                        &power_backend,
                         shasta_token,
                         shasta_base_url,
                         shasta_root_cert,
                         xname_vec,
                         cli_power_off_node.get_one::<String>("reason").cloned(),
                         *cli_power_off_node.get_one::<bool>("force").unwrap(),
+                        *cli_power_off_node.get_one::<u64>("timeout").unwrap(),
+                        cli_power_off_node.get_one::<String>("output"),
                     )
                     .await;
                 }
@@ -215,21 +261,32 @@ pub async fn process_cli(
                     .await;
 
                     power_reset_cluster::exec(
+                        &power_backend,
                         shasta_token,
                         shasta_base_url,
                         shasta_root_cert,
                         target_hsm_group_vec.first(),
+                        cli_power_reset_cluster.get_one::<String>("reason").cloned(),
+                        *cli_power_reset_cluster.get_one::<bool>("force").unwrap(),
+                        *cli_power_reset_cluster.get_one::<u64>("timeout").unwrap(),
+                        cli_power_reset_cluster.get_one::<String>("output"),
+                        get_rolling_options(
+                            cli_power_reset_cluster,
+                            *cli_power_reset_cluster.get_one::<u64>("timeout").unwrap(),
+                        )
+                        .as_ref(),
                     )
                     .await;
                 } else if let Some(cli_power_reset_node) =
                     cli_power_reset.subcommand_matches("node")
                 {
-                    let xname_vec: Vec<String> = cli_power_reset_node
-                        .get_one::<String>("NODE_NAME")
-                        .unwrap()
-                        .split(',')
-                        .map(|xname| xname.trim().to_string())
-                        .collect();
+                    let xname_vec = get_xname_vec_from_hostlist(
+                        shasta_token,
+                        shasta_base_url,
+                        shasta_root_cert,
+                        cli_power_reset_node.get_one::<String>("NODE_NAME").unwrap(),
+                    )
+                    .await;
 
                     let _ = validate_target_hsm_members(
                         shasta_token,
@@ -240,12 +297,71 @@ pub async fn process_cli(
                     .await;
 
                     power_reset_nodes::exec(
+                        &power_backend,
                         shasta_token,
                         shasta_base_url,
                         shasta_root_cert,
                         xname_vec,
                         cli_power_reset_node.get_one::<String>("reason").cloned(),
                         *cli_power_reset_node.get_one::<bool>("force").unwrap(),
+                        *cli_power_reset_node.get_one::<u64>("timeout").unwrap(),
+                        cli_power_reset_node.get_one::<String>("output"),
+                    )
+                    .await;
+                }
+            } else if let Some(cli_power_status) = cli_power.subcommand_matches("status") {
+                if let Some(cli_power_status_cluster) =
+                    cli_power_status.subcommand_matches("cluster")
+                {
+                    let hsm_group_name_arg_opt =
+                        cli_power_status_cluster.get_one::<String>("CLUSTER_NAME");
+
+                    let target_hsm_group_vec = get_target_hsm_group_vec(
+                        shasta_token,
+                        shasta_base_url,
+                        shasta_root_cert,
+                        hsm_group_name_arg_opt,
+                        settings_hsm_group_name_opt,
+                    )
+                    .await;
+
+                    power_status_cluster::exec(
+                        &power_backend,
+                        shasta_token,
+                        shasta_base_url,
+                        shasta_root_cert,
+                        target_hsm_group_vec.first(),
+                        cli_power_status_cluster.get_one::<String>("output"),
+                    )
+                    .await;
+                } else if let Some(cli_power_status_node) =
+                    cli_power_status.subcommand_matches("node")
+                {
+                    let xname_vec = get_xname_vec_from_hostlist(
+                        shasta_token,
+                        shasta_base_url,
+                        shasta_root_cert,
+                        cli_power_status_node
+                            .get_one::<String>("NODE_NAME")
+                            .unwrap(),
+                    )
+                    .await;
+
+                    let _ = validate_target_hsm_members(
+                        shasta_token,
+                        shasta_base_url,
+                        shasta_root_cert,
+                        xname_vec.clone(),
+                    )
+                    .await;
+
+                    power_status_nodes::exec(
+                        &power_backend,
+                        shasta_token,
+                        shasta_base_url,
+                        shasta_root_cert,
+                        xname_vec,
+                        cli_power_status_node.get_one::<String>("output"),
                     )
                     .await;
                 }
@@ -275,13 +391,21 @@ pub async fn process_cli(
                 )
                 .await;
             } else if let Some(cli_add_nodes) = cli_add.subcommand_matches("nodes") {
+                let xname_vec = get_xname_vec_from_hostlist(
+                    shasta_token,
+                    shasta_base_url,
+                    shasta_root_cert,
+                    cli_add_nodes.get_one::<String>("XNAMES").unwrap(),
+                )
+                .await;
+
                 add_nodes::exec(
                     shasta_token,
                     shasta_base_url,
                     shasta_root_cert,
                     cli_add_nodes.get_one::<String>("cluster").unwrap(),
                     "nodes_free",
-                    cli_add_nodes.get_one::<String>("XNAMES").unwrap(),
+                    &xname_vec.join(","),
                 )
                 .await;
             }
@@ -311,13 +435,21 @@ pub async fn process_cli(
                 )
                 .await;
             } else if let Some(cli_remove_nodes) = cli_remove.subcommand_matches("nodes") {
+                let xname_vec = get_xname_vec_from_hostlist(
+                    shasta_token,
+                    shasta_base_url,
+                    shasta_root_cert,
+                    cli_remove_nodes.get_one::<String>("XNAMES").unwrap(),
+                )
+                .await;
+
                 remove_nodes::exec(
                     shasta_token,
                     shasta_base_url,
                     shasta_root_cert,
                     cli_remove_nodes.get_one::<String>("cluster").unwrap(),
                     "nodes_free",
-                    cli_remove_nodes.get_one::<String>("XNAMES").unwrap(),
+                    &xname_vec.join(","),
                 )
                 .await;
             }
@@ -353,14 +485,24 @@ pub async fn process_cli(
                         .get_one::<String>("XNAMES")
                         .expect("HSM group name is needed at this point");
 
-                    let xname_vec: Vec<String> =
-                        xnames.split(',').map(|xname| xname.to_string()).collect();
+                    let xname_vec = get_xname_vec_from_hostlist(
+                        shasta_token,
+                        shasta_base_url,
+                        shasta_root_cert,
+                        xnames,
+                    )
+                    .await;
+
+                    if xname_vec.len() != 1 {
+                        eprintln!("Only one node can be provided. Exit");
+                        std::process::exit(1);
+                    }
 
                     validate_target_hsm_members(
                         shasta_token,
                         shasta_base_url,
                         shasta_root_cert,
-                        xname_vec,
+                        xname_vec.clone(),
                     )
                     .await;
 
@@ -368,7 +510,7 @@ pub async fn process_cli(
                         shasta_token,
                         shasta_base_url,
                         shasta_root_cert,
-                        xnames,
+                        xname_vec.first().unwrap(),
                         cli_get_hw_configuration_node.get_one::<String>("type"),
                         cli_get_hw_configuration_node.get_one::<String>("output"),
                     )
@@ -485,21 +627,44 @@ pub async fn process_cli(
                 )
                 .await;
 
-                get_nodes::exec(
-                    shasta_token,
-                    shasta_base_url,
-                    shasta_root_cert,
-                    &target_hsm_group_vec,
-                    *cli_get_node
-                        .get_one::<bool>("nids-only-one-line")
-                        .unwrap_or(&false),
-                    *cli_get_node
-                        .get_one::<bool>("xnames-only-one-line")
-                        .unwrap_or(&false),
-                    cli_get_node.get_one::<String>("output"),
-                    *cli_get_node.get_one::<bool>("status").unwrap_or(&false),
-                )
-                .await;
+                if *cli_get_node.get_one::<bool>("health").unwrap_or(&false) {
+                    get_nodes::exec_health(
+                        shasta_token,
+                        shasta_base_url,
+                        shasta_root_cert,
+                        &target_hsm_group_vec,
+                        &get_threshold(cli_get_node, "warning"),
+                        &get_threshold(cli_get_node, "critical"),
+                        cli_get_node.get_one::<String>("output"),
+                    )
+                    .await;
+                } else if let Some(interval_secs) = cli_get_node.get_one::<u64>("watch") {
+                    get_nodes::exec_watch(
+                        shasta_token,
+                        shasta_base_url,
+                        shasta_root_cert,
+                        &target_hsm_group_vec,
+                        *interval_secs,
+                        cli_get_node.get_one::<String>("until"),
+                    )
+                    .await;
+                } else {
+                    get_nodes::exec(
+                        shasta_token,
+                        shasta_base_url,
+                        shasta_root_cert,
+                        &target_hsm_group_vec,
+                        *cli_get_node
+                            .get_one::<bool>("nids-only-one-line")
+                            .unwrap_or(&false),
+                        *cli_get_node
+                            .get_one::<bool>("xnames-only-one-line")
+                            .unwrap_or(&false),
+                        cli_get_node.get_one::<String>("output"),
+                        *cli_get_node.get_one::<bool>("status").unwrap_or(&false),
+                    )
+                    .await;
+                }
             } else if let Some(cli_get_node) = cli_get.subcommand_matches("nodes") {
                 let hsm_group_name_arg_opt = cli_get_node.get_one::<String>("HSM_GROUP_NAME");
 
@@ -512,19 +677,112 @@ pub async fn process_cli(
                 )
                 .await;
 
-                get_nodes::exec(
+                if let Some(interval_secs) = cli_get_node.get_one::<u64>("watch") {
+                    get_nodes::exec_watch(
+                        shasta_token,
+                        shasta_base_url,
+                        shasta_root_cert,
+                        &target_hsm_group_vec,
+                        *interval_secs,
+                        cli_get_node.get_one::<String>("until"),
+                    )
+                    .await;
+                } else {
+                    get_nodes::exec(
+                        shasta_token,
+                        shasta_base_url,
+                        shasta_root_cert,
+                        &target_hsm_group_vec,
+                        *cli_get_node
+                            .get_one::<bool>("nids-only-one-line")
+                            .unwrap_or(&false),
+                        *cli_get_node
+                            .get_one::<bool>("xnames-only-one-line")
+                            .unwrap_or(&false),
+                        cli_get_node.get_one::<String>("output"),
+                        false,
+                    )
+                    .await;
+                }
+            } else if let Some(cli_get_node_map) = cli_get.subcommand_matches("node-map") {
+                get_node_map::exec(
                     shasta_token,
                     shasta_base_url,
                     shasta_root_cert,
-                    &target_hsm_group_vec,
-                    *cli_get_node
-                        .get_one::<bool>("nids-only-one-line")
-                        .unwrap_or(&false),
-                    *cli_get_node
-                        .get_one::<bool>("xnames-only-one-line")
+                    *cli_get_node_map
+                        .get_one::<bool>("refresh")
                         .unwrap_or(&false),
-                    cli_get_node.get_one::<String>("output"),
-                    false,
+                    cli_get_node_map.get_one::<String>("output"),
+                )
+                .await;
+            } else if let Some(cli_get_boot_params) = cli_get.subcommand_matches("boot-params") {
+                let xname_vec = get_xname_vec_from_hostlist(
+                    shasta_token,
+                    shasta_base_url,
+                    shasta_root_cert,
+                    cli_get_boot_params.get_one::<String>("XNAMES").unwrap(),
+                )
+                .await;
+
+                validate_target_hsm_members(
+                    shasta_token,
+                    shasta_base_url,
+                    shasta_root_cert,
+                    xname_vec.clone(),
+                )
+                .await;
+
+                get_boot_params::exec(
+                    shasta_token,
+                    shasta_base_url,
+                    shasta_root_cert,
+                    &xname_vec,
+                    cli_get_boot_params.get_one::<String>("output"),
+                )
+                .await;
+            } else if let Some(cli_get_ephemeral_envs) =
+                cli_get.subcommand_matches("ephemeral-environments")
+            {
+                get_ephemeral_env::exec(
+                    shasta_token,
+                    shasta_base_url,
+                    shasta_root_cert,
+                    cli_get_ephemeral_envs.get_one::<String>("user"),
+                    cli_get_ephemeral_envs.get_one::<String>("output"),
+                )
+                .await;
+            } else if let Some(cli_get_public_keys) = cli_get.subcommand_matches("public-keys") {
+                get_public_keys::exec(
+                    shasta_token,
+                    shasta_base_url,
+                    shasta_root_cert,
+                    cli_get_public_keys.get_one::<String>("output"),
+                )
+                .await;
+            } else if let Some(cli_get_node_history) = cli_get.subcommand_matches("node-history") {
+                let xname_vec = get_xname_vec_from_hostlist(
+                    shasta_token,
+                    shasta_base_url,
+                    shasta_root_cert,
+                    cli_get_node_history.get_one::<String>("XNAMES").unwrap(),
+                )
+                .await;
+
+                validate_target_hsm_members(
+                    shasta_token,
+                    shasta_base_url,
+                    shasta_root_cert,
+                    xname_vec.clone(),
+                )
+                .await;
+
+                get_node_history::exec(
+                    shasta_token,
+                    shasta_base_url,
+                    shasta_root_cert,
+                    &xname_vec,
+                    cli_get_node_history.get_one::<usize>("limit"),
+                    cli_get_node_history.get_one::<String>("output"),
                 )
                 .await;
             } else if let Some(cli_get_hsm_groups) = cli_get.subcommand_matches("hsm-groups") {
@@ -558,12 +816,37 @@ pub async fn process_cli(
                 )
                 .await;
 
+                let since_opt = cli_get_images.get_one::<String>("since").map(|since| {
+                    match chrono::NaiveDate::parse_from_str(since, "%Y-%m-%d") {
+                        Ok(date) => date.and_hms_opt(0, 0, 0).unwrap(),
+                        Err(_) => {
+                            eprintln!("Date '{}' not valid, use format %Y-%m-%d. Exit", since);
+                            std::process::exit(1);
+                        }
+                    }
+                });
+
+                let in_use_opt = if *cli_get_images.get_one::<bool>("in-use").unwrap_or(&false) {
+                    Some(true)
+                } else if *cli_get_images.get_one::<bool>("unused").unwrap_or(&false) {
+                    Some(false)
+                } else {
+                    None
+                };
+
                 get_images::exec(
                     shasta_token,
                     shasta_base_url,
                     shasta_root_cert,
                     &target_hsm_group_vec,
                     cli_get_images.get_one::<u8>("limit"),
+                    cli_get_images.get_one::<String>("name"),
+                    since_opt,
+                    *cli_get_images
+                        .get_one::<bool>("show-usage")
+                        .unwrap_or(&false),
+                    in_use_opt,
+                    cli_get_images.get_one::<String>("output"),
                 )
                 .await;
             }
@@ -610,8 +893,22 @@ pub async fn process_cli(
                     chrono::Utc::now().format("%Y%m%d%H%M%S").to_string()
                 };
 
+                // Same as in SAT files, '__DATE__' in the configuration name is replaced by the tag
+                let cfs_configuration_name_opt = cli_apply_configuration
+                    .get_one::<String>("name")
+                    .map(
+                        |name| match cli_apply_configuration.get_one::<String>("tag") {
+                            Some(input_tag) if !name.contains("__DATE__") => {
+                                format!("{}-{}", name, input_tag)
+                            }
+                            _ => name.replace("__DATE__", &tag),
+                        },
+                    );
+
                 let _ = apply_configuration::exec(
-                    cli_apply_configuration.get_one("file").unwrap(),
+                    cli_apply_configuration.get_one("file"),
+                    cfs_configuration_name_opt.as_ref(),
+                    get_layer_spec_vec(cli_apply_configuration),
                     shasta_token,
                     shasta_base_url,
                     shasta_root_cert,
@@ -620,6 +917,7 @@ pub async fn process_cli(
                     vault_role_id,
                     k8s_api_url,
                     gitea_token,
+                    gitea_base_url,
                     &tag,
                     cli_apply_configuration.get_one::<String>("output"),
                 )
@@ -627,7 +925,21 @@ pub async fn process_cli(
             } else if let Some(cli_apply_session) = cli_apply.subcommand_matches("session") {
                 let hsm_group_name_arg_opt = cli_apply_session.try_get_one("hsm-group");
 
-                let hsm_group_members_opt = cli_apply_session.get_one::<String>("ansible-limit");
+                // Ansible limit accepts hostlist expressions, CFS needs a list of xnames
+                let hsm_group_members_opt =
+                    match cli_apply_session.get_one::<String>("ansible-limit") {
+                        Some(ansible_limit) => Some(
+                            get_xname_vec_from_hostlist(
+                                shasta_token,
+                                shasta_base_url,
+                                shasta_root_cert,
+                                ansible_limit,
+                            )
+                            .await
+                            .join(","),
+                        ),
+                        None => None,
+                    };
 
                 let target_hsm_group_vec = get_target_hsm_group_vec(
                     shasta_token,
@@ -640,7 +952,33 @@ pub async fn process_cli(
 
                 Some(target_hsm_group_vec.first().unwrap());
 
-                if let Some(ansible_limit) = hsm_group_members_opt {
+                let cfs_session_name_from_opt = cli_apply_session.get_one::<String>("from");
+
+                let wait_for_free_timeout_opt = if *cli_apply_session
+                    .get_one::<bool>("wait-for-free")
+                    .unwrap_or(&false)
+                {
+                    cli_apply_session
+                        .get_one::<u64>("wait-for-free-timeout")
+                        .copied()
+                } else {
+                    None
+                };
+
+                if cfs_session_name_from_opt.is_none()
+                    && settings_hsm_group_name_opt.is_none()
+                    && cli_apply_session
+                        .try_get_one::<String>("hsm-group")
+                        .ok()
+                        .flatten()
+                        .is_none()
+                    && hsm_group_members_opt.is_none()
+                {
+                    eprintln!("Need to specify either ansible-limit or hsm-group or both. Exit");
+                    std::process::exit(1);
+                }
+
+                if let Some(ansible_limit) = &hsm_group_members_opt {
                     validate_target_hsm_members(
                         shasta_token,
                         shasta_base_url,
@@ -653,35 +991,69 @@ pub async fn process_cli(
                     .await;
                 }
 
-                apply_session::exec(
-                    gitea_token,
-                    gitea_base_url,
-                    vault_base_url,
-                    vault_secret_path,
-                    vault_role_id,
-                    shasta_token,
-                    shasta_base_url,
-                    shasta_root_cert,
-                    k8s_api_url,
-                    cli_apply_session.get_one::<String>("name").cloned(),
-                    Some(target_hsm_group_vec.first().unwrap()),
-                    cli_apply_session
-                        .get_many("repo-path")
-                        .unwrap()
-                        .cloned()
-                        .collect(),
-                    hsm_group_members_opt.cloned(),
-                    cli_apply_session
-                        .get_one::<String>("ansible-verbosity")
-                        .cloned(),
-                    cli_apply_session
-                        .get_one::<String>("ansible-passthrough")
-                        .cloned(),
-                    *cli_apply_session
-                        .get_one::<bool>("watch-logs")
-                        .unwrap_or(&false),
-                )
-                .await;
+                if let Some(cfs_session_name_from) = cfs_session_name_from_opt {
+                    // Only override ansible verbosity if provided by the user
+                    let ansible_verbosity_opt = if cli_apply_session
+                        .value_source("ansible-verbosity")
+                        .is_some_and(|value_source| value_source == ValueSource::CommandLine)
+                    {
+                        cli_apply_session.get_one::<String>("ansible-verbosity")
+                    } else {
+                        None
+                    };
+
+                    apply_session_from::exec(
+                        vault_base_url,
+                        vault_secret_path,
+                        vault_role_id,
+                        shasta_token,
+                        shasta_base_url,
+                        shasta_root_cert,
+                        k8s_api_url,
+                        &target_hsm_group_vec,
+                        cfs_session_name_from,
+                        cli_apply_session.get_one::<String>("name"),
+                        cli_apply_session.get_one::<String>("configuration"),
+                        hsm_group_members_opt.as_ref(),
+                        ansible_verbosity_opt,
+                        cli_apply_session.get_one::<String>("ansible-passthrough"),
+                        *cli_apply_session
+                            .get_one::<bool>("failed-only")
+                            .unwrap_or(&false),
+                        *cli_apply_session
+                            .get_one::<bool>("watch-logs")
+                            .unwrap_or(&false),
+                        wait_for_free_timeout_opt,
+                    )
+                    .await;
+                } else {
+                    apply_session::exec(
+                        gitea_token,
+                        gitea_base_url,
+                        vault_base_url,
+                        vault_secret_path,
+                        vault_role_id,
+                        shasta_token,
+                        shasta_base_url,
+                        shasta_root_cert,
+                        k8s_api_url,
+                        cli_apply_session.get_one::<String>("name").cloned(),
+                        Some(target_hsm_group_vec.first().unwrap()),
+                        get_layer_spec_vec(cli_apply_session),
+                        hsm_group_members_opt,
+                        cli_apply_session
+                            .get_one::<String>("ansible-verbosity")
+                            .cloned(),
+                        cli_apply_session
+                            .get_one::<String>("ansible-passthrough")
+                            .cloned(),
+                        *cli_apply_session
+                            .get_one::<bool>("watch-logs")
+                            .unwrap_or(&false),
+                        wait_for_free_timeout_opt,
+                    )
+                    .await;
+                }
             } else if let Some(cli_apply_image) = cli_apply.subcommand_matches("image") {
                 get_target_hsm_group_vec(
                     shasta_token,
@@ -741,6 +1113,7 @@ pub async fn process_cli(
                 };
 
                 apply_cluster::exec(
+                    &power_backend,
                     shasta_token,
                     shasta_base_url,
                     shasta_root_cert,
@@ -781,12 +1154,13 @@ pub async fn process_cli(
                     )
                     .await; */
 
-                    let xname_vec: Vec<String> = cli_apply_node_on
-                        .get_one::<String>("XNAMES")
-                        .unwrap()
-                        .split(',')
-                        .map(|xname| xname.trim().to_string())
-                        .collect();
+                    let xname_vec = get_xname_vec_from_hostlist(
+                        shasta_token,
+                        shasta_base_url,
+                        shasta_root_cert,
+                        cli_apply_node_on.get_one::<String>("XNAMES").unwrap(),
+                    )
+                    .await;
 
                     validate_target_hsm_members(
                         shasta_token,
@@ -797,11 +1171,14 @@ pub async fn process_cli(
                     .await;
 
                     power_on_nodes::exec(
+                        &power_backend,
                         shasta_token,
                         shasta_base_url,
                         shasta_root_cert,
                         xname_vec,
                         cli_apply_node_on.get_one::<String>("reason").cloned(),
+                        power_transition::DEFAULT_POWER_TRANSITION_TIMEOUT_SECS,
+                        None,
                     )
                     .await;
                 } else if let Some(cli_apply_node_off) = cli_apply_node.subcommand_matches("off") {
@@ -821,12 +1198,13 @@ pub async fn process_cli(
                     )
                     .await; */
 
-                    let xname_vec: Vec<String> = cli_apply_node_off
-                        .get_one::<String>("XNAMES")
-                        .unwrap()
-                        .split(',')
-                        .map(|xname| xname.trim().to_string())
-                        .collect();
+                    let xname_vec = get_xname_vec_from_hostlist(
+                        shasta_token,
+                        shasta_base_url,
+                        shasta_root_cert,
+                        cli_apply_node_off.get_one::<String>("XNAMES").unwrap(),
+                    )
+                    .await;
 
                     let _ = validate_target_hsm_members(
                         shasta_token,
@@ -837,12 +1215,15 @@ pub async fn process_cli(
                     .await;
 
                     power_off_nodes::exec(
+                        &power_backend,
                         shasta_token,
                         shasta_base_url,
                         shasta_root_cert,
                         xname_vec,
                         cli_apply_node_off.get_one::<String>("reason").cloned(),
                         *cli_apply_node_off.get_one::<bool>("force").unwrap(),
+                        power_transition::DEFAULT_POWER_TRANSITION_TIMEOUT_SECS,
+                        None,
                     )
                     .await;
                 } else if let Some(cli_apply_node_reset) =
@@ -866,12 +1247,13 @@ pub async fn process_cli(
                     )
                     .await; */
 
-                    let xname_vec: Vec<String> = cli_apply_node_reset
-                        .get_one::<String>("XNAMES")
-                        .unwrap()
-                        .split(',')
-                        .map(|xname| xname.trim().to_string())
-                        .collect();
+                    let xname_vec = get_xname_vec_from_hostlist(
+                        shasta_token,
+                        shasta_base_url,
+                        shasta_root_cert,
+                        cli_apply_node_reset.get_one::<String>("XNAMES").unwrap(),
+                    )
+                    .await;
 
                     let _ = validate_target_hsm_members(
                         shasta_token,
@@ -882,19 +1264,26 @@ pub async fn process_cli(
                     .await;
 
                     power_reset_nodes::exec(
+                        &power_backend,
                         shasta_token,
                         shasta_base_url,
                         shasta_root_cert,
                         xname_vec,
                         cli_apply_node_reset.get_one::<String>("reason").cloned(),
                         *cli_apply_node_reset.get_one::<bool>("force").unwrap(),
+                        power_transition::DEFAULT_POWER_TRANSITION_TIMEOUT_SECS,
+                        None,
                     )
                     .await;
                 }
             } else if let Some(cli_apply_ephemeral_environment) =
                 cli_apply.subcommand_matches("ephemeral-environment")
             {
-                if !std::io::stdout().is_terminal() {
+                let connect = *cli_apply_ephemeral_environment
+                    .get_one::<bool>("connect")
+                    .unwrap_or(&false);
+
+                if connect && !std::io::stdout().is_terminal() {
                     eprintln!("This command needs to run in interactive mode. Exit");
                     std::process::exit(1);
                 }
@@ -903,17 +1292,51 @@ pub async fn process_cli(
                     shasta_token,
                     shasta_base_url,
                     shasta_root_cert,
-                    // cli_apply_ephemeral_environment
-                    //     .get_one::<bool>("block")
-                    //     .copied(),
                     cli_apply_ephemeral_environment
                         .get_one::<String>("image-id")
                         .unwrap(),
+                    cli_apply_ephemeral_environment.get_one::<String>("name"),
+                    *cli_apply_ephemeral_environment
+                        .get_one::<u32>("ttl")
+                        .unwrap(),
+                    *cli_apply_ephemeral_environment
+                        .get_one::<bool>("wait")
+                        .unwrap_or(&false),
+                    connect,
+                    cli_apply_ephemeral_environment.get_one::<PathBuf>("public-key"),
                 )
                 .await;
             }
         } else if let Some(cli_update) = cli_root.subcommand_matches("update") {
-            if let Some(cli_update_node) = cli_update.subcommand_matches("nodes") {
+            if let Some(cli_update_boot_params) = cli_update.subcommand_matches("boot-params") {
+                let xname_vec = get_xname_vec_from_hostlist(
+                    shasta_token,
+                    shasta_base_url,
+                    shasta_root_cert,
+                    cli_update_boot_params.get_one::<String>("XNAMES").unwrap(),
+                )
+                .await;
+
+                validate_target_hsm_members(
+                    shasta_token,
+                    shasta_base_url,
+                    shasta_root_cert,
+                    xname_vec.clone(),
+                )
+                .await;
+
+                update_boot_params::exec(
+                    shasta_token,
+                    shasta_base_url,
+                    shasta_root_cert,
+                    &xname_vec,
+                    &get_boot_params_edit(cli_update_boot_params),
+                    *cli_update_boot_params
+                        .get_one::<bool>("yes")
+                        .unwrap_or(&false),
+                )
+                .await;
+            } else if let Some(cli_update_node) = cli_update.subcommand_matches("nodes") {
                 let hsm_group_name_arg_opt = cli_update_node.get_one::<String>("HSM_GROUP_NAME");
 
                 let target_hsm_group_vec = get_target_hsm_group_vec(
@@ -925,19 +1348,37 @@ pub async fn process_cli(
                 )
                 .await;
 
+                let xname_vec = get_xname_vec_from_hostlist(
+                    shasta_token,
+                    shasta_base_url,
+                    shasta_root_cert,
+                    cli_update_node.get_one::<String>("XNAMES").unwrap(),
+                )
+                .await;
+
+                validate_target_hsm_members(
+                    shasta_token,
+                    shasta_base_url,
+                    shasta_root_cert,
+                    xname_vec.clone(),
+                )
+                .await;
+
                 update_node::exec(
+                    &power_backend,
                     shasta_token,
                     shasta_base_url,
                     shasta_root_cert,
                     target_hsm_group_vec.first(),
                     cli_update_node.get_one::<String>("boot-image"),
                     cli_update_node.get_one::<String>("desired-configuration"),
-                    cli_update_node
-                        .get_one::<String>("XNAMES")
-                        .unwrap()
-                        .split(',')
-                        .map(|xname| xname.trim())
-                        .collect(),
+                    xname_vec.iter().map(String::as_str).collect(),
+                    &get_cfs_component_patch(cli_update_node),
+                    get_rolling_options(
+                        cli_update_node,
+                        power_transition::DEFAULT_POWER_TRANSITION_TIMEOUT_SECS,
+                    )
+                    .as_ref(),
                 )
                 .await;
             } else if let Some(cli_update_hsm_group) = cli_update.subcommand_matches("hsm-group") {
@@ -954,12 +1395,19 @@ pub async fn process_cli(
                 .await;
 
                 update_hsm_group::exec(
+                    &power_backend,
                     shasta_token,
                     shasta_base_url,
                     shasta_root_cert,
                     cli_update_hsm_group.get_one::<String>("boot-image"),
                     cli_update_hsm_group.get_one::<String>("desired-configuration"),
                     target_hsm_group_vec.first().unwrap(),
+                    &get_cfs_component_patch(cli_update_hsm_group),
+                    get_rolling_options(
+                        cli_update_hsm_group,
+                        power_transition::DEFAULT_POWER_TRANSITION_TIMEOUT_SECS,
+                    )
+                    .as_ref(),
                 )
                 .await;
             }
@@ -1004,11 +1452,29 @@ pub async fn process_cli(
         .await; */
         } else if let Some(cli_console) = cli_root.subcommand_matches("console") {
             if let Some(cli_console_node) = cli_console.subcommand_matches("node") {
-                if !std::io::stdout().is_terminal() {
+                let console_script_opt = get_console_script(cli_console_node);
+
+                // Scripted consoles do not need a terminal
+                if console_script_opt.is_none() && !std::io::stdout().is_terminal() {
                     eprintln!("This command needs to run in interactive mode. Exit");
                     std::process::exit(1);
                 }
 
+                let xname_vec = get_xname_vec_from_hostlist(
+                    shasta_token,
+                    shasta_base_url,
+                    shasta_root_cert,
+                    cli_console_node.get_one::<String>("XNAME").unwrap(),
+                )
+                .await;
+
+                if xname_vec.len() != 1 {
+                    eprintln!("Only one node can be provided. Exit");
+                    std::process::exit(1);
+                }
+
+                let xname = xname_vec.first().unwrap();
+
                 console_node::exec(
                     settings_hsm_group_name_opt,
                     // cli_console,
@@ -1019,16 +1485,77 @@ pub async fn process_cli(
                     vault_secret_path,
                     vault_role_id,
                     k8s_api_url,
-                    cli_console_node.get_one::<String>("XNAME").unwrap(),
+                    xname,
+                    get_console_record_file_path(settings, cli_console_node, xname).as_deref(),
+                    console_script_opt.as_ref(),
+                    get_console_escape(settings, cli_console_node),
+                )
+                .await;
+            } else if let Some(cli_console_log) = cli_console.subcommand_matches("log") {
+                let xname_vec = get_xname_vec_from_hostlist(
+                    shasta_token,
+                    shasta_base_url,
+                    shasta_root_cert,
+                    cli_console_log.get_one::<String>("XNAME").unwrap(),
+                )
+                .await;
+
+                if xname_vec.len() != 1 {
+                    eprintln!("Only one node can be provided. Exit");
+                    std::process::exit(1);
+                }
+
+                validate_target_hsm_members(
+                    shasta_token,
+                    shasta_base_url,
+                    shasta_root_cert,
+                    xname_vec.clone(),
+                )
+                .await;
+
+                console_log::exec(
+                    vault_base_url,
+                    vault_secret_path,
+                    vault_role_id,
+                    k8s_api_url,
+                    xname_vec.first().unwrap(),
+                    cli_console_log.get_one::<String>("since"),
+                    cli_console_log.get_one::<usize>("tail"),
+                    cli_console_log.get_one::<String>("grep"),
+                )
+                .await;
+            } else if let Some(cli_console_tail) = cli_console.subcommand_matches("tail") {
+                let xname_vec = get_xname_vec_from_hostlist(
+                    shasta_token,
+                    shasta_base_url,
+                    shasta_root_cert,
+                    cli_console_tail.get_one::<String>("XNAMES").unwrap(),
+                )
+                .await;
+
+                validate_target_hsm_members(
+                    shasta_token,
+                    shasta_base_url,
+                    shasta_root_cert,
+                    xname_vec.clone(),
+                )
+                .await;
+
+                console_tail::exec(
+                    vault_base_url,
+                    vault_secret_path,
+                    vault_role_id,
+                    k8s_api_url,
+                    &xname_vec,
+                    cli_console_tail.get_one::<String>("until"),
                 )
                 .await;
             } else if let Some(cli_console_target_ansible) =
                 cli_console.subcommand_matches("target-ansible")
             {
-                if !std::io::stdout().is_terminal() {
-                    eprintln!("This command needs to run in interactive mode. Exit");
-                    std::process::exit(1);
-                }
+                let interactive = *cli_console_target_ansible
+                    .get_one::<bool>("interactive")
+                    .unwrap_or(&false);
 
                 let target_hsm_group_vec = get_target_hsm_group_vec(
                     shasta_token,
@@ -1051,6 +1578,8 @@ pub async fn process_cli(
                     cli_console_target_ansible
                         .get_one::<String>("SESSION_NAME")
                         .unwrap(),
+                    get_console_escape(settings, cli_console_target_ansible),
+                    interactive,
                 )
                 .await;
             }
@@ -1092,7 +1621,111 @@ pub async fn process_cli(
                 )
                 .await;
             }
+        } else if let Some(cli_serve) = cli_root.subcommand_matches("serve") {
+            if let Some(cli_serve_metrics) = cli_serve.subcommand_matches("metrics") {
+                let target_hsm_group_vec = get_target_hsm_group_vec(
+                    shasta_token,
+                    shasta_base_url,
+                    shasta_root_cert,
+                    cli_serve_metrics.get_one::<String>("hsm-group"),
+                    settings_hsm_group_name_opt,
+                )
+                .await;
+
+                serve_metrics::exec(
+                    keycloak_base_url,
+                    shasta_base_url,
+                    shasta_root_cert,
+                    &target_hsm_group_vec,
+                    cli_serve_metrics.get_one::<String>("listen").unwrap(),
+                    *cli_serve_metrics.get_one::<u64>("cache-ttl").unwrap(),
+                )
+                .await;
+            }
         } else if let Some(cli_delete) = cli_root.subcommand_matches("delete") {
+            if let Some(cli_delete_ephemeral_env) =
+                cli_delete.subcommand_matches("ephemeral-environment")
+            {
+                let name_vec: Vec<String> = cli_delete_ephemeral_env
+                    .get_many::<String>("NAMES")
+                    .unwrap_or_default()
+                    .cloned()
+                    .collect();
+
+                delete_ephemeral_env::exec(
+                    shasta_token,
+                    shasta_base_url,
+                    shasta_root_cert,
+                    &name_vec,
+                    *cli_delete_ephemeral_env
+                        .get_one::<bool>("expired")
+                        .unwrap_or(&false),
+                    *cli_delete_ephemeral_env
+                        .get_one::<bool>("all-users")
+                        .unwrap_or(&false),
+                    *cli_delete_ephemeral_env
+                        .get_one::<bool>("yes")
+                        .unwrap_or(&false),
+                )
+                .await;
+
+                return Ok(());
+            } else if let Some(cli_delete_public_keys) =
+                cli_delete.subcommand_matches("public-keys")
+            {
+                let public_key_id_vec: Vec<String> = cli_delete_public_keys
+                    .get_many::<String>("IDS")
+                    .unwrap_or_default()
+                    .cloned()
+                    .collect();
+
+                delete_public_keys::exec(
+                    shasta_token,
+                    shasta_base_url,
+                    shasta_root_cert,
+                    &public_key_id_vec,
+                    *cli_delete_public_keys
+                        .get_one::<bool>("yes")
+                        .unwrap_or(&false),
+                )
+                .await;
+
+                return Ok(());
+            } else if let Some(cli_delete_images) = cli_delete.subcommand_matches("images") {
+                let hsm_group_name_arg_opt = cli_delete_images.try_get_one("hsm-group");
+
+                let target_hsm_group_vec = get_target_hsm_group_vec(
+                    shasta_token,
+                    shasta_base_url,
+                    shasta_root_cert,
+                    hsm_group_name_arg_opt.unwrap_or(None),
+                    settings_hsm_group_name_opt,
+                )
+                .await;
+
+                let image_id_vec: Vec<String> = cli_delete_images
+                    .get_many::<String>("IMAGE_IDS")
+                    .unwrap_or_default()
+                    .cloned()
+                    .collect();
+
+                let name_opt = cli_delete_images.get_one::<String>("name");
+
+                delete_images::exec(
+                    shasta_token,
+                    shasta_base_url,
+                    shasta_root_cert,
+                    &target_hsm_group_vec,
+                    &image_id_vec,
+                    name_opt,
+                    *cli_delete_images.get_one::<bool>("force").unwrap_or(&false),
+                    *cli_delete_images.get_one::<bool>("yes").unwrap_or(&false),
+                )
+                .await;
+
+                return Ok(());
+            }
+
             let hsm_group_name_arg_opt = cli_delete.get_one::<String>("hsm-group"); // For now, we
                                                                                     // want to panic if this param is missing
 
@@ -1162,6 +1795,269 @@ pub async fn process_cli(
     Ok(())
 }
 
+/// Returns the list of CFS layers requested by the user, either through '--repo-path',
+/// '--repo-url' and '--ref', '--layer' or '--layers-file'
+/// This method will exit if a layer can't be parsed
+pub fn get_layer_spec_vec(cli_matches: &ArgMatches) -> Vec<LayerSpec> {
+    let repo_path_vec: Vec<PathBuf> = cli_matches
+        .try_get_many("repo-path")
+        .ok()
+        .flatten()
+        .unwrap_or_default()
+        .cloned()
+        .collect();
+
+    let repo_url_vec: Vec<String> = cli_matches
+        .try_get_many("repo-url")
+        .ok()
+        .flatten()
+        .unwrap_or_default()
+        .cloned()
+        .collect();
+
+    let git_ref_vec: Vec<String> = cli_matches
+        .try_get_many("ref")
+        .ok()
+        .flatten()
+        .unwrap_or_default()
+        .cloned()
+        .collect();
+
+    let layer_vec: Vec<String> = cli_matches
+        .try_get_many("layer")
+        .ok()
+        .flatten()
+        .unwrap_or_default()
+        .cloned()
+        .collect();
+
+    let layers_file_opt: Option<&PathBuf> = cli_matches.try_get_one("layers-file").ok().flatten();
+
+    if !repo_path_vec.is_empty() {
+        repo_path_vec
+            .iter()
+            .map(|repo_path| LayerSpec::new_local(repo_path))
+            .collect()
+    } else if !repo_url_vec.is_empty() {
+        if git_ref_vec.len() != 1 && git_ref_vec.len() != repo_url_vec.len() {
+            eprintln!("Number of '--ref' values must be either 1 or match the number of '--repo-url' values. Exit");
+            std::process::exit(1);
+        }
+
+        repo_url_vec
+            .iter()
+            .enumerate()
+            .map(|(i, repo_url)| {
+                LayerSpec::new(
+                    repo_url,
+                    None,
+                    Some(git_ref_vec.get(i).unwrap_or(&git_ref_vec[0])),
+                )
+            })
+            .collect()
+    } else if let Some(layers_file) = layers_file_opt {
+        let file_content = std::fs::read_to_string(layers_file).unwrap_or_else(|_| {
+            eprintln!("Layers file {} not found. Exit", layers_file.display());
+            std::process::exit(1);
+        });
+
+        cfs_layer_ops::parse_layers_file(&file_content).unwrap_or_else(|error| {
+            eprintln!("{}. Exit", error);
+            std::process::exit(1);
+        })
+    } else {
+        layer_vec
+            .iter()
+            .map(|layer| {
+                cfs_layer_ops::parse_layer_spec(layer).unwrap_or_else(|error| {
+                    eprintln!("{}. Exit", error);
+                    std::process::exit(1);
+                })
+            })
+            .collect()
+    }
+}
+
+/// Resolves a hostlist expression provided by the user into a list of xnames.
+/// Exit if the expression is not valid
+pub async fn get_xname_vec_from_hostlist(
+    shasta_token: &str,
+    shasta_base_url: &str,
+    shasta_root_cert: &[u8],
+    expression: &str,
+) -> Vec<String> {
+    match hostlist::resolve(shasta_token, shasta_base_url, shasta_root_cert, expression).await {
+        Ok(xname_vec) => xname_vec,
+        Err(error) => {
+            eprintln!("{}. Exit", error);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Parses a health threshold provided by the user. Exit if the threshold is not valid
+pub fn get_threshold(cli_matches: &ArgMatches, arg_name: &str) -> Threshold {
+    match cluster_health::parse_threshold(cli_matches.get_one::<String>(arg_name).unwrap()) {
+        Ok(threshold) => threshold,
+        Err(error) => {
+            eprintln!("{}. Exit", error);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Returns the file to record the console to if the user asked for it
+pub fn get_console_record_file_path(
+    settings: &Config,
+    cli_matches: &ArgMatches,
+    name: &str,
+) -> Option<PathBuf> {
+    if cli_matches.contains_id("record") {
+        Some(console_recording::get_record_file_path(
+            settings,
+            cli_matches.get_one::<String>("record"),
+            name,
+        ))
+    } else {
+        None
+    }
+}
+
+/// Returns the console escape character from '--escape', 'console_escape' in manta
+/// configuration or the default one, in this order
+/// This method will exit if the escape character is not valid
+pub fn get_console_escape(settings: &Config, cli_matches: &ArgMatches) -> u8 {
+    let escape = cli_matches
+        .get_one::<String>("escape")
+        .cloned()
+        .or_else(|| settings.get_string("console_escape").ok())
+        .unwrap_or(console_escape::DEFAULT_CONSOLE_ESCAPE.to_string());
+
+    match console_escape::parse_escape(&escape) {
+        Ok(escape) => escape,
+        Err(error) => {
+            eprintln!("{}. Exit", error);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Returns the text to send and patterns to expect in the console in the order provided by the
+/// user or None if the console is interactive
+/// This method will exit if a pattern is not a valid regex
+pub fn get_console_script(cli_matches: &ArgMatches) -> Option<ConsoleScript> {
+    let get_indexed_value_vec = |arg_name: &str| -> Vec<(usize, String)> {
+        cli_matches
+            .indices_of(arg_name)
+            .unwrap_or_default()
+            .zip(
+                cli_matches
+                    .get_many::<String>(arg_name)
+                    .unwrap_or_default()
+                    .cloned(),
+            )
+            .collect()
+    };
+
+    let send_vec = get_indexed_value_vec("send");
+    let expect_vec = get_indexed_value_vec("expect");
+
+    if send_vec.is_empty() && expect_vec.is_empty() {
+        return None;
+    }
+
+    match console_script::get_step_vec(send_vec, expect_vec) {
+        Ok(step_vec) => Some(ConsoleScript {
+            step_vec,
+            timeout_secs: *cli_matches.get_one::<u64>("timeout").unwrap(),
+        }),
+        Err(error) => {
+            eprintln!("{}. Exit", error);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Returns the boot parameters changes requested by the user
+/// This method will exit if a parameter to set has no value
+pub fn get_boot_params_edit(cli_matches: &ArgMatches) -> BootParamsEdit {
+    let get_param_vec = |arg_name: &str| -> Vec<String> {
+        cli_matches
+            .try_get_many::<String>(arg_name)
+            .ok()
+            .flatten()
+            .unwrap_or_default()
+            .cloned()
+            .collect()
+    };
+
+    let set_vec = get_param_vec("set");
+
+    if let Some(set) = set_vec.iter().find(|set| !set.contains('=')) {
+        eprintln!(
+            "Kernel parameter '{}' not valid, '--set' expects format 'key=value'. Exit",
+            set
+        );
+        std::process::exit(1);
+    }
+
+    BootParamsEdit {
+        add_vec: get_param_vec("add"),
+        remove_vec: get_param_vec("remove"),
+        set_vec,
+        kernel_opt: cli_matches.get_one::<String>("kernel").cloned(),
+        initrd_opt: cli_matches.get_one::<String>("initrd").cloned(),
+    }
+}
+
+/// Returns the CFS component changes requested by the user
+pub fn get_cfs_component_patch(cli_matches: &ArgMatches) -> CfsComponentPatch {
+    let enabled_opt = if *cli_matches.get_one::<bool>("enable-cfs").unwrap_or(&false) {
+        Some(true)
+    } else if *cli_matches.get_one::<bool>("disable-cfs").unwrap_or(&false) {
+        Some(false)
+    } else {
+        None
+    };
+
+    CfsComponentPatch {
+        enabled_opt,
+        clear_error_count: *cli_matches
+            .get_one::<bool>("clear-error-count")
+            .unwrap_or(&false),
+        clear_state: *cli_matches.get_one::<bool>("clear-state").unwrap_or(&false),
+        clear_desired_configuration: *cli_matches
+            .get_one::<bool>("clear-desired-configuration")
+            .unwrap_or(&false),
+    }
+}
+
+/// Returns the rolling reboot settings if the user provided '--batch-size'
+/// This method will exit if the batch size is not valid
+pub fn get_rolling_options(
+    cli_matches: &ArgMatches,
+    power_timeout_secs: u64,
+) -> Option<RollingOptions> {
+    let batch_size = cli_matches.get_one::<String>("batch-size")?;
+
+    let batch_size = match rolling_reboot::parse_batch_size(batch_size) {
+        Ok(batch_size) => batch_size,
+        Err(error) => {
+            eprintln!("{}. Exit", error);
+            std::process::exit(1);
+        }
+    };
+
+    Some(RollingOptions {
+        batch_size,
+        pause_secs: *cli_matches.get_one::<u64>("pause").unwrap_or(&0),
+        power_timeout_secs,
+        health_timeout_secs: *cli_matches
+            .get_one::<u64>("health-timeout")
+            .unwrap_or(&3600),
+    })
+}
+
 /// Returns a list of HSM groups the user is expected to work with.
 /// This method will exit if the user is asking for HSM group not allowed
 /// If the user did not requested any HSM group, then it will return all HSM groups he has access
@@ -1,13 +1,35 @@
+pub mod boot_params;
 pub mod bos_sessiontemplate_utils;
+pub mod cfs_component;
 pub mod cfs_configuration_utils;
+pub mod cfs_layer_ops;
 pub mod cfs_session_utils;
+pub mod cluster_health;
 pub mod cluster_ops;
 pub mod config_ops;
+pub mod conman;
+pub mod console_escape;
+pub mod console_recording;
+pub mod console_script;
+pub mod ephemeral_env;
+pub mod gitea;
+pub mod hostlist;
+pub mod hsm_component;
+pub mod http_client_utils;
+pub mod image_usage;
+pub mod ims_image;
 pub mod ims_ops;
+pub mod ims_public_key;
 pub mod jwt_ops;
 pub mod local_git_repo;
 pub mod log_ops;
+pub mod node_map;
 pub mod node_ops;
+pub mod pcs;
+pub mod power_backend;
+pub mod power_transition;
+pub mod prometheus;
+pub mod rolling_reboot;
 pub mod sat_file;
 pub mod terminal_ops;
 pub mod vault;
@@ -0,0 +1,288 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use super::node_ops;
+
+/// BSS boot parameters shared by a list of nodes
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct BootParamsGroup {
+    pub xnames: Vec<String>,
+    pub image_id: String,
+    pub kernel: String,
+    pub initrd: String,
+    pub params: String,
+}
+
+/// Changes to apply to the kernel parameters, kernel and initrd of a list of nodes
+#[derive(Debug, Default, Clone)]
+pub struct BootParamsEdit {
+    /// Kernel parameters to append, eg 'quiet' or 'console=ttyS0,115200'
+    pub add_vec: Vec<String>,
+    /// Kernel parameters to remove, either a key (all values) or a 'key=value'
+    pub remove_vec: Vec<String>,
+    /// Kernel parameters with format 'key=value' which value is replaced, or appended if missing
+    pub set_vec: Vec<String>,
+    pub kernel_opt: Option<String>,
+    pub initrd_opt: Option<String>,
+}
+
+impl BootParamsEdit {
+    /// Returns the boot parameters after applying the changes. Parameters are removed first,
+    /// then replaced and finally appended
+    pub fn apply(&self, boot_params_group: &BootParamsGroup) -> BootParamsGroup {
+        let mut param_vec: Vec<String> = boot_params_group
+            .params
+            .split_whitespace()
+            .map(str::to_string)
+            .collect();
+
+        for remove in &self.remove_vec {
+            param_vec.retain(|param| {
+                if remove.contains('=') {
+                    param != remove
+                } else {
+                    get_param_key(param) != remove
+                }
+            });
+        }
+
+        for set in &self.set_vec {
+            let key = get_param_key(set);
+
+            match param_vec
+                .iter()
+                .position(|param| get_param_key(param) == key)
+            {
+                Some(position) => {
+                    // Replace first occurrence and drop the others
+                    param_vec[position] = set.clone();
+
+                    let mut index = 0;
+                    param_vec.retain(|param| {
+                        let keep = index == position || get_param_key(param) != key;
+                        index += 1;
+                        keep
+                    });
+                }
+                None => param_vec.push(set.clone()),
+            }
+        }
+
+        for add in &self.add_vec {
+            if !param_vec.contains(add) {
+                param_vec.push(add.clone());
+            }
+        }
+
+        let kernel = self
+            .kernel_opt
+            .clone()
+            .unwrap_or(boot_params_group.kernel.clone());
+
+        BootParamsGroup {
+            xnames: boot_params_group.xnames.clone(),
+            image_id: get_image_id_from_kernel(&kernel),
+            kernel,
+            initrd: self
+                .initrd_opt
+                .clone()
+                .unwrap_or(boot_params_group.initrd.clone()),
+            params: param_vec.join(" "),
+        }
+    }
+}
+
+fn get_param_key(param: &str) -> &str {
+    param.split_once('=').map(|(key, _)| key).unwrap_or(param)
+}
+
+fn get_image_id_from_kernel(kernel: &str) -> String {
+    node_ops::get_image_id_from_boot_param(&serde_json::json!({ "kernel": kernel }))
+        .unwrap_or_default()
+}
+
+/// Groups the BSS boot parameters of the nodes in 'xname_vec' so nodes with same kernel, initrd
+/// and kernel parameters are shown together
+pub fn group_boot_params(boot_param_vec: &[Value], xname_vec: &[String]) -> Vec<BootParamsGroup> {
+    let mut boot_params_map: BTreeMap<(String, String, String), Vec<String>> = BTreeMap::new();
+
+    for boot_param in boot_param_vec {
+        let kernel = boot_param["kernel"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string();
+        let initrd = boot_param["initrd"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string();
+        let params = boot_param["params"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string();
+
+        let host_vec: Vec<String> = boot_param["hosts"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|host| host.as_str())
+            .filter(|host| xname_vec.iter().any(|xname| xname == host))
+            .map(str::to_string)
+            .collect();
+
+        if host_vec.is_empty() {
+            continue;
+        }
+
+        boot_params_map
+            .entry((kernel, initrd, params))
+            .or_default()
+            .extend(host_vec);
+    }
+
+    boot_params_map
+        .into_iter()
+        .map(|((kernel, initrd, params), mut xnames)| {
+            xnames.sort();
+            xnames.dedup();
+
+            BootParamsGroup {
+                xnames,
+                image_id: get_image_id_from_kernel(&kernel),
+                kernel,
+                initrd,
+                params,
+            }
+        })
+        .collect()
+}
+
+/// Fetches the BSS boot parameters of a list of nodes grouped by kernel, initrd and kernel
+/// parameters. Nodes without boot parameters are reported. This method will exit if BSS can't be
+/// queried
+pub async fn get_boot_params_group_vec(
+    shasta_token: &str,
+    shasta_base_url: &str,
+    shasta_root_cert: &[u8],
+    xname_vec: &[String],
+) -> Vec<BootParamsGroup> {
+    let boot_param_vec = match mesa::bss::http_client::get_boot_params(
+        shasta_token,
+        shasta_base_url,
+        shasta_root_cert,
+        xname_vec,
+    )
+    .await
+    {
+        Ok(boot_param_vec) => boot_param_vec,
+        Err(error) => {
+            eprintln!(
+                "ERROR - Could not get boot parameters from BSS. Reason:\n{}\nExit",
+                error
+            );
+            std::process::exit(1);
+        }
+    };
+
+    let boot_params_group_vec = group_boot_params(&boot_param_vec, xname_vec);
+
+    let xname_without_boot_params_vec: Vec<&String> = xname_vec
+        .iter()
+        .filter(|xname| {
+            !boot_params_group_vec
+                .iter()
+                .any(|boot_params_group| boot_params_group.xnames.contains(xname))
+        })
+        .collect();
+
+    if !xname_without_boot_params_vec.is_empty() {
+        eprintln!(
+            "WARNING - Nodes without boot parameters in BSS: {:?}",
+            xname_without_boot_params_vec
+        );
+    }
+
+    boot_params_group_vec
+}
+
+/// Returns the lines describing the changes between two sets of boot parameters. Removed values
+/// are prefixed with '-' and new values with '+'
+pub fn get_diff(old: &BootParamsGroup, new: &BootParamsGroup) -> Vec<String> {
+    let mut diff_vec = Vec::new();
+
+    if old.kernel != new.kernel {
+        diff_vec.push(format!("- kernel: {}", old.kernel));
+        diff_vec.push(format!("+ kernel: {}", new.kernel));
+    }
+
+    if old.initrd != new.initrd {
+        diff_vec.push(format!("- initrd: {}", old.initrd));
+        diff_vec.push(format!("+ initrd: {}", new.initrd));
+    }
+
+    let old_param_vec: Vec<&str> = old.params.split_whitespace().collect();
+    let new_param_vec: Vec<&str> = new.params.split_whitespace().collect();
+
+    for param in &old_param_vec {
+        if !new_param_vec.contains(param) {
+            diff_vec.push(format!("- {}", param));
+        }
+    }
+
+    for param in &new_param_vec {
+        if !old_param_vec.contains(param) {
+            diff_vec.push(format!("+ {}", param));
+        }
+    }
+
+    diff_vec
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_boot_params_edit_apply() {
+        let boot_params_group = BootParamsGroup {
+            xnames: vec!["x1000c0s0b0n0".to_string()],
+            image_id: "my-image".to_string(),
+            kernel: "s3://boot-images/my-image/kernel".to_string(),
+            initrd: "s3://boot-images/my-image/initrd".to_string(),
+            params: "console=ttyS0,115200 quiet crashkernel=360M console=tty0 rd.shell".to_string(),
+        };
+
+        let boot_params_edit = BootParamsEdit {
+            add_vec: vec!["quiet".to_string(), "nosmt".to_string()],
+            remove_vec: vec!["rd.shell".to_string(), "crashkernel=1G".to_string()],
+            set_vec: vec!["console=ttyS1".to_string(), "hugepages=8".to_string()],
+            kernel_opt: Some("s3://boot-images/other-image/kernel".to_string()),
+            initrd_opt: None,
+        };
+
+        let new_boot_params_group = boot_params_edit.apply(&boot_params_group);
+
+        assert_eq!(
+            new_boot_params_group.params,
+            "console=ttyS1 quiet crashkernel=360M hugepages=8 nosmt"
+        );
+        assert_eq!(new_boot_params_group.image_id, "other-image");
+        assert_eq!(new_boot_params_group.initrd, boot_params_group.initrd);
+
+        assert_eq!(
+            get_diff(&boot_params_group, &new_boot_params_group),
+            vec![
+                "- kernel: s3://boot-images/my-image/kernel",
+                "+ kernel: s3://boot-images/other-image/kernel",
+                "- console=ttyS0,115200",
+                "- console=tty0",
+                "- rd.shell",
+                "+ console=ttyS1",
+                "+ hugepages=8",
+                "+ nosmt",
+            ]
+        );
+    }
+}
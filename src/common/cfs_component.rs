@@ -0,0 +1,135 @@
+use serde_json::{json, Map, Value};
+
+/// Changes to apply to CFS components without rebooting the nodes
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CfsComponentPatch {
+    /// Enable or disable CFS batcher management of the node
+    pub enabled_opt: Option<bool>,
+    pub clear_error_count: bool,
+    pub clear_state: bool,
+    pub clear_desired_configuration: bool,
+}
+
+impl CfsComponentPatch {
+    pub fn is_empty(&self) -> bool {
+        *self == CfsComponentPatch::default()
+    }
+
+    /// Returns the CFS component fields to PATCH
+    pub fn to_value(&self) -> Value {
+        let mut patch = Map::new();
+
+        if let Some(enabled) = self.enabled_opt {
+            patch.insert("enabled".to_string(), json!(enabled));
+        }
+
+        if self.clear_error_count {
+            patch.insert("errorCount".to_string(), json!(0));
+        }
+
+        if self.clear_state {
+            patch.insert("state".to_string(), json!([]));
+        }
+
+        if self.clear_desired_configuration {
+            patch.insert("desiredConfig".to_string(), json!(""));
+        }
+
+        Value::Object(patch)
+    }
+}
+
+/// Applies a patch to the CFS component of a list of nodes and prints the result
+pub async fn apply_patch(
+    shasta_token: &str,
+    shasta_base_url: &str,
+    shasta_root_cert: &[u8],
+    xname_vec: &[String],
+    cfs_component_patch: &CfsComponentPatch,
+) {
+    let patch = cfs_component_patch.to_value();
+
+    log::info!("Patching CFS components {:?} with {}", xname_vec, patch);
+
+    match http_client::patch_component_vec(
+        shasta_token,
+        shasta_base_url,
+        shasta_root_cert,
+        xname_vec,
+        &patch,
+    )
+    .await
+    {
+        Ok(_) => println!(
+            "CFS components updated with {}: {}",
+            patch,
+            xname_vec.join(", ")
+        ),
+        Err(error) => {
+            eprintln!(
+                "ERROR - Could not update CFS components. Reason:\n{}\nExit",
+                error
+            );
+            std::process::exit(1);
+        }
+    }
+}
+
+pub mod http_client {
+
+    use std::error::Error;
+
+    use serde_json::{json, Value};
+
+    use crate::common::http_client_utils;
+
+    /// Patches the CFS components of a list of nodes in a single request
+    pub async fn patch_component_vec(
+        shasta_token: &str,
+        shasta_base_url: &str,
+        shasta_root_cert: &[u8],
+        xname_vec: &[String],
+        patch: &Value,
+    ) -> Result<Value, Box<dyn Error>> {
+        let client = http_client_utils::get_client(shasta_root_cert)?;
+
+        let api_url = format!("{}/cfs/v2/components", shasta_base_url);
+
+        let resp = client
+            .patch(api_url)
+            .bearer_auth(shasta_token)
+            .json(&json!({
+                "patch": patch,
+                "filters": {
+                    "ids": xname_vec.join(","),
+                },
+            }))
+            .send()
+            .await?;
+
+        http_client_utils::get_response_value(resp, "CFS components").await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cfs_component_patch_to_value() {
+        assert!(CfsComponentPatch::default().is_empty());
+
+        let cfs_component_patch = CfsComponentPatch {
+            enabled_opt: Some(false),
+            clear_error_count: true,
+            clear_state: false,
+            clear_desired_configuration: true,
+        };
+
+        assert!(!cfs_component_patch.is_empty());
+        assert_eq!(
+            cfs_component_patch.to_value(),
+            json!({ "enabled": false, "errorCount": 0, "desiredConfig": "" })
+        );
+    }
+}
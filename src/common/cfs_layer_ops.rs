@@ -0,0 +1,366 @@
+use std::{
+    error::Error,
+    path::{Path, PathBuf},
+};
+
+use mesa::cfs::configuration::mesa::r#struct::cfs_configuration_request::CfsConfigurationRequest;
+use serde_json::{json, Value};
+
+use crate::common::{gitea, local_git_repo};
+
+/// CFS layer as provided by the user with format 'repo[:playbook][@ref]' where 'repo' is either a
+/// path to a local git repo (absolute or starting with './' or '../') or a Gitea repo url/name, 'playbook' is the ansible playbook to run
+/// (defaults to site.yml) and 'ref' is a branch, tag or commit id (defaults to local HEAD for
+/// local repos and to the default branch for Gitea repos)
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayerSpec {
+    pub repo: String,
+    pub playbook: Option<String>,
+    pub git_ref: Option<String>,
+    /// Set for '--repo-path' layers, which are always local repos whatever the path looks like
+    local: bool,
+}
+
+impl LayerSpec {
+    pub fn new(repo: &str, playbook: Option<&str>, git_ref: Option<&str>) -> Self {
+        LayerSpec {
+            repo: repo.to_string(),
+            playbook: playbook.map(str::to_string),
+            git_ref: git_ref.map(str::to_string),
+            local: false,
+        }
+    }
+
+    /// Layer for a local git repo, eg a '--repo-path' value such as '.' or 'my-repo'
+    pub fn new_local(repo_path: &Path) -> Self {
+        LayerSpec {
+            local: true,
+            ..LayerSpec::new(&repo_path.to_string_lossy(), None, None)
+        }
+    }
+
+    /// Local repos in '--layer' and '--layers-file' must be explicit so a directory named as a
+    /// Gitea repo in the current folder does not change where the layer comes from
+    pub fn is_local(&self) -> bool {
+        self.local
+            || Path::new(&self.repo).is_absolute()
+            || self.repo.starts_with("./")
+            || self.repo.starts_with("../")
+    }
+}
+
+/// Parses a layer with format 'repo[:playbook][@ref]'. Repo can be a URL or an SSH URL
+/// ('git@host:owner/repo'), in which case the scheme and host parts are not considered when
+/// looking for separators
+pub fn parse_layer_spec(layer_spec: &str) -> Result<LayerSpec, String> {
+    let layer_spec = layer_spec.trim();
+
+    // Skip scheme and host (eg 'https://user@host:port/' or 'git@host:') since they may contain
+    // ':' or '@'
+    let path_start = match layer_spec.find("://") {
+        Some(scheme_end) => layer_spec[scheme_end + 3..]
+            .find('/')
+            .map(|path_start| scheme_end + 3 + path_start)
+            .unwrap_or(layer_spec.len()),
+        None => layer_spec
+            .find(':')
+            .filter(|host_end| {
+                let user_and_host = &layer_spec[..*host_end];
+                user_and_host.contains('@') && !user_and_host.contains('/')
+            })
+            .map(|host_end| host_end + 1)
+            .unwrap_or(0),
+    };
+
+    let (repo_and_playbook, git_ref) = match layer_spec[path_start..].find('@') {
+        Some(ref_start) => (
+            &layer_spec[..path_start + ref_start],
+            Some(&layer_spec[path_start + ref_start + 1..]),
+        ),
+        None => (layer_spec, None),
+    };
+
+    let (repo, playbook) = match repo_and_playbook[path_start..].rfind(':') {
+        Some(playbook_start) => (
+            &repo_and_playbook[..path_start + playbook_start],
+            Some(&repo_and_playbook[path_start + playbook_start + 1..]),
+        ),
+        None => (repo_and_playbook, None),
+    };
+
+    if repo.is_empty() {
+        return Err(format!("Layer '{}' has no repo", layer_spec));
+    }
+
+    if playbook.is_some_and(str::is_empty) {
+        return Err(format!("Layer '{}' has an empty playbook", layer_spec));
+    }
+
+    if git_ref.is_some_and(str::is_empty) {
+        return Err(format!("Layer '{}' has an empty ref", layer_spec));
+    }
+
+    Ok(LayerSpec::new(repo, playbook, git_ref))
+}
+
+/// Parses a layers file. The file is a YAML document with a list of layers, each layer is either
+/// a string with format 'repo[:playbook][@ref]' or a map with keys 'repo', 'playbook' and 'ref'.
+/// Layers are applied in the same order they are declared, eg:
+///
+/// layers:
+///   - repo: my-repo
+///     playbook: gpu.yml
+///     ref: main
+///   - my-other-repo:debug.yml@v1.0.0
+pub fn parse_layers_file(file_content: &str) -> Result<Vec<LayerSpec>, String> {
+    let layers_file_yaml: serde_yaml::Value =
+        serde_yaml::from_str(file_content).map_err(|error| error.to_string())?;
+
+    let layer_yaml_vec = layers_file_yaml["layers"]
+        .as_sequence()
+        .ok_or("Layers file must have a 'layers' list")?;
+
+    layer_yaml_vec
+        .iter()
+        .map(|layer_yaml| {
+            if let Some(layer_spec) = layer_yaml.as_str() {
+                parse_layer_spec(layer_spec)
+            } else if let Some(repo) = layer_yaml["repo"].as_str() {
+                Ok(LayerSpec::new(
+                    repo,
+                    layer_yaml["playbook"].as_str(),
+                    layer_yaml["ref"].as_str(),
+                ))
+            } else {
+                Err(format!("Layer {:?} has no repo", layer_yaml))
+            }
+        })
+        .collect()
+}
+
+/// Returns the CFS configuration layer for a layer spec. Local repos are processed the same way
+/// as a '--repo-path', the commit must already exist in Shasta VCS. Gitea repos are resolved
+/// through the Gitea API
+pub async fn get_cfs_layer(
+    gitea_token: &str,
+    gitea_base_url: &str,
+    shasta_root_cert: &[u8],
+    layer_spec: &LayerSpec,
+) -> Result<Value, Box<dyn Error>> {
+    let mut layer = if layer_spec.is_local() {
+        if !Path::new(&layer_spec.repo).is_dir() {
+            return Err(format!("Local repo '{}' not found", layer_spec.repo).into());
+        }
+
+        let cfs_configuration = CfsConfigurationRequest::create_from_repos(
+            gitea_token,
+            gitea_base_url,
+            shasta_root_cert,
+            vec![PathBuf::from(&layer_spec.repo)],
+            &"layer".to_string(),
+        )
+        .await;
+
+        let mut layer = serde_json::to_value(cfs_configuration)?["layers"][0].clone();
+
+        if let Some(git_ref) = &layer_spec.git_ref {
+            // Use commit the local ref points to, commit must be pushed to Shasta VCS
+            let repo = local_git_repo::get_repo(&layer_spec.repo)?;
+            let commit_id = repo.revparse_single(git_ref)?.peel_to_commit()?.id();
+
+            let (owner, repo_name) =
+                gitea::get_owner_and_repo_name(layer["cloneUrl"].as_str().unwrap_or_default());
+
+            gitea::http_client::get_commit_id_from_ref(
+                gitea_base_url,
+                gitea_token,
+                shasta_root_cert,
+                &owner,
+                &repo_name,
+                &commit_id.to_string(),
+            )
+            .await
+            .map_err(|_| {
+                format!(
+                    "Commit {} ('{}') not found in Shasta VCS repo {}. Please push it first",
+                    commit_id, git_ref, repo_name
+                )
+            })?;
+
+            layer["commit"] = json!(commit_id.to_string());
+            layer.as_object_mut().unwrap().remove("branch");
+        }
+
+        layer
+    } else {
+        let (owner, repo_name) = gitea::get_owner_and_repo_name(&layer_spec.repo);
+
+        let git_ref = match &layer_spec.git_ref {
+            Some(git_ref) => git_ref.clone(),
+            None => {
+                gitea::http_client::get_default_branch(
+                    gitea_base_url,
+                    gitea_token,
+                    shasta_root_cert,
+                    &owner,
+                    &repo_name,
+                )
+                .await?
+            }
+        };
+
+        let commit_id = gitea::http_client::get_commit_id_from_ref(
+            gitea_base_url,
+            gitea_token,
+            shasta_root_cert,
+            &owner,
+            &repo_name,
+            &git_ref,
+        )
+        .await?;
+
+        json!({
+            "name": repo_name,
+            "cloneUrl": gitea::get_cfs_clone_url(&owner, &repo_name),
+            "commit": commit_id,
+            "playbook": "site.yml",
+        })
+    };
+
+    if let Some(playbook) = &layer_spec.playbook {
+        // Layer names must be unique, add playbook name so the same repo can be used in
+        // multiple layers with different playbooks
+        let layer_name = format!(
+            "{}-{}",
+            layer["name"].as_str().unwrap_or_default(),
+            playbook
+                .trim_end_matches(".yml")
+                .trim_end_matches(".yaml")
+                .replace(['/', '.', '_'], "-")
+        );
+
+        layer["name"] = json!(layer_name);
+        layer["playbook"] = json!(playbook);
+    }
+
+    Ok(layer)
+}
+
+/// Returns a CFS configuration with the layers in the same order as the layer specs
+pub async fn create_cfs_configuration_from_layer_specs(
+    gitea_token: &str,
+    gitea_base_url: &str,
+    shasta_root_cert: &[u8],
+    cfs_configuration_name: &str,
+    layer_spec_vec: &[LayerSpec],
+) -> Result<CfsConfigurationRequest, Box<dyn Error>> {
+    let mut layer_vec = Vec::new();
+
+    for layer_spec in layer_spec_vec {
+        log::info!("Processing layer {:?}", layer_spec);
+
+        layer_vec
+            .push(get_cfs_layer(gitea_token, gitea_base_url, shasta_root_cert, layer_spec).await?);
+    }
+
+    Ok(serde_json::from_value::<CfsConfigurationRequest>(json!({
+        "name": cfs_configuration_name,
+        "layers": layer_vec,
+    }))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_layer_spec_repo_only() {
+        assert_eq!(
+            parse_layer_spec("my-repo").unwrap(),
+            LayerSpec::new("my-repo", None, None)
+        );
+    }
+
+    #[test]
+    fn test_parse_layer_spec_playbook_and_ref() {
+        assert_eq!(
+            parse_layer_spec("my-repo:gpu.yml@feature/gpu").unwrap(),
+            LayerSpec::new("my-repo", Some("gpu.yml"), Some("feature/gpu"))
+        );
+        assert_eq!(
+            parse_layer_spec("../repos/my-repo@v1.0.0").unwrap(),
+            LayerSpec::new("../repos/my-repo", None, Some("v1.0.0"))
+        );
+    }
+
+    #[test]
+    fn test_parse_layer_spec_url() {
+        assert_eq!(
+            parse_layer_spec(
+                "https://user@api.cmn.alps.cscs.ch:443/vcs/cray/my-repo.git:debug.yml@main"
+            )
+            .unwrap(),
+            LayerSpec::new(
+                "https://user@api.cmn.alps.cscs.ch:443/vcs/cray/my-repo.git",
+                Some("debug.yml"),
+                Some("main")
+            )
+        );
+    }
+
+    #[test]
+    fn test_parse_layer_spec_ssh_url() {
+        assert_eq!(
+            parse_layer_spec("git@api.cmn.alps.cscs.ch:cray/my-repo.git:debug.yml@main").unwrap(),
+            LayerSpec::new(
+                "git@api.cmn.alps.cscs.ch:cray/my-repo.git",
+                Some("debug.yml"),
+                Some("main")
+            )
+        );
+        assert_eq!(
+            parse_layer_spec("git@api.cmn.alps.cscs.ch:cray/my-repo.git").unwrap(),
+            LayerSpec::new("git@api.cmn.alps.cscs.ch:cray/my-repo.git", None, None)
+        );
+    }
+
+    #[test]
+    fn test_layer_spec_is_local() {
+        assert!(LayerSpec::new("/home/jdoe/my-repo", None, None).is_local());
+        assert!(LayerSpec::new("./my-repo", None, None).is_local());
+        assert!(LayerSpec::new("../repos/my-repo", None, None).is_local());
+        assert!(!LayerSpec::new("my-repo", None, None).is_local());
+        assert!(!LayerSpec::new("cray/my-repo", None, None).is_local());
+        assert!(LayerSpec::new_local(Path::new(".")).is_local());
+        assert!(LayerSpec::new_local(Path::new("my-repo")).is_local());
+        assert_eq!(LayerSpec::new_local(Path::new("my-repo")).repo, "my-repo");
+    }
+
+    #[test]
+    fn test_parse_layer_spec_invalid() {
+        assert!(parse_layer_spec(":site.yml").is_err());
+        assert!(parse_layer_spec("my-repo:@main").is_err());
+        assert!(parse_layer_spec("my-repo@").is_err());
+    }
+
+    #[test]
+    fn test_parse_layers_file() {
+        let layers_file = r#"
+layers:
+  - repo: my-repo
+    playbook: gpu.yml
+    ref: main
+  - my-other-repo:debug.yml@v1.0.0
+  - my-repo
+"#;
+
+        assert_eq!(
+            parse_layers_file(layers_file).unwrap(),
+            vec![
+                LayerSpec::new("my-repo", Some("gpu.yml"), Some("main")),
+                LayerSpec::new("my-other-repo", Some("debug.yml"), Some("v1.0.0")),
+                LayerSpec::new("my-repo", None, None),
+            ]
+        );
+    }
+}
@@ -1,5 +1,11 @@
+use std::collections::HashMap;
+
 use comfy_table::Table;
 use mesa::cfs::session::mesa::r#struct::CfsSessionGetResponse;
+use regex::Regex;
+use serde_json::Value;
+
+use crate::common::node_map;
 
 pub fn cfs_session_struct_to_vec(cfs_session: CfsSessionGetResponse) -> Vec<String> {
     let mut result = vec![cfs_session.name.unwrap()];
@@ -279,6 +285,236 @@ pub async fn get_image_id_from_cfs_session_list(
     None
 }
 
+/// Node or group targeted by a CFS session, either in ansible limit or in target groups
+#[derive(Debug, Clone, PartialEq)]
+enum CfsSessionTarget {
+    Xname(String),
+    /// NID or NID hostname, eg 'nid001000'
+    Nid(u64),
+    HsmGroup(String),
+}
+
+/// Parses a CFS session ansible limit, eg 'x1000c0s0b0n0,nid001001,zinal,!x1000c0s1b0n0'.
+/// Returns the targets together with a flag telling if the target is excluded ('!'). Ansible
+/// patterns (eg 'all', '*' or '&group') are not supported, the target is returned as error
+fn parse_ansible_limit(limit: &str) -> Result<Vec<(bool, CfsSessionTarget)>, String> {
+    let xname_regex = Regex::new(r"^x\d+c\d+s\d+b\d+n\d+$").unwrap();
+
+    limit
+        .split([',', ':'])
+        .map(str::trim)
+        .filter(|target| !target.is_empty())
+        .map(|target| {
+            let (excluded, name) = match target.strip_prefix('!') {
+                Some(name) => (true, name),
+                None => (false, target),
+            };
+
+            let cfs_session_target = if xname_regex.is_match(name) {
+                CfsSessionTarget::Xname(name.to_string())
+            } else if let Some(nid) = node_map::get_nid(name) {
+                CfsSessionTarget::Nid(nid)
+            } else if name.eq("all")
+                || name.is_empty()
+                || name.contains(['*', '?', '[', ']', '&', '~', '!'])
+            {
+                return Err(target.to_string());
+            } else {
+                CfsSessionTarget::HsmGroup(name.to_string())
+            };
+
+            Ok((excluded, cfs_session_target))
+        })
+        .collect()
+}
+
+/// Returns the xnames targeted by a CFS session. Ansible limit may contain xnames, NIDs, NID
+/// hostnames and HSM group names, targets prefixed with '!' are excluded. Target groups are HSM
+/// group names. HSM group members are cached in 'hsm_group_member_map' to avoid fetching HSM
+/// groups for each CFS session.
+/// Returns None if the CFS session does not restrict its targets (CFS session target dynamic with
+/// neither ansible limit nor target groups, or with exclusions only), meaning it targets all
+/// nodes. Returns an error if a target is neither a node nor an HSM group since then conflicts
+/// can't be checked
+pub async fn get_xname_vec_targeted_by_cfs_session(
+    shasta_token: &str,
+    shasta_base_url: &str,
+    shasta_root_cert: &[u8],
+    cfs_session: &CfsSessionGetResponse,
+    hsm_group_member_map: &mut HashMap<String, Vec<String>>,
+) -> Result<Option<Vec<String>>, String> {
+    let cfs_session_value = serde_json::to_value(cfs_session).unwrap_or_default();
+
+    let cfs_session_name = cfs_session.name.clone().unwrap_or_default();
+
+    let unknown_target_error = |target: &str| {
+        format!(
+            "CFS session {} has unknown target '{}', cannot check conflicts",
+            cfs_session_name, target
+        )
+    };
+
+    // CFS sessions target image run on IMS pods, not on nodes
+    if cfs_session_value
+        .pointer("/target/definition")
+        .and_then(Value::as_str)
+        .is_some_and(|definition| definition.eq("image"))
+    {
+        return Ok(Some(Vec::new()));
+    }
+
+    let mut target_vec = parse_ansible_limit(
+        cfs_session_value
+            .pointer("/ansible/limit")
+            .and_then(Value::as_str)
+            .unwrap_or_default(),
+    )
+    .map_err(|target| unknown_target_error(&target))?;
+
+    target_vec.extend(
+        cfs_session_value
+            .pointer("/target/groups")
+            .and_then(Value::as_array)
+            .unwrap_or(&Vec::new())
+            .iter()
+            .filter_map(|group| group["name"].as_str())
+            .map(|group_name| (false, CfsSessionTarget::HsmGroup(group_name.to_string()))),
+    );
+
+    if target_vec.iter().all(|(excluded, _)| *excluded) {
+        return Ok(None);
+    }
+
+    // Fetch all HSM groups once, so names which are not HSM groups are detected
+    if hsm_group_member_map.is_empty()
+        && target_vec
+            .iter()
+            .any(|(_, target)| matches!(target, CfsSessionTarget::HsmGroup(_)))
+    {
+        let hsm_group_vec = mesa::hsm::group::shasta::http_client::get_all(
+            shasta_token,
+            shasta_base_url,
+            shasta_root_cert,
+        )
+        .await
+        .map_err(|error| format!("Could not get HSM groups: {}", error))?;
+
+        for hsm_group in hsm_group_vec {
+            hsm_group_member_map.insert(
+                hsm_group["label"].as_str().unwrap_or_default().to_string(),
+                mesa::hsm::group::shasta::utils::get_member_vec_from_hsm_group_value(&hsm_group),
+            );
+        }
+    }
+
+    let nid_vec: Vec<u64> = target_vec
+        .iter()
+        .filter_map(|(_, target)| match target {
+            CfsSessionTarget::Nid(nid) => Some(*nid),
+            _ => None,
+        })
+        .collect();
+
+    let nid_xname_map = if nid_vec.is_empty() {
+        HashMap::new()
+    } else {
+        node_map::get_nid_xname_map(shasta_token, shasta_base_url, shasta_root_cert, &nid_vec)
+            .await?
+    };
+
+    let mut xname_vec = Vec::new();
+    let mut xname_excluded_vec = Vec::new();
+
+    for (excluded, target) in &target_vec {
+        let target_xname_vec = match target {
+            CfsSessionTarget::Xname(xname) => vec![xname.clone()],
+            CfsSessionTarget::Nid(nid) => vec![nid_xname_map
+                .get(nid)
+                .cloned()
+                .ok_or_else(|| unknown_target_error(&node_map::get_hostname_from_nid(*nid)))?],
+            CfsSessionTarget::HsmGroup(hsm_group_name) => hsm_group_member_map
+                .get(hsm_group_name)
+                .cloned()
+                .ok_or_else(|| unknown_target_error(hsm_group_name))?,
+        };
+
+        if *excluded {
+            xname_excluded_vec.extend(target_xname_vec);
+        } else {
+            xname_vec.extend(target_xname_vec);
+        }
+    }
+
+    xname_vec.retain(|xname| !xname_excluded_vec.contains(xname));
+    xname_vec.sort();
+    xname_vec.dedup();
+
+    Ok(Some(xname_vec))
+}
+
+/// Returns the list of CFS sessions running or pending which target any of the xnames provided
+/// together with the xnames they target, like [(<cfs session name>, [<xname>, ...]), ...]
+pub async fn get_cfs_session_conflict_vec(
+    shasta_token: &str,
+    shasta_base_url: &str,
+    shasta_root_cert: &[u8],
+    xname_vec: &[String],
+) -> Result<Vec<(String, Vec<String>)>, Box<dyn std::error::Error>> {
+    // Get ALL sessions
+    let cfs_session_vec = mesa::cfs::session::mesa::http_client::get(
+        shasta_token,
+        shasta_base_url,
+        shasta_root_cert,
+        None,
+        None,
+    )
+    .await?;
+
+    let mut hsm_group_member_map = HashMap::new();
+
+    let mut cfs_session_conflict_vec = Vec::new();
+
+    for cfs_session in cfs_session_vec.iter().filter(|cfs_session| {
+        cfs_session
+            .status
+            .as_ref()
+            .and_then(|status| status.session.as_ref())
+            .and_then(|session| session.status.as_ref())
+            .is_some_and(|status| ["running", "pending"].contains(&status.as_str()))
+    }) {
+        let cfs_session_name = cfs_session.name.clone().unwrap_or_default();
+
+        let xname_blocked_vec: Vec<String> = match get_xname_vec_targeted_by_cfs_session(
+            shasta_token,
+            shasta_base_url,
+            shasta_root_cert,
+            cfs_session,
+            &mut hsm_group_member_map,
+        )
+        .await?
+        {
+            Some(cfs_session_xname_vec) => xname_vec
+                .iter()
+                .filter(|xname| cfs_session_xname_vec.contains(xname))
+                .cloned()
+                .collect(),
+            None => xname_vec.to_vec(),
+        };
+
+        log::info!(
+            "CFS session {} running or pending blocks nodes: {:?}",
+            cfs_session_name,
+            xname_blocked_vec
+        );
+
+        if !xname_blocked_vec.is_empty() {
+            cfs_session_conflict_vec.push((cfs_session_name, xname_blocked_vec));
+        }
+    }
+
+    Ok(cfs_session_conflict_vec)
+}
+
 /* pub async fn transform(
     shasta_token: &str,
     shasta_base_url: &str,
@@ -446,3 +682,28 @@ pub async fn get_image_id_from_cfs_session_list(
 
     cfs_session_table_data_list
 } */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ansible_limit() {
+        assert_eq!(
+            parse_ansible_limit("x1000c0s0b0n0, nid001001:zinal,!x1000c0s1b0n0").unwrap(),
+            vec![
+                (false, CfsSessionTarget::Xname("x1000c0s0b0n0".to_string())),
+                (false, CfsSessionTarget::Nid(1001)),
+                (false, CfsSessionTarget::HsmGroup("zinal".to_string())),
+                (true, CfsSessionTarget::Xname("x1000c0s1b0n0".to_string())),
+            ]
+        );
+        assert_eq!(parse_ansible_limit("").unwrap(), vec![]);
+        assert_eq!(parse_ansible_limit("zinal,all"), Err("all".to_string()));
+        assert_eq!(
+            parse_ansible_limit("x1000c0s*"),
+            Err("x1000c0s*".to_string())
+        );
+        assert_eq!(parse_ansible_limit("&zinal"), Err("&zinal".to_string()));
+    }
+}
@@ -0,0 +1,315 @@
+use std::collections::BTreeMap;
+
+use comfy_table::Table;
+use mesa::node::r#struct::NodeDetails;
+use serde::Serialize;
+
+/// Cluster health following Nagios plugin conventions
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum HealthStatus {
+    Ok,
+    Warning,
+    Critical,
+    Unknown,
+}
+
+impl HealthStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HealthStatus::Ok => "OK",
+            HealthStatus::Warning => "WARNING",
+            HealthStatus::Critical => "CRITICAL",
+            HealthStatus::Unknown => "UNKNOWN",
+        }
+    }
+
+    /// Nagios plugin exit code
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            HealthStatus::Ok => 0,
+            HealthStatus::Warning => 1,
+            HealthStatus::Critical => 2,
+            HealthStatus::Unknown => 3,
+        }
+    }
+}
+
+/// Number of unhealthy nodes, either as a number of nodes or as a percentage of the cluster,
+/// from which the cluster is in warning or critical status
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Threshold {
+    Count(usize),
+    Percentage(usize),
+}
+
+impl Threshold {
+    pub fn is_reached(&self, unhealthy_node_count: usize, node_count: usize) -> bool {
+        match self {
+            Threshold::Count(count) => unhealthy_node_count >= *count,
+            Threshold::Percentage(percentage) => {
+                unhealthy_node_count > 0 && unhealthy_node_count * 100 >= percentage * node_count
+            }
+        }
+    }
+}
+
+/// Parses a threshold with format 'N' or 'N%'
+pub fn parse_threshold(threshold: &str) -> Result<Threshold, String> {
+    let threshold = threshold.trim();
+
+    if let Some(percentage) = threshold.strip_suffix('%') {
+        match percentage.parse::<usize>() {
+            Ok(percentage) if (1..=100).contains(&percentage) => {
+                Ok(Threshold::Percentage(percentage))
+            }
+            _ => Err(format!(
+                "Threshold '{}' not valid, percentage must be between 1% and 100%",
+                threshold
+            )),
+        }
+    } else {
+        match threshold.parse::<usize>() {
+            Ok(count) if count > 0 => Ok(Threshold::Count(count)),
+            _ => Err(format!(
+                "Threshold '{}' not valid, it must be a number of nodes (eg 2) or a percentage (eg 10%)",
+                threshold
+            )),
+        }
+    }
+}
+
+/// Node fields the health report is based on
+#[derive(Debug, Clone)]
+pub struct NodeHealthState {
+    pub xname: String,
+    pub power_status: String,
+    pub configuration_status: String,
+    pub enabled: String,
+}
+
+impl From<&NodeDetails> for NodeHealthState {
+    fn from(node_details: &NodeDetails) -> Self {
+        NodeHealthState {
+            xname: node_details.xname.clone(),
+            power_status: node_details.power_status.to_lowercase(),
+            configuration_status: node_details.configuration_status.to_lowercase(),
+            enabled: node_details.enabled.to_lowercase(),
+        }
+    }
+}
+
+/// Nodes sharing the same value for a category, eg nodes with power status 'off'
+#[derive(Serialize, Debug, Clone)]
+pub struct HealthCategory {
+    pub category: String,
+    pub state: String,
+    pub healthy: bool,
+    pub node_count: usize,
+    pub xnames: Vec<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ClusterHealthReport {
+    pub status: HealthStatus,
+    pub node_count: usize,
+    pub unhealthy_node_count: usize,
+    pub categories: Vec<HealthCategory>,
+    pub unhealthy_xnames: Vec<String>,
+}
+
+/// A node is unhealthy if it is not powered on, its configuration failed or it is disabled
+fn is_category_state_healthy(category: &str, state: &str) -> bool {
+    match category {
+        "power" => state.eq("on"),
+        "configuration" => !state.eq("failed"),
+        "enabled" => state.eq("true"),
+        _ => true,
+    }
+}
+
+/// Counts nodes per power status, configuration status and enabled flag and sets the cluster
+/// status based on the number of unhealthy nodes
+pub fn get_report(
+    node_health_state_vec: &[NodeHealthState],
+    warning: &Threshold,
+    critical: &Threshold,
+) -> ClusterHealthReport {
+    let mut category_map: BTreeMap<(&str, &str), Vec<String>> = BTreeMap::new();
+
+    for node in node_health_state_vec {
+        for (category, state) in [
+            ("power", node.power_status.as_str()),
+            ("configuration", node.configuration_status.as_str()),
+            ("enabled", node.enabled.as_str()),
+        ] {
+            category_map
+                .entry((category, state))
+                .or_default()
+                .push(node.xname.clone());
+        }
+    }
+
+    let categories: Vec<HealthCategory> = category_map
+        .into_iter()
+        .map(|((category, state), xnames)| HealthCategory {
+            category: category.to_string(),
+            state: state.to_string(),
+            healthy: is_category_state_healthy(category, state),
+            node_count: xnames.len(),
+            xnames,
+        })
+        .collect();
+
+    let unhealthy_xnames: Vec<String> = node_health_state_vec
+        .iter()
+        .filter(|node| {
+            !is_category_state_healthy("power", &node.power_status)
+                || !is_category_state_healthy("configuration", &node.configuration_status)
+                || !is_category_state_healthy("enabled", &node.enabled)
+        })
+        .map(|node| node.xname.clone())
+        .collect();
+
+    let node_count = node_health_state_vec.len();
+    let unhealthy_node_count = unhealthy_xnames.len();
+
+    let status = if node_count == 0 {
+        HealthStatus::Unknown
+    } else if critical.is_reached(unhealthy_node_count, node_count) {
+        HealthStatus::Critical
+    } else if warning.is_reached(unhealthy_node_count, node_count) {
+        HealthStatus::Warning
+    } else {
+        HealthStatus::Ok
+    };
+
+    ClusterHealthReport {
+        status,
+        node_count,
+        unhealthy_node_count,
+        categories,
+        unhealthy_xnames,
+    }
+}
+
+/// Prints the report. The first line follows Nagios plugin output format
+/// '<STATUS> - <summary> | <perfdata>'
+pub fn print_report(cluster_name: &str, report: &ClusterHealthReport, output_opt: Option<&String>) {
+    if output_opt.is_some() && output_opt.unwrap().eq("json") {
+        println!("{}", serde_json::to_string_pretty(report).unwrap());
+        return;
+    }
+
+    println!(
+        "CLUSTER {} {} - {}/{} nodes healthy | nodes={} unhealthy={}",
+        cluster_name,
+        report.status.as_str(),
+        report.node_count - report.unhealthy_node_count,
+        report.node_count,
+        report.node_count,
+        report.unhealthy_node_count
+    );
+
+    let mut table = Table::new();
+
+    table.set_header(vec!["Category", "State", "Nodes", "Xnames"]);
+
+    for category in &report.categories {
+        table.add_row(vec![
+            category.category.clone(),
+            category.state.clone(),
+            category.node_count.to_string(),
+            // Only list nodes causing the cluster to be unhealthy
+            if category.healthy {
+                "".to_string()
+            } else {
+                category.xnames.join(", ")
+            },
+        ]);
+    }
+
+    println!("{table}");
+}
+
+/// Prints the report of a cluster which health could not be checked, eg a CSM API failed, and
+/// exits with the UNKNOWN exit code so monitoring does not report it as CRITICAL
+pub fn exit_unknown(cluster_name: &str, reason: &str, output_opt: Option<&String>) -> ! {
+    if output_opt.is_some() && output_opt.unwrap().eq("json") {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&serde_json::json!({
+                "status": HealthStatus::Unknown,
+                "reason": reason,
+            }))
+            .unwrap()
+        );
+    } else {
+        println!(
+            "CLUSTER {} {} - {}",
+            cluster_name,
+            HealthStatus::Unknown.as_str(),
+            reason
+        );
+    }
+
+    std::process::exit(HealthStatus::Unknown.exit_code());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_node(xname: &str, power_status: &str, configuration_status: &str) -> NodeHealthState {
+        NodeHealthState {
+            xname: xname.to_string(),
+            power_status: power_status.to_string(),
+            configuration_status: configuration_status.to_string(),
+            enabled: "true".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse_threshold() {
+        assert_eq!(parse_threshold("2"), Ok(Threshold::Count(2)));
+        assert_eq!(parse_threshold("10%"), Ok(Threshold::Percentage(10)));
+        assert!(parse_threshold("0").is_err());
+        assert!(parse_threshold("200%").is_err());
+        assert!(parse_threshold("two").is_err());
+    }
+
+    #[test]
+    fn test_get_report() {
+        let node_vec = vec![
+            get_node("x1000c0s0b0n0", "on", "configured"),
+            get_node("x1000c0s0b0n1", "on", "pending"),
+            get_node("x1000c0s1b0n0", "off", "configured"),
+            get_node("x1000c0s1b0n1", "on", "failed"),
+        ];
+
+        let report = get_report(&node_vec, &Threshold::Count(1), &Threshold::Percentage(75));
+
+        assert_eq!(report.status, HealthStatus::Warning);
+        assert_eq!(
+            report.unhealthy_xnames,
+            vec!["x1000c0s1b0n0", "x1000c0s1b0n1"]
+        );
+
+        let power_off = report
+            .categories
+            .iter()
+            .find(|category| category.category == "power" && category.state == "off")
+            .unwrap();
+        assert_eq!(power_off.xnames, vec!["x1000c0s1b0n0"]);
+        assert!(!power_off.healthy);
+
+        let report = get_report(&node_vec, &Threshold::Count(1), &Threshold::Percentage(50));
+        assert_eq!(report.status, HealthStatus::Critical);
+
+        let report = get_report(&node_vec[..2], &Threshold::Count(1), &Threshold::Count(2));
+        assert_eq!(report.status, HealthStatus::Ok);
+
+        let report = get_report(&[], &Threshold::Count(1), &Threshold::Count(2));
+        assert_eq!(report.status, HealthStatus::Unknown);
+    }
+}
@@ -0,0 +1,280 @@
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+use k8s_openapi::api::core::v1::Pod;
+use kube::{
+    api::{AttachParams, ListParams},
+    Api, Client,
+};
+use regex::Regex;
+use serde_json::Value;
+use tokio::io::AsyncReadExt;
+
+const CONSOLE_NAMESPACE: &str = "services";
+const CONSOLE_OPERATOR_LABEL: &str = "app.kubernetes.io/name=cray-console-operator";
+const CONSOLE_OPERATOR_CONTAINER: &str = "cray-console-operator";
+const CONSOLE_NODE_CONTAINER: &str = "cray-console-node";
+const CONSOLE_LOG_DIR: &str = "/var/log/conman";
+
+/// Runs a command in a pod and returns its stdout
+async fn exec_in_pod(
+    client: Client,
+    pod_name: &str,
+    container: &str,
+    command: Vec<String>,
+) -> Result<String, anyhow::Error> {
+    let pod_api: Api<Pod> = Api::namespaced(client, CONSOLE_NAMESPACE);
+
+    let attach_params = AttachParams::default()
+        .container(container)
+        .stdin(false)
+        .stdout(true)
+        .stderr(false);
+
+    let mut attached = pod_api.exec(pod_name, command, &attach_params).await?;
+
+    let mut output = Vec::new();
+
+    attached
+        .stdout()
+        .ok_or_else(|| anyhow::anyhow!("Could not read output of pod {}", pod_name))?
+        .read_to_end(&mut output)
+        .await?;
+
+    attached.join().await?;
+
+    // Console output may contain bytes which are not valid UTF-8
+    Ok(String::from_utf8_lossy(&output).to_string())
+}
+
+/// Returns the cray-console-node pod managing the console of a node. The console operator
+/// knows which pod each node is assigned to
+pub async fn get_console_node_pod_name(
+    client: Client,
+    xname: &str,
+) -> Result<String, anyhow::Error> {
+    let pod_api: Api<Pod> = Api::namespaced(client.clone(), CONSOLE_NAMESPACE);
+
+    let console_operator_pod_name = pod_api
+        .list(&ListParams::default().labels(CONSOLE_OPERATOR_LABEL))
+        .await?
+        .items
+        .first()
+        .and_then(|pod| pod.metadata.name.clone())
+        .ok_or_else(|| anyhow::anyhow!("Console operator pod not found"))?;
+
+    let output = exec_in_pod(
+        client,
+        &console_operator_pod_name,
+        CONSOLE_OPERATOR_CONTAINER,
+        vec![
+            "sh".to_string(),
+            "-c".to_string(),
+            format!("/app/get-node {}", xname),
+        ],
+    )
+    .await?;
+
+    serde_json::from_str::<Value>(&output)
+        .ok()
+        .and_then(|node| node["podname"].as_str().map(str::to_string))
+        .ok_or_else(|| anyhow::anyhow!("Console pod for {} not found: {}", xname, output.trim()))
+}
+
+/// Returns the number of the line the console log of a node has to be printed from to include
+/// everything printed after 'since'. Only the timestamp lines are read from the pod
+pub async fn get_first_line_number_since(
+    client: Client,
+    console_node_pod_name: &str,
+    xname: &str,
+    since: NaiveDateTime,
+) -> Result<usize, anyhow::Error> {
+    let timestamp_lines = exec_in_pod(
+        client,
+        console_node_pod_name,
+        CONSOLE_NODE_CONTAINER,
+        vec![
+            "grep".to_string(),
+            "-n".to_string(),
+            "-F".to_string(),
+            "<ConMan> Console [".to_string(),
+            format!("{}/console.{}", CONSOLE_LOG_DIR, xname),
+        ],
+    )
+    .await?;
+
+    Ok(get_first_line_number_since_from_timestamp_lines(
+        &timestamp_lines,
+        since,
+    ))
+}
+
+/// Returns the console log conman keeps for a node. The log is filtered inside the pod so only
+/// the lines requested are transferred: lines from 'first_line_number_opt' onwards, then lines
+/// matching the extended regex 'grep_opt' (same as 'grep -E'), then the last 'tail_opt' lines
+pub async fn get_console_log(
+    client: Client,
+    console_node_pod_name: &str,
+    xname: &str,
+    first_line_number_opt: Option<usize>,
+    grep_opt: Option<&str>,
+    tail_opt: Option<usize>,
+) -> Result<String, anyhow::Error> {
+    // Values are passed as positional parameters so they are not interpreted by the shell
+    let mut script = r#"tail -n +"$2" "$1""#.to_string();
+
+    if grep_opt.is_some() {
+        script.push_str(r#" | grep -E -e "$3""#);
+    }
+
+    if tail_opt.is_some() {
+        script.push_str(r#" | tail -n "$4""#);
+    }
+
+    exec_in_pod(
+        client,
+        console_node_pod_name,
+        CONSOLE_NODE_CONTAINER,
+        vec![
+            "sh".to_string(),
+            "-c".to_string(),
+            script,
+            "sh".to_string(),
+            format!("{}/console.{}", CONSOLE_LOG_DIR, xname),
+            first_line_number_opt.unwrap_or(1).to_string(),
+            grep_opt.unwrap_or_default().to_string(),
+            tail_opt.unwrap_or_default().to_string(),
+        ],
+    )
+    .await
+}
+
+/// Parses '--since' values, either a date with format '%Y-%m-%d' or '%Y-%m-%dT%H:%M:%S' or a
+/// duration relative to 'now' like '30m', '12h' or '2d'
+pub fn parse_since(since: &str, now: NaiveDateTime) -> Result<NaiveDateTime, String> {
+    if let Ok(date_time) = NaiveDateTime::parse_from_str(since, "%Y-%m-%dT%H:%M:%S") {
+        return Ok(date_time);
+    }
+
+    if let Ok(date) = NaiveDate::parse_from_str(since, "%Y-%m-%d") {
+        return Ok(date.and_hms_opt(0, 0, 0).unwrap());
+    }
+
+    let error = format!(
+        "Since '{}' not valid, use a date (eg 2024-01-31 or 2024-01-31T22:00:00) or a duration (eg 30m, 12h or 2d)",
+        since
+    );
+
+    let (amount, unit) = since
+        .char_indices()
+        .last()
+        .map(|(unit_index, _)| since.split_at(unit_index))
+        .ok_or_else(|| error.clone())?;
+    let amount: i64 = amount.parse().map_err(|_| error.clone())?;
+
+    let duration_opt = match unit {
+        "m" => Duration::try_minutes(amount),
+        "h" => Duration::try_hours(amount),
+        "d" => Duration::try_days(amount),
+        _ => None,
+    };
+
+    duration_opt
+        .and_then(|duration| now.checked_sub_signed(duration))
+        .ok_or(error)
+}
+
+/// Returns the number of the last line with a conman timestamp at or before 'since', or 1 if
+/// there is no such line. 'timestamp_lines' is the output of 'grep -n' for the lines conman
+/// writes when the log is opened and periodically, eg
+/// '3:<ConMan> Console [x1000c0s0b0n0] log at 2024-01-31 22:00:00 UTC.'. Console output has no
+/// timestamps per line, so these are the only dates in the log and lines after 'since' may come
+/// after the last timestamp
+pub fn get_first_line_number_since_from_timestamp_lines(
+    timestamp_lines: &str,
+    since: NaiveDateTime,
+) -> usize {
+    let conman_timestamp_regex =
+        Regex::new(r"^(\d+):<ConMan> Console \[.*\] log at (\d{4}-\d{2}-\d{2} \d{2}:\d{2}:\d{2})")
+            .unwrap();
+
+    timestamp_lines
+        .lines()
+        .rev()
+        .find_map(|line| {
+            let captures = conman_timestamp_regex.captures(line)?;
+
+            let date_time =
+                NaiveDateTime::parse_from_str(&captures[2], "%Y-%m-%d %H:%M:%S").ok()?;
+
+            if date_time <= since {
+                captures[1].parse().ok()
+            } else {
+                None
+            }
+        })
+        .unwrap_or(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_since() {
+        let now =
+            NaiveDateTime::parse_from_str("2024-01-31T22:00:00", "%Y-%m-%dT%H:%M:%S").unwrap();
+
+        assert_eq!(
+            parse_since("2h", now).unwrap().to_string(),
+            "2024-01-31 20:00:00"
+        );
+        assert_eq!(
+            parse_since("2024-01-30", now).unwrap().to_string(),
+            "2024-01-30 00:00:00"
+        );
+        assert!(parse_since("yesterday", now).is_err());
+    }
+
+    #[test]
+    fn test_parse_since_not_valid() {
+        let now =
+            NaiveDateTime::parse_from_str("2024-01-31T22:00:00", "%Y-%m-%dT%H:%M:%S").unwrap();
+
+        assert!(parse_since("", now).is_err());
+        assert!(parse_since("2é", now).is_err());
+        assert!(parse_since("9223372036854775807d", now).is_err());
+    }
+
+    #[test]
+    fn test_get_first_line_number_since_from_timestamp_lines() {
+        let timestamp_lines = [
+            "1:<ConMan> Console [x1000c0s0b0n0] log at 2024-01-31 20:00:00 UTC.",
+            "3:<ConMan> Console [x1000c0s0b0n0] log at 2024-01-31 21:00:00 UTC.",
+        ]
+        .join("\n");
+
+        let since =
+            NaiveDateTime::parse_from_str("2024-01-31T20:30:00", "%Y-%m-%dT%H:%M:%S").unwrap();
+
+        assert_eq!(
+            get_first_line_number_since_from_timestamp_lines(&timestamp_lines, since),
+            1
+        );
+
+        // Lines printed after the last timestamp are kept
+        let since =
+            NaiveDateTime::parse_from_str("2024-01-31T21:30:00", "%Y-%m-%dT%H:%M:%S").unwrap();
+
+        assert_eq!(
+            get_first_line_number_since_from_timestamp_lines(&timestamp_lines, since),
+            3
+        );
+
+        let since =
+            NaiveDateTime::parse_from_str("2024-01-31T19:00:00", "%Y-%m-%dT%H:%M:%S").unwrap();
+
+        assert_eq!(
+            get_first_line_number_since_from_timestamp_lines(&timestamp_lines, since),
+            1
+        );
+    }
+}
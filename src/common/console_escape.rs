@@ -0,0 +1,279 @@
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+/// Escape character used when neither '--escape' nor 'console_escape' are set, same as conman
+pub const DEFAULT_CONSOLE_ESCAPE: &str = "&";
+
+/// Conman sequence to send a serial break to the node
+pub const CONMAN_BREAK_SEQUENCE: &[u8] = b"&B";
+
+/// Conman escape character, conman sends it to the node only when typed twice
+const CONMAN_ESCAPE: u8 = b'&';
+
+/// Result of processing user input in an interactive console
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConsoleInput {
+    /// Bytes to write to the console
+    Data(Vec<u8>),
+    /// User wants to leave the console
+    Detach,
+    /// Text to show to the user, it is not sent to the console
+    Message(String),
+}
+
+/// Intercepts the escape sequences typed by the user in an interactive console:
+///  - <escape>. detaches from the console
+///  - <escape>? lists the escape sequences
+///  - <escape>B sends a break
+///  - <escape><escape> sends the escape character
+///
+/// Any other character after the escape character is sent as is
+#[derive(Debug, Clone)]
+pub struct ConsoleEscape {
+    escape: u8,
+    /// Bytes sent to the console when the escape character is typed twice
+    literal_escape: Vec<u8>,
+    /// Bytes sent to the console to send a break, None if the console does not support breaks
+    break_sequence_opt: Option<Vec<u8>>,
+    /// Byte the console interprets itself, it is sent twice so the console passes it to the node
+    console_escape_opt: Option<u8>,
+    escape_pending: bool,
+}
+
+impl ConsoleEscape {
+    pub fn new(escape: u8, literal_escape: &[u8], break_sequence_opt: Option<&[u8]>) -> Self {
+        ConsoleEscape {
+            escape,
+            literal_escape: literal_escape.to_vec(),
+            break_sequence_opt: break_sequence_opt.map(<[u8]>::to_vec),
+            console_escape_opt: None,
+            escape_pending: false,
+        }
+    }
+
+    /// Escape for node consoles. Conman interprets '&' itself, so when '&' is also the manta
+    /// escape character, typing it twice sends '&&' for conman to print a single '&'. With any
+    /// other escape character every '&' typed is sent as '&&', otherwise conman would still
+    /// detach on '&.'
+    pub fn for_conman(escape: u8) -> Self {
+        if escape == CONMAN_ESCAPE {
+            ConsoleEscape::new(escape, b"&&", Some(CONMAN_BREAK_SEQUENCE))
+        } else {
+            ConsoleEscape {
+                console_escape_opt: Some(CONMAN_ESCAPE),
+                ..ConsoleEscape::new(escape, &[escape], Some(CONMAN_BREAK_SEQUENCE))
+            }
+        }
+    }
+
+    /// Adds a byte typed by the user to the data sent to the console
+    fn push_byte(&self, byte_vec: &mut Vec<u8>, byte: u8) {
+        if self.console_escape_opt == Some(byte) {
+            byte_vec.push(byte);
+        }
+
+        byte_vec.push(byte);
+    }
+
+    /// Escape character in human readable format, eg '&' or '^]'
+    pub fn escape_str(&self) -> String {
+        get_escape_str(self.escape)
+    }
+
+    pub fn help(&self) -> String {
+        let escape = self.escape_str();
+
+        let mut help = format!(
+            "Supported escape sequences:\r\n {escape}. - detach from the console\r\n {escape}? - show this help\r\n"
+        );
+
+        if self.break_sequence_opt.is_some() {
+            help.push_str(&format!(" {escape}B - send break\r\n"));
+        }
+
+        help.push_str(&format!(
+            " {escape}{escape} - send the escape character '{escape}'\r\n"
+        ));
+
+        help
+    }
+
+    /// Splits user input into data to send to the console and escape commands
+    pub fn process(&mut self, data: &[u8]) -> Vec<ConsoleInput> {
+        let mut console_input_vec = Vec::new();
+        let mut byte_vec = Vec::new();
+
+        for byte in data {
+            if !self.escape_pending {
+                if *byte == self.escape {
+                    self.escape_pending = true;
+                } else {
+                    self.push_byte(&mut byte_vec, *byte);
+                }
+
+                continue;
+            }
+
+            self.escape_pending = false;
+
+            let command_input_opt = match byte {
+                b'.' => Some(ConsoleInput::Detach),
+                b'?' => Some(ConsoleInput::Message(self.help())),
+                b'B' | b'b' => match &self.break_sequence_opt {
+                    Some(break_sequence) => {
+                        byte_vec.extend_from_slice(break_sequence);
+                        None
+                    }
+                    None => Some(ConsoleInput::Message(
+                        "Send break not supported by this console\r\n".to_string(),
+                    )),
+                },
+                byte if *byte == self.escape => {
+                    byte_vec.extend_from_slice(&self.literal_escape);
+                    None
+                }
+                byte => {
+                    self.push_byte(&mut byte_vec, self.escape);
+                    self.push_byte(&mut byte_vec, *byte);
+                    None
+                }
+            };
+
+            if let Some(command_input) = command_input_opt {
+                if !byte_vec.is_empty() {
+                    console_input_vec.push(ConsoleInput::Data(std::mem::take(&mut byte_vec)));
+                }
+
+                let is_detach = command_input == ConsoleInput::Detach;
+
+                console_input_vec.push(command_input);
+
+                if is_detach {
+                    return console_input_vec;
+                }
+            }
+        }
+
+        if !byte_vec.is_empty() {
+            console_input_vec.push(ConsoleInput::Data(byte_vec));
+        }
+
+        console_input_vec
+    }
+}
+
+/// Writes user input to the console and shows escape command messages to the user. Returns
+/// true if the user wants to detach from the console
+pub async fn forward_input<I, O>(
+    console_escape: &mut ConsoleEscape,
+    data: &[u8],
+    input: &mut I,
+    stdout: &mut O,
+) -> Result<bool, std::io::Error>
+where
+    I: AsyncWrite + Unpin,
+    O: AsyncWrite + Unpin,
+{
+    for console_input in console_escape.process(data) {
+        match console_input {
+            ConsoleInput::Data(byte_vec) => input.write_all(&byte_vec).await?,
+            ConsoleInput::Message(message) => {
+                stdout.write_all(message.as_bytes()).await?;
+                stdout.flush().await?;
+            }
+            ConsoleInput::Detach => return Ok(true),
+        }
+    }
+
+    Ok(false)
+}
+
+/// Parses an escape character, either a single character (eg '~') or a control character with
+/// format '^X' or 'ctrl-X' (eg '^]')
+pub fn parse_escape(escape: &str) -> Result<u8, String> {
+    let control_opt = escape.strip_prefix('^').or_else(|| {
+        escape
+            .to_lowercase()
+            .strip_prefix("ctrl-")
+            .map(|_| &escape[5..])
+    });
+
+    match (control_opt, escape.as_bytes()) {
+        (Some(control), _) if control.len() == 1 => {
+            let byte = control.to_ascii_uppercase().as_bytes()[0];
+
+            if (b'@'..=b'_').contains(&byte) {
+                Ok(byte & 0x1f)
+            } else {
+                Err(format!("Escape '{}' not valid", escape))
+            }
+        }
+        (None, [byte]) if byte.is_ascii_graphic() => Ok(*byte),
+        _ => Err(format!(
+            "Escape '{}' not valid, it must be a single character (eg '~') or a control character (eg '^]')",
+            escape
+        )),
+    }
+}
+
+fn get_escape_str(escape: u8) -> String {
+    if escape.is_ascii_control() {
+        format!("^{}", (escape | 0x40) as char)
+    } else {
+        (escape as char).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_escape() {
+        assert_eq!(parse_escape("&"), Ok(b'&'));
+        assert_eq!(parse_escape("^]"), Ok(0x1d));
+        assert_eq!(parse_escape("ctrl-a"), Ok(0x01));
+        assert!(parse_escape("ab").is_err());
+        assert!(parse_escape(" ").is_err());
+        assert_eq!(get_escape_str(0x1d), "^]");
+    }
+
+    #[test]
+    fn test_console_escape_process() {
+        let mut console_escape = ConsoleEscape::for_conman(b'&');
+
+        assert_eq!(
+            console_escape.process(b"ls&&x&"),
+            vec![ConsoleInput::Data(b"ls&&x".to_vec())]
+        );
+        assert_eq!(
+            console_escape.process(b"Bls&?&.ignored"),
+            vec![
+                ConsoleInput::Data(b"&Bls".to_vec()),
+                ConsoleInput::Message(console_escape.help()),
+                ConsoleInput::Detach
+            ]
+        );
+
+        let mut console_escape = ConsoleEscape::new(b'~', b"~", None);
+
+        assert_eq!(
+            console_escape.process(b"a~~~x~B"),
+            vec![
+                ConsoleInput::Data(b"a~~x".to_vec()),
+                ConsoleInput::Message("Send break not supported by this console\r\n".to_string())
+            ]
+        );
+        assert!(!console_escape.help().contains("send break"));
+    }
+
+    #[test]
+    fn test_console_escape_process_conman_escape_quoted() {
+        let mut console_escape = ConsoleEscape::for_conman(0x1d);
+
+        assert_eq!(
+            console_escape.process(b"&.a\x1dB\x1d&"),
+            vec![ConsoleInput::Data(b"&&.a&B\x1d&&".to_vec())]
+        );
+        assert_eq!(console_escape.process(b"\x1d."), vec![ConsoleInput::Detach]);
+    }
+}
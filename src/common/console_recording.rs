@@ -0,0 +1,194 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use config::Config;
+use directories::ProjectDirs;
+use serde_json::{json, Value};
+
+/// Records a console session in asciicast v2 format (https://docs.asciinema.org/manual/asciicast/v2/).
+/// First line is a header with the terminal size and start time, then each line is an output
+/// event '[<seconds since start>, "o", "<data>"]'. Recordings can be played with
+/// 'manta console replay' or 'asciinema play'
+pub struct ConsoleRecorder {
+    writer: BufWriter<File>,
+    start: Instant,
+    /// Bytes of an incomplete UTF-8 character split between two console messages
+    pending_byte_vec: Vec<u8>,
+}
+
+impl ConsoleRecorder {
+    pub fn create(
+        path: &Path,
+        title: &str,
+        width: u16,
+        height: u16,
+    ) -> Result<Self, std::io::Error> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let mut writer = BufWriter::new(File::create(path)?);
+
+        let header = json!({
+            "version": 2,
+            "width": width,
+            "height": height,
+            "timestamp": SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            "title": title,
+        });
+
+        writeln!(writer, "{}", header)?;
+        writer.flush()?;
+
+        Ok(ConsoleRecorder {
+            writer,
+            start: Instant::now(),
+            pending_byte_vec: Vec::new(),
+        })
+    }
+
+    /// Appends console output to the recording. Each event is flushed so the recording is
+    /// complete even if manta is killed
+    pub fn record(&mut self, data: &[u8]) -> Result<(), std::io::Error> {
+        self.pending_byte_vec.extend_from_slice(data);
+
+        let valid_up_to = match std::str::from_utf8(&self.pending_byte_vec) {
+            Ok(_) => self.pending_byte_vec.len(),
+            // Keep the trailing incomplete character for the next message
+            Err(error) if error.error_len().is_none() => error.valid_up_to(),
+            Err(_) => self.pending_byte_vec.len(),
+        };
+
+        let byte_vec: Vec<u8> = self.pending_byte_vec.drain(..valid_up_to).collect();
+
+        if byte_vec.is_empty() {
+            return Ok(());
+        }
+
+        writeln!(
+            self.writer,
+            "{}",
+            format_event(
+                self.start.elapsed().as_secs_f64(),
+                &String::from_utf8_lossy(&byte_vec)
+            )
+        )?;
+        self.writer.flush()
+    }
+}
+
+fn format_event(elapsed_secs: f64, data: &str) -> String {
+    json!([
+        (elapsed_secs * 1_000_000.0).round() / 1_000_000.0,
+        "o",
+        data
+    ])
+    .to_string()
+}
+
+/// Returns the file to record the console of a node to. If 'record_path_opt' is a file, then
+/// it is used as is, otherwise a timestamped file is created in that folder or in the folder
+/// defined by 'console_record_dir' in manta configuration, eg
+/// ~/.local/share/manta/console/x1003c1s7b0n0_20240101T120000.cast
+pub fn get_record_file_path(
+    settings: &Config,
+    record_path_opt: Option<&String>,
+    name: &str,
+) -> PathBuf {
+    let record_dir = match record_path_opt {
+        Some(record_path) if !Path::new(record_path).is_dir() => {
+            return PathBuf::from(record_path);
+        }
+        Some(record_path) => PathBuf::from(record_path),
+        None => settings
+            .get_string("console_record_dir")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| get_default_record_dir()),
+    };
+
+    record_dir.join(format!(
+        "{}_{}.cast",
+        name,
+        chrono::Local::now().format("%Y%m%dT%H%M%S")
+    ))
+}
+
+fn get_default_record_dir() -> PathBuf {
+    // XDG Base Directory Specification
+    let project_dirs = ProjectDirs::from(
+        "local", /*qualifier*/
+        "cscs",  /*organization*/
+        "manta", /*application*/
+    );
+
+    match project_dirs {
+        Some(project_dirs) => project_dirs.data_dir().join("console"),
+        None => PathBuf::from("."),
+    }
+}
+
+/// Returns the output events of a recording as (seconds since start, data)
+pub fn parse_recording(recording: &str) -> Result<Vec<(f64, String)>, String> {
+    let mut line_iter = recording.lines();
+
+    let header: Value = line_iter
+        .next()
+        .and_then(|line| serde_json::from_str(line).ok())
+        .ok_or("Recording header not valid")?;
+
+    if header["version"].as_u64() != Some(2) {
+        return Err("Recording format not supported, only asciicast v2 is supported".to_string());
+    }
+
+    let mut event_vec = Vec::new();
+
+    for (line_number, line) in line_iter.enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let event: Value = serde_json::from_str(line)
+            .map_err(|error| format!("Event in line {} not valid: {}", line_number + 2, error))?;
+
+        // Only output events are replayed
+        if let (Some(elapsed_secs), Some("o"), Some(data)) =
+            (event[0].as_f64(), event[1].as_str(), event[2].as_str())
+        {
+            event_vec.push((elapsed_secs, data.to_string()));
+        }
+    }
+
+    Ok(event_vec)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_recording() {
+        let recording = format!(
+            "{}\n{}\n{}\n[1.5, \"i\", \"ignored\"]\n",
+            json!({"version": 2, "width": 80, "height": 24}),
+            format_event(0.1234567, "Booting\r\n"),
+            format_event(2.0, "login: \u{1b}[0m"),
+        );
+
+        assert_eq!(
+            parse_recording(&recording),
+            Ok(vec![
+                (0.123457, "Booting\r\n".to_string()),
+                (2.0, "login: \u{1b}[0m".to_string())
+            ])
+        );
+
+        assert!(parse_recording("{\"version\": 1}\n").is_err());
+    }
+}
@@ -0,0 +1,127 @@
+use regex::Regex;
+
+/// Exit code when an expected pattern does not show up in time, same as coreutils 'timeout'
+pub const CONSOLE_EXPECT_TIMEOUT_EXIT_CODE: i32 = 124;
+
+#[derive(Debug, Clone)]
+pub enum ConsoleStep {
+    /// Bytes to write to the console
+    Send(Vec<u8>),
+    /// Pattern to wait for in the console output
+    Expect(Regex),
+}
+
+/// Steps to run against a console without user interaction
+#[derive(Debug, Clone)]
+pub struct ConsoleScript {
+    pub step_vec: Vec<ConsoleStep>,
+    /// Seconds to wait for each expected pattern
+    pub timeout_secs: u64,
+}
+
+/// Builds the steps from the '--send' and '--expect' values and their position in the command
+/// line, so steps run in the same order the user typed them
+pub fn get_step_vec(
+    send_vec: Vec<(usize, String)>,
+    expect_vec: Vec<(usize, String)>,
+) -> Result<Vec<ConsoleStep>, String> {
+    let mut indexed_step_vec: Vec<(usize, ConsoleStep)> = send_vec
+        .into_iter()
+        .map(|(index, send)| (index, ConsoleStep::Send(unescape(&send))))
+        .collect();
+
+    for (index, expect) in expect_vec {
+        let expect_regex = Regex::new(&expect)
+            .map_err(|error| format!("Pattern '{}' not valid: {}", expect, error))?;
+
+        indexed_step_vec.push((index, ConsoleStep::Expect(expect_regex)));
+    }
+
+    indexed_step_vec.sort_by_key(|(index, _)| *index);
+
+    Ok(indexed_step_vec.into_iter().map(|(_, step)| step).collect())
+}
+
+/// Replaces escape sequences so control characters can be sent to the console, eg '\r' (enter),
+/// '\e' (escape), '\x03' (ctrl-c). '\xHH' is sent as the raw byte, other characters are sent
+/// UTF-8 encoded
+pub fn unescape(text: &str) -> Vec<u8> {
+    let mut unescaped: Vec<u8> = Vec::new();
+    let mut char_iter = text.chars().peekable();
+
+    let push_char = |unescaped: &mut Vec<u8>, c: char| {
+        unescaped.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes())
+    };
+
+    while let Some(c) = char_iter.next() {
+        if c != '\\' {
+            push_char(&mut unescaped, c);
+            continue;
+        }
+
+        match char_iter.next() {
+            Some('n') => unescaped.push(b'\n'),
+            Some('r') => unescaped.push(b'\r'),
+            Some('t') => unescaped.push(b'\t'),
+            Some('e') => unescaped.push(b'\x1b'),
+            Some('\\') => unescaped.push(b'\\'),
+            Some('x') => {
+                let hex: String = (0..2)
+                    .filter_map(|_| char_iter.next_if(char::is_ascii_hexdigit))
+                    .collect();
+
+                match u8::from_str_radix(&hex, 16) {
+                    Ok(byte) => unescaped.push(byte),
+                    Err(_) => {
+                        unescaped.extend_from_slice(b"\\x");
+                        unescaped.extend_from_slice(hex.as_bytes());
+                    }
+                }
+            }
+            Some(other) => {
+                unescaped.push(b'\\');
+                push_char(&mut unescaped, other);
+            }
+            None => unescaped.push(b'\\'),
+        }
+    }
+
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_step_vec() {
+        assert_eq!(unescape(r"root\r\e[A\x03\\x\q"), b"root\r\x1b[A\x03\\x\\q");
+        assert_eq!(unescape(r"\xff\x80é"), vec![0xff, 0x80, 0xc3, 0xa9]);
+
+        let step_vec = get_step_vec(
+            vec![(4, r"root\r".to_string()), (0, r"\r".to_string())],
+            vec![(2, "login:".to_string()), (6, r"\$ $".to_string())],
+        )
+        .unwrap();
+
+        let step_str_vec: Vec<String> = step_vec
+            .iter()
+            .map(|step| match step {
+                ConsoleStep::Send(text) => format!("send {:?}", String::from_utf8_lossy(text)),
+                ConsoleStep::Expect(regex) => format!("expect {}", regex),
+            })
+            .collect();
+
+        assert_eq!(
+            step_str_vec,
+            vec![
+                "send \"\\r\"",
+                "expect login:",
+                "send \"root\\r\"",
+                "expect \\$ $"
+            ]
+        );
+
+        assert!(get_step_vec(vec![], vec![(0, "(".to_string())]).is_err());
+    }
+}
@@ -0,0 +1,311 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+
+/// Prefix of the IMS job 'image_root_archive_name' of ephemeral environments, used to tell them
+/// apart from other IMS jobs
+pub const EPHEMERAL_ENV_PREFIX: &str = "ephemeral-env";
+
+/// Seconds to wait for an ephemeral environment to accept SSH connections
+pub const EPHEMERAL_ENV_WAIT_TIMEOUT_SECS: u64 = 900;
+
+/// Seconds between checks while waiting for an ephemeral environment
+const EPHEMERAL_ENV_WAIT_INTERVAL_SECS: u64 = 10;
+
+/// User ephemeral environments run as
+pub const EPHEMERAL_ENV_SSH_USER: &str = "root";
+
+/// Ephemeral environment, an IMS customize job with SSH enabled. IMS jobs have no field for
+/// custom metadata, so the owner, name and TTL are stored in the job
+/// 'image_root_archive_name' with format 'ephemeral-env.<user>.<name>.ttl<hours>h'
+#[derive(Debug, Clone, PartialEq)]
+pub struct EphemeralEnv {
+    pub id: String,
+    pub name: String,
+    pub user: String,
+    pub image_id: String,
+    pub status: String,
+    pub host_opt: Option<String>,
+    pub port_opt: Option<u64>,
+    pub created_opt: Option<DateTime<Utc>>,
+    pub ttl_hours: u32,
+}
+
+impl EphemeralEnv {
+    /// Returns None if the IMS job is not an ephemeral environment
+    pub fn from_ims_job(ims_job: &Value) -> Option<Self> {
+        let (user, name, ttl_hours) =
+            parse_archive_name(ims_job["image_root_archive_name"].as_str()?)?;
+
+        let customer_access = ims_job.pointer("/ssh_containers/0/connection_info/customer_access");
+
+        Some(EphemeralEnv {
+            id: ims_job["id"].as_str()?.to_string(),
+            name,
+            user,
+            image_id: ims_job["artifact_id"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            status: ims_job["status"].as_str().unwrap_or_default().to_string(),
+            host_opt: customer_access
+                .and_then(|customer_access| customer_access["host"].as_str())
+                .map(str::to_string),
+            port_opt: customer_access.and_then(|customer_access| customer_access["port"].as_u64()),
+            created_opt: ims_job["created"]
+                .as_str()
+                .and_then(|created| DateTime::parse_from_rfc3339(created).ok())
+                .map(|created| created.with_timezone(&Utc)),
+            ttl_hours,
+        })
+    }
+
+    pub fn expires_opt(&self) -> Option<DateTime<Utc>> {
+        self.created_opt
+            .map(|created| created + chrono::Duration::hours(self.ttl_hours.into()))
+    }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_opt().is_some_and(|expires| expires <= now)
+    }
+}
+
+/// Checks the ephemeral environment name can be stored in the IMS job 'image_root_archive_name'
+pub fn validate_name(name: &str) -> Result<(), String> {
+    if !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        Ok(())
+    } else {
+        Err(format!(
+            "Ephemeral environment name '{}' not valid, only letters, digits, '-' and '_' are allowed",
+            name
+        ))
+    }
+}
+
+/// Returns the owner stored in the IMS job 'image_root_archive_name' for a user. '.' separates
+/// the fields of the archive name so it is replaced by '-', eg 'john.doe' is stored as 'john-doe'
+pub fn get_owner(user: &str) -> String {
+    user.replace('.', "-")
+}
+
+/// Returns the IMS job 'image_root_archive_name' of an ephemeral environment
+pub fn get_archive_name(user: &str, name: &str, ttl_hours: u32) -> String {
+    format!(
+        "{}.{}.{}.ttl{}h",
+        EPHEMERAL_ENV_PREFIX,
+        get_owner(user),
+        name,
+        ttl_hours
+    )
+}
+
+/// Returns the user, name and TTL in hours stored in the IMS job 'image_root_archive_name'
+pub fn parse_archive_name(archive_name: &str) -> Option<(String, String, u32)> {
+    let field_vec: Vec<&str> = archive_name.split('.').collect();
+
+    match field_vec.as_slice() {
+        [prefix, user, name, ttl] if *prefix == EPHEMERAL_ENV_PREFIX => {
+            let ttl_hours = ttl.strip_prefix("ttl")?.strip_suffix('h')?.parse().ok()?;
+
+            Some((user.to_string(), name.to_string(), ttl_hours))
+        }
+        _ => None,
+    }
+}
+
+/// Returns the ephemeral environments, oldest first
+pub async fn get_ephemeral_env_vec(
+    shasta_token: &str,
+    shasta_base_url: &str,
+    shasta_root_cert: &[u8],
+) -> Vec<EphemeralEnv> {
+    let ims_job_vec =
+        match http_client::get_job_vec(shasta_token, shasta_base_url, shasta_root_cert).await {
+            Ok(ims_job_vec) => ims_job_vec,
+            Err(error) => {
+                eprintln!("ERROR - Could not get IMS jobs. Reason:\n{}\nExit", error);
+                std::process::exit(1);
+            }
+        };
+
+    let mut ephemeral_env_vec: Vec<EphemeralEnv> = ims_job_vec
+        .iter()
+        .filter_map(EphemeralEnv::from_ims_job)
+        .collect();
+
+    ephemeral_env_vec.sort_by_key(|ephemeral_env| ephemeral_env.created_opt);
+
+    ephemeral_env_vec
+}
+
+/// Waits until the SSH server of an ephemeral environment accepts connections and returns the
+/// ephemeral environment
+pub async fn wait_for_ssh(
+    shasta_token: &str,
+    shasta_base_url: &str,
+    shasta_root_cert: &[u8],
+    ims_job_id: &str,
+) -> Result<EphemeralEnv, String> {
+    let start = std::time::Instant::now();
+
+    loop {
+        let ims_job =
+            http_client::get_job(shasta_token, shasta_base_url, shasta_root_cert, ims_job_id)
+                .await
+                .map_err(|error| error.to_string())?;
+
+        let ephemeral_env = EphemeralEnv::from_ims_job(&ims_job)
+            .ok_or_else(|| format!("IMS job {} is not an ephemeral environment", ims_job_id))?;
+
+        if ephemeral_env.status == "error" {
+            return Err(format!("IMS job {} failed", ims_job_id));
+        }
+
+        if let Some(host) = &ephemeral_env.host_opt {
+            let port = ephemeral_env.port_opt.unwrap_or(22) as u16;
+
+            let ssh_connection_rslt = tokio::time::timeout(
+                Duration::from_secs(5),
+                tokio::net::TcpStream::connect((host.as_str(), port)),
+            )
+            .await;
+
+            if matches!(ssh_connection_rslt, Ok(Ok(_))) {
+                return Ok(ephemeral_env);
+            }
+        }
+
+        if start.elapsed().as_secs() > EPHEMERAL_ENV_WAIT_TIMEOUT_SECS {
+            return Err(format!(
+                "Ephemeral environment {} not reachable after {} seconds (status '{}')",
+                ephemeral_env.name, EPHEMERAL_ENV_WAIT_TIMEOUT_SECS, ephemeral_env.status
+            ));
+        }
+
+        log::info!(
+            "Waiting for ephemeral environment {} (status '{}')",
+            ephemeral_env.name,
+            ephemeral_env.status
+        );
+
+        tokio::time::sleep(Duration::from_secs(EPHEMERAL_ENV_WAIT_INTERVAL_SECS)).await;
+    }
+}
+
+pub mod http_client {
+
+    use std::error::Error;
+
+    use serde_json::Value;
+
+    use crate::common::http_client_utils;
+
+    pub async fn get_job_vec(
+        shasta_token: &str,
+        shasta_base_url: &str,
+        shasta_root_cert: &[u8],
+    ) -> Result<Vec<Value>, Box<dyn Error>> {
+        let client = http_client_utils::get_client(shasta_root_cert)?;
+
+        let api_url = format!("{}/ims/v3/jobs", shasta_base_url);
+
+        let resp = client.get(api_url).bearer_auth(shasta_token).send().await?;
+
+        Ok(http_client_utils::get_response_value(resp, "IMS jobs")
+            .await?
+            .as_array()
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    pub async fn get_job(
+        shasta_token: &str,
+        shasta_base_url: &str,
+        shasta_root_cert: &[u8],
+        ims_job_id: &str,
+    ) -> Result<Value, Box<dyn Error>> {
+        let client = http_client_utils::get_client(shasta_root_cert)?;
+
+        let api_url = format!("{}/ims/v3/jobs/{}", shasta_base_url, ims_job_id);
+
+        let resp = client.get(api_url).bearer_auth(shasta_token).send().await?;
+
+        http_client_utils::get_response_value(resp, "IMS jobs").await
+    }
+
+    /// Deletes an IMS job, IMS also deletes the kubernetes job and the SSH service
+    pub async fn delete_job(
+        shasta_token: &str,
+        shasta_base_url: &str,
+        shasta_root_cert: &[u8],
+        ims_job_id: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        let client = http_client_utils::get_client(shasta_root_cert)?;
+
+        let api_url = format!("{}/ims/v3/jobs/{}", shasta_base_url, ims_job_id);
+
+        let resp = client
+            .delete(api_url)
+            .bearer_auth(shasta_token)
+            .send()
+            .await?;
+
+        http_client_utils::get_response_value(resp, "IMS jobs")
+            .await
+            .map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_archive_name() {
+        let archive_name = get_archive_name("john.doe", "my-env", 8);
+
+        assert_eq!(archive_name, "ephemeral-env.john-doe.my-env.ttl8h");
+        assert_eq!(
+            parse_archive_name(&archive_name),
+            Some(("john-doe".to_string(), "my-env".to_string(), 8))
+        );
+        assert_eq!(parse_archive_name("__test_image_to_delete"), None);
+        assert_eq!(get_owner("john.doe"), "john-doe");
+        assert!(validate_name("my.env").is_err());
+    }
+
+    #[test]
+    fn test_ephemeral_env_from_ims_job() {
+        let ims_job = json!({
+            "id": "4f5b1a4e",
+            "image_root_archive_name": "ephemeral-env.jdoe.my-env.ttl2h",
+            "artifact_id": "0d3f1c2b",
+            "status": "waiting_on_user",
+            "created": "2024-01-31T20:00:00.000000+00:00",
+            "ssh_containers": [{
+                "connection_info": {
+                    "customer_access": { "host": "4f5b1a4e.ims.cmn.alps.cscs.ch", "port": 22 }
+                }
+            }]
+        });
+
+        let ephemeral_env = EphemeralEnv::from_ims_job(&ims_job).unwrap();
+
+        assert_eq!(ephemeral_env.user, "jdoe");
+        assert_eq!(ephemeral_env.port_opt, Some(22));
+
+        let now = DateTime::parse_from_rfc3339("2024-01-31T21:59:59+00:00")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        assert!(!ephemeral_env.is_expired(now));
+        assert!(ephemeral_env.is_expired(now + chrono::Duration::seconds(1)));
+        assert!(EphemeralEnv::from_ims_job(&json!({ "id": "1" })).is_none());
+    }
+}
@@ -0,0 +1,212 @@
+/// Returns the (owner, repo name) of a Gitea repo. Accepts a repo name, 'owner/repo name' or a
+/// full URL to the repo, eg 'https://api.cmn.alps.cscs.ch/vcs/cray/my-repo.git'. Owner defaults to
+/// 'cray' which is the organization CSM uses in VCS
+pub fn get_owner_and_repo_name(repo_url: &str) -> (String, String) {
+    let repo_path = repo_url
+        .trim()
+        .trim_end_matches('/')
+        .trim_end_matches(".git")
+        .to_string();
+
+    let mut path_segment_iter = repo_path.rsplit('/');
+
+    let repo_name = path_segment_iter.next().unwrap_or_default().to_string();
+
+    // SSH URLs have format 'git@<host>:<owner>/<repo name>'
+    let owner = path_segment_iter
+        .next()
+        .and_then(|owner| owner.rsplit(':').next())
+        .filter(|owner| !owner.is_empty())
+        .unwrap_or("cray")
+        .to_string();
+
+    (owner, repo_name)
+}
+
+/// Percent encodes a value so it can be used as a single segment in a Gitea API URL, eg branch
+/// 'feature/x' becomes 'feature%2Fx'
+pub fn encode_path_segment(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+/// Returns the clone URL CFS uses to fetch a repo from VCS
+pub fn get_cfs_clone_url(owner: &str, repo_name: &str) -> String {
+    format!(
+        "https://api-gw-service-nmn.local/vcs/{}/{}.git",
+        owner, repo_name
+    )
+}
+
+pub mod http_client {
+
+    use std::error::Error;
+
+    use serde_json::Value;
+
+    use crate::common::http_client_utils;
+
+    async fn get(
+        gitea_token: &str,
+        shasta_root_cert: &[u8],
+        api_url: &str,
+    ) -> Result<Option<Value>, Box<dyn Error>> {
+        let client = http_client_utils::get_client(shasta_root_cert)?;
+
+        log::debug!("Gitea request: {}", api_url);
+
+        let resp = client
+            .get(api_url)
+            .header("Authorization", format!("token {}", gitea_token))
+            .send()
+            .await?;
+
+        if resp.status() == reqwest::StatusCode::NOT_FOUND {
+            Ok(None)
+        } else if resp.status().is_success() {
+            Ok(Some(resp.json::<Value>().await?))
+        } else {
+            Err(resp.text().await?.into())
+        }
+    }
+
+    /// Returns the default branch of a Gitea repo
+    pub async fn get_default_branch(
+        gitea_base_url: &str,
+        gitea_token: &str,
+        shasta_root_cert: &[u8],
+        owner: &str,
+        repo_name: &str,
+    ) -> Result<String, Box<dyn Error>> {
+        let repo = get(
+            gitea_token,
+            shasta_root_cert,
+            &format!("{}/api/v1/repos/{}/{}", gitea_base_url, owner, repo_name),
+        )
+        .await?
+        .ok_or_else(|| format!("Repo {}/{} not found in Shasta VCS", owner, repo_name))?;
+
+        Ok(repo["default_branch"]
+            .as_str()
+            .unwrap_or("main")
+            .to_string())
+    }
+
+    /// Resolves a git ref (branch, tag or commit id) in a Gitea repo and returns the commit id it
+    /// points to
+    pub async fn get_commit_id_from_ref(
+        gitea_base_url: &str,
+        gitea_token: &str,
+        shasta_root_cert: &[u8],
+        owner: &str,
+        repo_name: &str,
+        git_ref: &str,
+    ) -> Result<String, Box<dyn Error>> {
+        let repo_api_url = format!("{}/api/v1/repos/{}/{}", gitea_base_url, owner, repo_name);
+
+        // Check if ref is a branch
+        if let Some(branch) = get(
+            gitea_token,
+            shasta_root_cert,
+            &format!(
+                "{}/branches/{}",
+                repo_api_url,
+                super::encode_path_segment(git_ref)
+            ),
+        )
+        .await?
+        {
+            log::info!("Ref '{}' is a branch in repo {}", git_ref, repo_name);
+            return branch["commit"]["id"]
+                .as_str()
+                .map(str::to_string)
+                .ok_or_else(|| format!("Branch '{}' has no commit", git_ref).into());
+        }
+
+        // Check if ref is a tag
+        if let Some(tag) = get(
+            gitea_token,
+            shasta_root_cert,
+            &format!(
+                "{}/tags/{}",
+                repo_api_url,
+                super::encode_path_segment(git_ref)
+            ),
+        )
+        .await?
+        {
+            log::info!("Ref '{}' is a tag in repo {}", git_ref, repo_name);
+            return tag["commit"]["sha"]
+                .as_str()
+                .map(str::to_string)
+                .ok_or_else(|| format!("Tag '{}' has no commit", git_ref).into());
+        }
+
+        // Check if ref is a commit id
+        if let Some(commit) = get(
+            gitea_token,
+            shasta_root_cert,
+            &format!(
+                "{}/git/commits/{}",
+                repo_api_url,
+                super::encode_path_segment(git_ref)
+            ),
+        )
+        .await?
+        {
+            log::info!("Ref '{}' is a commit in repo {}", git_ref, repo_name);
+            return commit["sha"]
+                .as_str()
+                .map(str::to_string)
+                .ok_or_else(|| format!("Commit '{}' not valid", git_ref).into());
+        }
+
+        Err(format!(
+            "Ref '{}' not found in repo {}/{}. Check it is a valid branch, tag or commit id",
+            git_ref, owner, repo_name
+        )
+        .into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_owner_and_repo_name() {
+        let owner_and_repo_name =
+            |owner: &str, repo_name: &str| (owner.to_string(), repo_name.to_string());
+
+        assert_eq!(
+            get_owner_and_repo_name("my-repo"),
+            owner_and_repo_name("cray", "my-repo")
+        );
+        assert_eq!(
+            get_owner_and_repo_name("cscs/my-repo"),
+            owner_and_repo_name("cscs", "my-repo")
+        );
+        assert_eq!(
+            get_owner_and_repo_name("https://api.cmn.alps.cscs.ch/vcs/cray/my-repo.git/"),
+            owner_and_repo_name("cray", "my-repo")
+        );
+        assert_eq!(
+            get_owner_and_repo_name("git@api.cmn.alps.cscs.ch:cscs/my-repo.git"),
+            owner_and_repo_name("cscs", "my-repo")
+        );
+    }
+
+    #[test]
+    fn test_encode_path_segment() {
+        assert_eq!(encode_path_segment("v1.2.3"), "v1.2.3");
+        assert_eq!(encode_path_segment("feature/x"), "feature%2Fx");
+        assert_eq!(encode_path_segment("fix #1"), "fix%20%231");
+    }
+}
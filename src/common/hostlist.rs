@@ -0,0 +1,429 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io::Read,
+};
+
+use crate::common::node_map::{self, get_nid};
+
+/// How a term in a hostlist expression is combined with the nodes selected so far
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SetOperation {
+    Union,
+    Difference,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum HostlistTerm {
+    /// Xnames, NIDs or NID hostnames (eg nid001000)
+    NodeVec(Vec<String>),
+    /// Members of an HSM group, eg '@zinal'
+    HsmGroup(String),
+    /// Hostlist expression read from stdin, eg '-'
+    Stdin,
+}
+
+/// Resolves a hostlist expression into a list of xnames. An expression is a list of terms
+/// separated by ',' or whitespace (union), a term prefixed with '!' is removed from the nodes
+/// selected so far (difference). A term can be:
+///  - an xname, NID or NID hostname with bracket ranges, eg 'x1000c[0-3]s[0-7]b0n[0-1]',
+///    '[1000-1063]' or 'nid[001000-001063]'
+///  - an HSM group prefixed with '@', eg '@zinal'
+///  - '-' to read the expression from stdin
+///
+/// NIDs and NID hostnames are translated to xnames using the node map. Nodes are returned in the order
+/// they are first selected, without duplicates
+pub async fn resolve(
+    shasta_token: &str,
+    shasta_base_url: &str,
+    shasta_root_cert: &[u8],
+    expression: &str,
+) -> Result<Vec<String>, String> {
+    let term_vec = parse(expression)?;
+
+    let stdin_term_vec = if term_vec
+        .iter()
+        .any(|(_, term)| *term == HostlistTerm::Stdin)
+    {
+        let mut stdin_expression = String::new();
+        std::io::stdin()
+            .read_to_string(&mut stdin_expression)
+            .map_err(|error| format!("Could not read nodes from stdin: {}", error))?;
+
+        let stdin_term_vec = parse(&stdin_expression)?;
+
+        if stdin_term_vec
+            .iter()
+            .any(|(_, term)| *term == HostlistTerm::Stdin)
+        {
+            return Err("Nodes read from stdin can't reference stdin ('-')".to_string());
+        }
+
+        stdin_term_vec
+    } else {
+        Vec::new()
+    };
+
+    let all_term_vec: Vec<&HostlistTerm> = term_vec
+        .iter()
+        .chain(stdin_term_vec.iter())
+        .map(|(_, term)| term)
+        .collect();
+
+    // Fetch members of HSM groups referenced
+    let mut hsm_group_member_map: HashMap<String, Vec<String>> = HashMap::new();
+
+    for term in &all_term_vec {
+        if let HostlistTerm::HsmGroup(hsm_group_name) = term {
+            if !hsm_group_member_map.contains_key(hsm_group_name) {
+                let hsm_group_member_vec =
+                    mesa::hsm::group::shasta::utils::get_member_vec_from_hsm_group_name(
+                        shasta_token,
+                        shasta_base_url,
+                        shasta_root_cert,
+                        hsm_group_name,
+                    )
+                    .await;
+
+                hsm_group_member_map.insert(hsm_group_name.clone(), hsm_group_member_vec);
+            }
+        }
+    }
+
+    // Fetch NID to xname translation only if NIDs are used
+    let nid_vec: Vec<u64> = all_term_vec
+        .iter()
+        .flat_map(|term| match term {
+            HostlistTerm::NodeVec(node_vec) => {
+                node_vec.iter().filter_map(|node| get_nid(node)).collect()
+            }
+            _ => Vec::new(),
+        })
+        .collect();
+
+    let nid_xname_map = if nid_vec.is_empty() {
+        HashMap::new()
+    } else {
+        node_map::get_nid_xname_map(shasta_token, shasta_base_url, shasta_root_cert, &nid_vec)
+            .await?
+    };
+
+    let xname_vec = evaluate(
+        &term_vec,
+        &stdin_term_vec,
+        &hsm_group_member_map,
+        &nid_xname_map,
+    )?;
+
+    if xname_vec.is_empty() {
+        return Err(format!(
+            "Expression '{}' does not select any node",
+            expression
+        ));
+    }
+
+    Ok(xname_vec)
+}
+
+/// Splits a hostlist expression into terms and the set operation to apply to each of them
+fn parse(expression: &str) -> Result<Vec<(SetOperation, HostlistTerm)>, String> {
+    let mut term_vec = Vec::new();
+    let mut token = String::new();
+    let mut operation = SetOperation::Union;
+    let mut bracket_depth = 0;
+
+    let mut flush = |token: &mut String, operation: &mut SetOperation| -> Result<(), String> {
+        if !token.is_empty() {
+            term_vec.push((*operation, parse_term(token)?));
+            token.clear();
+            *operation = SetOperation::Union;
+        }
+
+        Ok(())
+    };
+
+    for c in expression.chars() {
+        match c {
+            '[' => {
+                bracket_depth += 1;
+                token.push(c);
+            }
+            ']' => {
+                bracket_depth -= 1;
+                token.push(c);
+            }
+            ',' if bracket_depth == 0 => flush(&mut token, &mut operation)?,
+            c if c.is_whitespace() && bracket_depth == 0 => flush(&mut token, &mut operation)?,
+            '!' if bracket_depth == 0 => {
+                flush(&mut token, &mut operation)?;
+                operation = SetOperation::Difference;
+            }
+            _ => token.push(c),
+        }
+    }
+
+    let trailing_difference = token.is_empty() && operation == SetOperation::Difference;
+
+    flush(&mut token, &mut operation)?;
+
+    if trailing_difference {
+        return Err(format!(
+            "Expression '{}' not valid, '!' must be followed by the nodes to exclude",
+            expression.trim()
+        ));
+    }
+
+    if term_vec
+        .first()
+        .is_some_and(|(operation, _)| *operation == SetOperation::Difference)
+    {
+        return Err(format!(
+            "Expression '{}' not valid, it can't start with '!'",
+            expression.trim()
+        ));
+    }
+
+    Ok(term_vec)
+}
+
+fn parse_term(token: &str) -> Result<HostlistTerm, String> {
+    if token == "-" {
+        Ok(HostlistTerm::Stdin)
+    } else if let Some(hsm_group_name) = token.strip_prefix('@') {
+        if hsm_group_name.is_empty() {
+            Err("HSM group name missing after '@'".to_string())
+        } else {
+            Ok(HostlistTerm::HsmGroup(hsm_group_name.to_string()))
+        }
+    } else {
+        Ok(HostlistTerm::NodeVec(expand_brackets(token)?))
+    }
+}
+
+/// Expands bracket ranges in a node name, eg 'x1000c0s[0-1]b0n[0,3]' ->
+/// 'x1000c0s0b0n0', 'x1000c0s0b0n3', 'x1000c0s1b0n0', 'x1000c0s1b0n3'.
+/// Numbers are zero padded to the length of the start of the range, eg 'nid[001-002]' ->
+/// 'nid001', 'nid002'
+fn expand_brackets(pattern: &str) -> Result<Vec<String>, String> {
+    let Some(start) = pattern.find('[') else {
+        if pattern.contains(']') {
+            return Err(format!("Unmatched ']' in '{}'", pattern));
+        }
+
+        return Ok(vec![pattern.to_string()]);
+    };
+
+    let prefix = &pattern[..start];
+
+    if prefix.contains(']') {
+        return Err(format!("Unmatched ']' in '{}'", pattern));
+    }
+
+    let end = pattern[start..]
+        .find(']')
+        .map(|end| start + end)
+        .ok_or(format!("Unmatched '[' in '{}'", pattern))?;
+
+    let range_list = &pattern[start + 1..end];
+
+    if range_list.contains('[') {
+        return Err(format!("Nested brackets not supported in '{}'", pattern));
+    }
+
+    let suffix_vec = expand_brackets(&pattern[end + 1..])?;
+
+    let mut node_vec = Vec::new();
+
+    for value in expand_range_list(range_list).map_err(|error| {
+        format!(
+            "Range '[{}]' not valid in '{}': {}",
+            range_list, pattern, error
+        )
+    })? {
+        for suffix in &suffix_vec {
+            node_vec.push(format!("{}{}{}", prefix, value, suffix));
+        }
+    }
+
+    Ok(node_vec)
+}
+
+/// Expands a comma separated list of numbers and ranges, eg '0,2-3' -> '0', '2', '3'
+fn expand_range_list(range_list: &str) -> Result<Vec<String>, String> {
+    let mut value_vec = Vec::new();
+
+    for range in range_list.split(',') {
+        let (start, end) = range.split_once('-').unwrap_or((range, range));
+
+        if start.is_empty()
+            || end.is_empty()
+            || !start.chars().chain(end.chars()).all(|c| c.is_ascii_digit())
+        {
+            return Err(format!("'{}' is not a number or a range", range));
+        }
+
+        let width = start.len();
+        let start: u64 = start.parse().map_err(|_| format!("'{}' too big", start))?;
+        let end: u64 = end.parse().map_err(|_| format!("'{}' too big", end))?;
+
+        if start > end {
+            return Err(format!("start of range '{}' greater than its end", range));
+        }
+
+        value_vec.extend((start..=end).map(|value| format!("{:0width$}", value, width = width)));
+    }
+
+    Ok(value_vec)
+}
+
+/// Applies the set operations in order. Nodes read from stdin are combined into a single set
+/// before being applied
+fn evaluate(
+    term_vec: &[(SetOperation, HostlistTerm)],
+    stdin_term_vec: &[(SetOperation, HostlistTerm)],
+    hsm_group_member_map: &HashMap<String, Vec<String>>,
+    nid_xname_map: &HashMap<u64, String>,
+) -> Result<Vec<String>, String> {
+    let mut xname_vec: Vec<String> = Vec::new();
+
+    for (operation, term) in term_vec {
+        let term_xname_vec = match term {
+            HostlistTerm::NodeVec(node_vec) => node_vec
+                .iter()
+                .map(|node| match get_nid(node) {
+                    Some(nid) => nid_xname_map
+                        .get(&nid)
+                        .cloned()
+                        .ok_or(format!("NID '{}' not found in HSM", node)),
+                    None => Ok(node.clone()),
+                })
+                .collect::<Result<Vec<String>, String>>()?,
+            HostlistTerm::HsmGroup(hsm_group_name) => hsm_group_member_map
+                .get(hsm_group_name)
+                .cloned()
+                .unwrap_or_default(),
+            HostlistTerm::Stdin => {
+                evaluate(stdin_term_vec, &[], hsm_group_member_map, nid_xname_map)?
+            }
+        };
+
+        match operation {
+            SetOperation::Union => xname_vec.extend(term_xname_vec),
+            SetOperation::Difference => {
+                let term_xname_set: HashSet<String> = term_xname_vec.into_iter().collect();
+                xname_vec.retain(|xname| !term_xname_set.contains(xname));
+            }
+        }
+    }
+
+    let mut xname_seen_set = HashSet::new();
+    xname_vec.retain(|xname| xname_seen_set.insert(xname.clone()));
+
+    Ok(xname_vec)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_brackets() {
+        assert_eq!(
+            expand_brackets("x1000c0s[0-1]b0n[0,3]").unwrap(),
+            vec![
+                "x1000c0s0b0n0",
+                "x1000c0s0b0n3",
+                "x1000c0s1b0n0",
+                "x1000c0s1b0n3"
+            ]
+        );
+        assert_eq!(
+            expand_brackets("nid[000998-001001]").unwrap(),
+            vec!["nid000998", "nid000999", "nid001000", "nid001001"]
+        );
+        assert_eq!(
+            expand_brackets("x1000c[0-3]s[0-7]b0n[0-1]").unwrap().len(),
+            64
+        );
+        assert_eq!(
+            expand_brackets("x1000c0s0b0n0").unwrap(),
+            vec!["x1000c0s0b0n0"]
+        );
+        assert!(expand_brackets("x1000c0s[0-1b0n0").is_err());
+        assert!(expand_brackets("x1000c0s0-1]b0n0").is_err());
+        assert!(expand_brackets("x1000c0s[1-0]b0n0").is_err());
+        assert!(expand_brackets("x1000c0s[a]b0n0").is_err());
+        assert!(expand_brackets("x1000c0s[[0]]b0n0").is_err());
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse("x1000c0s0b0n[0-1],@zinal !x1000c0s0b0n1").unwrap(),
+            vec![
+                (
+                    SetOperation::Union,
+                    HostlistTerm::NodeVec(vec![
+                        "x1000c0s0b0n0".to_string(),
+                        "x1000c0s0b0n1".to_string()
+                    ])
+                ),
+                (
+                    SetOperation::Union,
+                    HostlistTerm::HsmGroup("zinal".to_string())
+                ),
+                (
+                    SetOperation::Difference,
+                    HostlistTerm::NodeVec(vec!["x1000c0s0b0n1".to_string()])
+                ),
+            ]
+        );
+        assert_eq!(
+            parse("-").unwrap(),
+            vec![(SetOperation::Union, HostlistTerm::Stdin)]
+        );
+        assert!(parse("!x1000c0s0b0n0").is_err());
+        assert!(parse("x1000c0s0b0n0!").is_err());
+        assert!(parse("@").is_err());
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let hsm_group_member_map = HashMap::from([(
+            "zinal".to_string(),
+            vec![
+                "x1000c0s0b0n0".to_string(),
+                "x1000c0s0b0n1".to_string(),
+                "x1000c0s1b0n0".to_string(),
+            ],
+        )]);
+        let nid_xname_map = HashMap::from([(1, "x1000c0s0b0n1".to_string())]);
+
+        assert_eq!(
+            evaluate(
+                &parse("@zinal!nid000001,x1000c0s0b0n0").unwrap(),
+                &[],
+                &hsm_group_member_map,
+                &nid_xname_map,
+            )
+            .unwrap(),
+            vec!["x1000c0s0b0n0", "x1000c0s1b0n0"]
+        );
+        assert_eq!(
+            evaluate(
+                &parse("x1000c0s1b0n0!-").unwrap(),
+                &parse("x1000c0s1b0n0\nx1000c0s2b0n0\n").unwrap(),
+                &hsm_group_member_map,
+                &nid_xname_map,
+            )
+            .unwrap(),
+            Vec::<String>::new()
+        );
+        assert!(evaluate(
+            &parse("nid000002").unwrap(),
+            &[],
+            &hsm_group_member_map,
+            &nid_xname_map,
+        )
+        .is_err());
+    }
+}
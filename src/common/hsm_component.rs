@@ -0,0 +1,35 @@
+/// Client for the HSM State Components API
+pub mod http_client {
+
+    use std::error::Error;
+
+    use serde_json::Value;
+
+    use crate::common::http_client_utils;
+
+    /// Returns the HSM components of type 'Node'. Each component contains, among others, 'ID'
+    /// (xname), 'NID' and 'Role'
+    pub async fn get_node_component_vec(
+        shasta_token: &str,
+        shasta_base_url: &str,
+        shasta_root_cert: &[u8],
+    ) -> Result<Vec<Value>, Box<dyn Error>> {
+        let client = http_client_utils::get_client(shasta_root_cert)?;
+
+        let api_url = format!("{}/smd/hsm/v2/State/Components", shasta_base_url);
+
+        let resp = client
+            .get(api_url)
+            .query(&[("type", "Node")])
+            .bearer_auth(shasta_token)
+            .send()
+            .await?;
+
+        Ok(
+            http_client_utils::get_response_value(resp, "HSM components").await?["Components"]
+                .as_array()
+                .cloned()
+                .unwrap_or_default(),
+        )
+    }
+}
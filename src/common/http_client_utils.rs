@@ -0,0 +1,41 @@
+use std::error::Error;
+
+use serde_json::Value;
+
+/// Returns a client trusting the Shasta root cert. Requests go through the SOCKS5 proxy if the
+/// 'SOCKS5' environment variable is set
+pub fn get_client(shasta_root_cert: &[u8]) -> Result<reqwest::Client, reqwest::Error> {
+    let client_builder = reqwest::Client::builder()
+        .add_root_certificate(reqwest::Certificate::from_pem(shasta_root_cert)?);
+
+    // Build client
+    if let Ok(socks5_env) = std::env::var("SOCKS5") {
+        // socks5 proxy
+        log::debug!("SOCKS5 enabled");
+        let socks5proxy = reqwest::Proxy::all(socks5_env)?;
+
+        // rest client to authenticate
+        client_builder.proxy(socks5proxy).build()
+    } else {
+        client_builder.build()
+    }
+}
+
+/// Returns the JSON body of a successful response (null if the body is empty). Otherwise returns
+/// an error with the status and body, 'api_name' tells which API failed, eg 'IMS jobs'
+pub async fn get_response_value(
+    resp: reqwest::Response,
+    api_name: &str,
+) -> Result<Value, Box<dyn Error>> {
+    if resp.status().is_success() {
+        Ok(resp.json::<Value>().await.unwrap_or(Value::Null))
+    } else {
+        Err(format!(
+            "{} request failed ({}): {}",
+            api_name,
+            resp.status(),
+            resp.text().await?
+        )
+        .into())
+    }
+}
@@ -0,0 +1,198 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::common::node_ops;
+
+/// Where an image is used. An image is in use if nodes boot it or BOS sessiontemplates reference
+/// it, CFS sessions only tell how the image was built
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct ImageUsage {
+    pub nodes: Vec<String>,
+    pub bos_sessiontemplates: Vec<String>,
+    pub cfs_sessions: Vec<String>,
+}
+
+impl ImageUsage {
+    pub fn is_in_use(&self) -> bool {
+        !self.nodes.is_empty() || !self.bos_sessiontemplates.is_empty()
+    }
+}
+
+/// Returns the image id in a BOS sessiontemplate boot set path. Path has format
+/// 's3://boot-images/<image id>/manifest.json'
+pub fn get_image_id_from_boot_set_path(path: &str) -> Option<&str> {
+    path.strip_prefix("s3://boot-images/")
+        .and_then(|prefix_strip_path| prefix_strip_path.strip_suffix("/manifest.json"))
+}
+
+/// Returns the usage of each image. 'bos_sessiontemplate_image_vec' and
+/// 'cfs_session_image_vec' are lists of (<BOS sessiontemplate or CFS session name>, <image id>)
+pub fn get_image_usage_map(
+    image_id_vec: &[String],
+    boot_param_vec: &[Value],
+    bos_sessiontemplate_image_vec: &[(String, String)],
+    cfs_session_image_vec: &[(String, String)],
+) -> HashMap<String, ImageUsage> {
+    let get_name_vec = |image_id: &str, name_image_vec: &[(String, String)]| -> Vec<String> {
+        let mut name_vec: Vec<String> = name_image_vec
+            .iter()
+            .filter(|(_, name_image_id)| name_image_id.eq(image_id))
+            .map(|(name, _)| name.clone())
+            .collect();
+
+        name_vec.sort();
+        name_vec.dedup();
+
+        name_vec
+    };
+
+    image_id_vec
+        .iter()
+        .map(|image_id| {
+            (
+                image_id.clone(),
+                ImageUsage {
+                    nodes: node_ops::get_node_vec_booting_image(image_id, boot_param_vec),
+                    bos_sessiontemplates: get_name_vec(image_id, bos_sessiontemplate_image_vec),
+                    cfs_sessions: get_name_vec(image_id, cfs_session_image_vec),
+                },
+            )
+        })
+        .collect()
+}
+
+/// Fetches BSS boot parameters, BOS sessiontemplates and CFS sessions and returns the usage of
+/// each image. This method will exit if any of them can't be fetched
+pub async fn get_image_usage_map_from_csm(
+    shasta_token: &str,
+    shasta_base_url: &str,
+    shasta_root_cert: &[u8],
+    image_id_vec: &[String],
+) -> HashMap<String, ImageUsage> {
+    let (boot_param_rslt, bos_sessiontemplate_rslt, cfs_session_rslt) = tokio::join!(
+        mesa::bss::http_client::get_boot_params(
+            shasta_token,
+            shasta_base_url,
+            shasta_root_cert,
+            &[],
+        ),
+        mesa::bos::template::mesa::http_client::get(
+            shasta_token,
+            shasta_base_url,
+            shasta_root_cert,
+            None,
+        ),
+        mesa::cfs::session::mesa::http_client::get(
+            shasta_token,
+            shasta_base_url,
+            shasta_root_cert,
+            None,
+            None,
+        )
+    );
+
+    let (boot_param_vec, bos_sessiontemplate_vec, cfs_session_vec) = match (
+        boot_param_rslt,
+        bos_sessiontemplate_rslt,
+        cfs_session_rslt,
+    ) {
+        (Ok(boot_param_vec), Ok(bos_sessiontemplate_vec), Ok(cfs_session_vec)) => {
+            (boot_param_vec, bos_sessiontemplate_vec, cfs_session_vec)
+        }
+        _ => {
+            eprintln!("ERROR - Could not get BSS boot parameters, BOS sessiontemplates or CFS sessions. Exit");
+            std::process::exit(1);
+        }
+    };
+
+    let bos_sessiontemplate_image_vec: Vec<(String, String)> = bos_sessiontemplate_vec
+        .iter()
+        .flat_map(|bos_sessiontemplate| {
+            let bos_sessiontemplate_name = bos_sessiontemplate.name.clone().unwrap_or_default();
+
+            bos_sessiontemplate
+                .boot_sets
+                .as_ref()
+                .map(|boot_set_map| boot_set_map.values().collect::<Vec<_>>())
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(move |boot_set| {
+                    boot_set
+                        .path
+                        .as_deref()
+                        .and_then(get_image_id_from_boot_set_path)
+                        .map(|image_id| (bos_sessiontemplate_name.clone(), image_id.to_string()))
+                })
+        })
+        .collect();
+
+    let cfs_session_image_vec: Vec<(String, String)> = cfs_session_vec
+        .iter()
+        .flat_map(|cfs_session| {
+            let cfs_session_name = cfs_session.name.clone().unwrap_or_default();
+
+            cfs_session
+                .status
+                .as_ref()
+                .and_then(|status| status.artifacts.as_ref())
+                .map(|artifact_vec| artifact_vec.iter().collect::<Vec<_>>())
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(move |artifact| {
+                    artifact
+                        .result_id
+                        .as_ref()
+                        .map(|image_id| (cfs_session_name.clone(), image_id.clone()))
+                })
+        })
+        .collect();
+
+    get_image_usage_map(
+        image_id_vec,
+        &boot_param_vec,
+        &bos_sessiontemplate_image_vec,
+        &cfs_session_image_vec,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_get_image_usage_map() {
+        assert_eq!(
+            get_image_id_from_boot_set_path("s3://boot-images/1a2b/manifest.json"),
+            Some("1a2b")
+        );
+
+        let boot_param_vec = vec![
+            json!({ "hosts": ["x1000c0s0b0n1", "x1000c0s0b0n0"], "kernel": "s3://boot-images/1a2b/kernel" }),
+            json!({ "hosts": ["x1000c0s1b0n0"], "kernel": "s3://boot-images/3c4d/kernel" }),
+        ];
+
+        let image_usage_map = get_image_usage_map(
+            &["1a2b".to_string(), "5e6f".to_string()],
+            &boot_param_vec,
+            &[("zinal-template".to_string(), "1a2b".to_string())],
+            &[
+                ("batcher-1".to_string(), "1a2b".to_string()),
+                ("batcher-2".to_string(), "5e6f".to_string()),
+            ],
+        );
+
+        assert_eq!(
+            image_usage_map["1a2b"],
+            ImageUsage {
+                nodes: vec!["x1000c0s0b0n0".to_string(), "x1000c0s0b0n1".to_string()],
+                bos_sessiontemplates: vec!["zinal-template".to_string()],
+                cfs_sessions: vec!["batcher-1".to_string()],
+            }
+        );
+        assert!(image_usage_map["1a2b"].is_in_use());
+        assert!(!image_usage_map["5e6f"].is_in_use());
+    }
+}
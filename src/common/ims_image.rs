@@ -0,0 +1,38 @@
+pub mod http_client {
+
+    use std::error::Error;
+
+    use crate::common::http_client_utils;
+
+    /// Deletes an IMS image and its artifacts in S3. IMS first soft deletes the image record and
+    /// its artifacts, then the soft deleted image is removed permanently
+    pub async fn delete_image_and_artifacts(
+        shasta_token: &str,
+        shasta_base_url: &str,
+        shasta_root_cert: &[u8],
+        image_id: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        let client = http_client_utils::get_client(shasta_root_cert)?;
+
+        let resp = client
+            .delete(format!("{}/ims/v3/images/{}", shasta_base_url, image_id))
+            .bearer_auth(shasta_token)
+            .send()
+            .await?;
+
+        http_client_utils::get_response_value(resp, "IMS images").await?;
+
+        let resp = client
+            .delete(format!(
+                "{}/ims/v3/deleted/images/{}",
+                shasta_base_url, image_id
+            ))
+            .bearer_auth(shasta_token)
+            .send()
+            .await?;
+
+        http_client_utils::get_response_value(resp, "IMS deleted images")
+            .await
+            .map(|_| ())
+    }
+}
@@ -8,12 +8,18 @@ use log4rs::{
     Config,
 };
 
-// Code base log4rs configuration to avoid having a separate file for this to keep portability
-pub fn configure(log_level: String) {
-    let mut audit_file_path = "/var/log/manta/requests.log";
+/// Returns the path to the audit file
+pub fn get_audit_file_path() -> &'static str {
     if env::consts::OS == "macos" {
-        audit_file_path = "./manta-requests.log";
+        "./manta-requests.log"
+    } else {
+        "/var/log/manta/requests.log"
     }
+}
+
+// Code base log4rs configuration to avoid having a separate file for this to keep portability
+pub fn configure(log_level: String) {
+    let audit_file_path = get_audit_file_path();
 
     let stdout = ConsoleAppender::builder()
         .encoder(Box::new(PatternEncoder::new(
@@ -0,0 +1,226 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+
+use crate::common::hsm_component;
+
+/// Node map is cached locally since HSM components rarely change
+const NODE_MAP_CACHE_TTL_SECS: u64 = 24 * 60 * 60;
+
+/// Relation between a node xname, NID and hostname
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct NodeMapEntry {
+    pub xname: String,
+    pub nid: u64,
+    pub hostname: String,
+    pub role: String,
+    pub sub_role: String,
+}
+
+/// Returns the hostname of a node based on its NID, eg 1000 -> 'nid001000'
+pub fn get_hostname_from_nid(nid: u64) -> String {
+    format!("nid{:06}", nid)
+}
+
+/// Returns the NID of a NID hostname or a NID, eg 'nid001000' -> 1000 or '1000' -> 1000
+pub fn get_nid(node: &str) -> Option<u64> {
+    let nid = node.strip_prefix("nid").unwrap_or(node);
+
+    if !nid.is_empty() && nid.chars().all(|c| c.is_ascii_digit()) {
+        nid.parse().ok()
+    } else {
+        None
+    }
+}
+
+/// Returns the nodes xname, NID and hostname. Nodes are read from the local cache unless it is
+/// older than a day or 'refresh' is true, in which case they are fetched from HSM and cached
+pub async fn get_node_map(
+    shasta_token: &str,
+    shasta_base_url: &str,
+    shasta_root_cert: &[u8],
+    refresh: bool,
+) -> Result<Vec<NodeMapEntry>, String> {
+    let cache_file_path = get_cache_file_path(shasta_base_url);
+
+    if !refresh {
+        if let Some(node_map) = cache_file_path.as_deref().and_then(read_cache) {
+            return Ok(node_map);
+        }
+    }
+
+    let node_component_vec = hsm_component::http_client::get_node_component_vec(
+        shasta_token,
+        shasta_base_url,
+        shasta_root_cert,
+    )
+    .await
+    .map_err(|error| format!("Could not get HSM components: {}", error))?;
+
+    let mut node_map: Vec<NodeMapEntry> = node_component_vec
+        .iter()
+        .filter_map(|component| {
+            let nid = component["NID"].as_u64()?;
+
+            Some(NodeMapEntry {
+                xname: component["ID"].as_str()?.to_string(),
+                nid,
+                hostname: get_hostname_from_nid(nid),
+                role: component["Role"].as_str().unwrap_or_default().to_string(),
+                sub_role: component["SubRole"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+            })
+        })
+        .collect();
+
+    node_map.sort_by_key(|node| node.nid);
+
+    if let Some(cache_file_path) = cache_file_path {
+        write_cache(&cache_file_path, &node_map);
+    }
+
+    Ok(node_map)
+}
+
+/// Returns the xname related to each NID. If any NID in 'nid_vec' is missing, the cache may be
+/// stale so the node map is fetched again from HSM
+pub async fn get_nid_xname_map(
+    shasta_token: &str,
+    shasta_base_url: &str,
+    shasta_root_cert: &[u8],
+    nid_vec: &[u64],
+) -> Result<HashMap<u64, String>, String> {
+    let get_map = |node_map: Vec<NodeMapEntry>| -> HashMap<u64, String> {
+        node_map
+            .into_iter()
+            .map(|node| (node.nid, node.xname))
+            .collect()
+    };
+
+    let nid_xname_map =
+        get_map(get_node_map(shasta_token, shasta_base_url, shasta_root_cert, false).await?);
+
+    if nid_vec.iter().all(|nid| nid_xname_map.contains_key(nid)) {
+        return Ok(nid_xname_map);
+    }
+
+    log::info!("NIDs not found in node map, refreshing node map cache");
+
+    Ok(get_map(
+        get_node_map(shasta_token, shasta_base_url, shasta_root_cert, true).await?,
+    ))
+}
+
+/// Returns the hostname related to each xname. Used to display NIDs next to xnames, if the node
+/// map can't be fetched, then the map returned is empty
+pub async fn get_xname_hostname_map(
+    shasta_token: &str,
+    shasta_base_url: &str,
+    shasta_root_cert: &[u8],
+) -> HashMap<String, String> {
+    match get_node_map(shasta_token, shasta_base_url, shasta_root_cert, false).await {
+        Ok(node_map) => node_map
+            .into_iter()
+            .map(|node| (node.xname, node.hostname))
+            .collect(),
+        Err(error) => {
+            log::warn!("{}", error);
+            HashMap::new()
+        }
+    }
+}
+
+/// Cache file is per CSM API so switching sites does not mix nodes,
+/// eg ~/.cache/manta/node_map_api.cmn.alps.cscs.ch.json
+fn get_cache_file_path(shasta_base_url: &str) -> Option<PathBuf> {
+    // XDG Base Directory Specification
+    let project_dirs = ProjectDirs::from(
+        "local", /*qualifier*/
+        "cscs",  /*organization*/
+        "manta", /*application*/
+    )?;
+
+    let host = shasta_base_url
+        .split("://")
+        .last()
+        .unwrap_or_default()
+        .split('/')
+        .next()
+        .unwrap_or_default()
+        .replace(':', "_");
+
+    let mut cache_file_path = PathBuf::from(project_dirs.cache_dir());
+    cache_file_path.push(format!("node_map_{}.json", host));
+
+    Some(cache_file_path)
+}
+
+/// Returns the node map cached if the cache file exists and it is not expired
+fn read_cache(cache_file_path: &Path) -> Option<Vec<NodeMapEntry>> {
+    let modified = std::fs::metadata(cache_file_path).ok()?.modified().ok()?;
+
+    if SystemTime::now()
+        .duration_since(modified)
+        .unwrap_or_default()
+        > Duration::from_secs(NODE_MAP_CACHE_TTL_SECS)
+    {
+        log::info!("Node map cache {} expired", cache_file_path.display());
+        return None;
+    }
+
+    let file = File::open(cache_file_path).ok()?;
+
+    match serde_json::from_reader(file) {
+        Ok(node_map) => {
+            log::debug!("Node map read from cache {}", cache_file_path.display());
+            Some(node_map)
+        }
+        Err(error) => {
+            log::warn!(
+                "Node map cache {} not valid: {}",
+                cache_file_path.display(),
+                error
+            );
+            None
+        }
+    }
+}
+
+fn write_cache(cache_file_path: &Path, node_map: &[NodeMapEntry]) {
+    let result = cache_file_path
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|_| File::create(cache_file_path))
+        .map_err(|error| error.to_string())
+        .and_then(|file| serde_json::to_writer(file, node_map).map_err(|error| error.to_string()));
+
+    if let Err(error) = result {
+        log::warn!(
+            "Could not write node map cache {}: {}",
+            cache_file_path.display(),
+            error
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_nid() {
+        assert_eq!(get_nid("nid001000"), Some(1000));
+        assert_eq!(get_nid("1000"), Some(1000));
+        assert_eq!(get_nid("nid"), None);
+        assert_eq!(get_nid("x1000c0s0b0n0"), None);
+        assert_eq!(get_hostname_from_nid(1000), "nid001000");
+    }
+}
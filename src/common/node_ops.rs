@@ -1,8 +1,31 @@
-use comfy_table::{Cell, Table};
+use std::collections::HashMap;
+
+use comfy_table::{Attribute, Cell, Color, Table};
 use mesa::node::r#struct::NodeDetails;
 use serde_json::Value;
 
 pub fn print_table(nodes_status: Vec<NodeDetails>) {
+    println!("{}", get_table(&nodes_status, None));
+}
+
+/// Power status, configuration status and error count of a node. Used to detect node changes
+/// between refreshes
+pub type NodeWatchState = (String, String, String);
+
+pub fn get_node_watch_state(node_details: &NodeDetails) -> NodeWatchState {
+    (
+        node_details.power_status.clone(),
+        node_details.configuration_status.clone(),
+        node_details.error_count.clone(),
+    )
+}
+
+/// Returns the nodes table. If the state of the nodes in the previous refresh is provided, then
+/// power status, configuration status and error count cells which changed are highlighted
+pub fn get_table(
+    nodes_status: &[NodeDetails],
+    previous_node_state_map_opt: Option<&HashMap<String, NodeWatchState>>,
+) -> Table {
     let mut table = Table::new();
 
     table.set_header(vec![
@@ -19,20 +42,45 @@ pub fn print_table(nodes_status: Vec<NodeDetails>) {
     ]);
 
     for node_status in nodes_status {
+        let (power_status_changed, configuration_status_changed, error_count_changed) =
+            match previous_node_state_map_opt
+                .and_then(|previous_node_state_map| previous_node_state_map.get(&node_status.xname))
+            {
+                Some((power_status, configuration_status, error_count)) => (
+                    node_status.power_status.ne(power_status),
+                    node_status.configuration_status.ne(configuration_status),
+                    node_status.error_count.ne(error_count),
+                ),
+                None => (false, false, false),
+            };
+
         table.add_row(vec![
-            Cell::new(node_status.xname),
-            Cell::new(node_status.nid),
-            Cell::new(node_status.power_status),
-            Cell::new(node_status.desired_configuration),
-            Cell::new(node_status.configuration_status),
-            Cell::new(node_status.enabled),
-            Cell::new(node_status.error_count),
-            Cell::new(node_status.boot_configuration),
-            Cell::new(node_status.boot_image_id),
+            Cell::new(&node_status.xname),
+            Cell::new(&node_status.nid),
+            get_cell(&node_status.power_status, power_status_changed),
+            Cell::new(&node_status.desired_configuration),
+            get_cell(
+                &node_status.configuration_status,
+                configuration_status_changed,
+            ),
+            Cell::new(&node_status.enabled),
+            get_cell(&node_status.error_count, error_count_changed),
+            Cell::new(&node_status.boot_configuration),
+            Cell::new(&node_status.boot_image_id),
         ]);
     }
 
-    println!("{table}");
+    table
+}
+
+fn get_cell(content: &str, changed: bool) -> Cell {
+    if changed {
+        Cell::new(content)
+            .fg(Color::Yellow)
+            .add_attribute(Attribute::Bold)
+    } else {
+        Cell::new(content)
+    }
 }
 
 pub fn nodes_to_string_format_one_line(nodes: Option<&Vec<Value>>) -> String {
@@ -125,6 +173,17 @@ pub async fn validate_xnames(
     true
 } */
 
+/// Returns the image id in a BSS boot param. Kernel path has format
+/// 's3://boot-images/<image id>/kernel'
+pub fn get_image_id_from_boot_param(boot_param: &Value) -> Option<String> {
+    boot_param
+        .get("kernel")
+        .and_then(|kernel_value| kernel_value.as_str())
+        .and_then(|kernel_path| kernel_path.strip_prefix("s3://boot-images/"))
+        .and_then(|prefix_strip_kernel_path| prefix_strip_kernel_path.strip_suffix("/kernel"))
+        .map(str::to_string)
+}
+
 pub fn get_node_vec_booting_image(image_id: &str, boot_param_vec: &[Value]) -> Vec<String> {
     let mut node_booting_image_vec = boot_param_vec
         .iter()
@@ -0,0 +1,82 @@
+/// Client for the Power Control Service (PCS). PCS replaces CAPMC in CSM 1.4 and newer releases
+pub mod http_client {
+
+    use std::error::Error;
+
+    use serde_json::{json, Value};
+
+    use crate::common::http_client_utils;
+
+    /// Creates a PCS transition. Operation is one of 'on', 'off', 'soft-off', 'soft-restart',
+    /// 'hard-restart', 'init' or 'force-off'. Returns the transition created, its id is in
+    /// 'transitionID'
+    pub async fn post_transition(
+        client: &reqwest::Client,
+        shasta_token: &str,
+        shasta_base_url: &str,
+        operation: &str,
+        xname_vec: &[String],
+    ) -> Result<Value, Box<dyn Error>> {
+        let api_url = format!("{}/power-control/v1/transitions", shasta_base_url);
+
+        let payload = json!({
+            "operation": operation,
+            "location": xname_vec
+                .iter()
+                .map(|xname| json!({ "xname": xname }))
+                .collect::<Vec<Value>>(),
+        });
+
+        log::debug!("PCS transition request:\n{:#?}", payload);
+
+        let resp = client
+            .post(api_url)
+            .bearer_auth(shasta_token)
+            .json(&payload)
+            .send()
+            .await?;
+
+        http_client_utils::get_response_value(resp, "PCS").await
+    }
+
+    /// Returns a PCS transition, including the status of the task related to each xname
+    pub async fn get_transition(
+        client: &reqwest::Client,
+        shasta_token: &str,
+        shasta_base_url: &str,
+        transition_id: &str,
+    ) -> Result<Value, Box<dyn Error>> {
+        let api_url = format!(
+            "{}/power-control/v1/transitions/{}",
+            shasta_base_url, transition_id
+        );
+
+        let resp = client.get(api_url).bearer_auth(shasta_token).send().await?;
+
+        http_client_utils::get_response_value(resp, "PCS").await
+    }
+
+    /// Returns the power status of a list of xnames
+    pub async fn get_power_status(
+        client: &reqwest::Client,
+        shasta_token: &str,
+        shasta_base_url: &str,
+        xname_vec: &[String],
+    ) -> Result<Value, Box<dyn Error>> {
+        let api_url = format!("{}/power-control/v1/power-status", shasta_base_url);
+
+        let resp = client
+            .get(api_url)
+            .query(
+                &xname_vec
+                    .iter()
+                    .map(|xname| ("xname", xname))
+                    .collect::<Vec<(&str, &String)>>(),
+            )
+            .bearer_auth(shasta_token)
+            .send()
+            .await?;
+
+        http_client_utils::get_response_value(resp, "PCS").await
+    }
+}
@@ -0,0 +1,659 @@
+use std::{collections::HashMap, error::Error};
+
+use config::Config;
+use serde_json::Value;
+
+use crate::common::{http_client_utils, pcs};
+
+const PCS_TRANSITION_POLL_INTERVAL_SECS: u64 = 5;
+const PCS_TRANSITION_TIMEOUT_SECS: u64 = 600;
+
+/// Power operations manta can submit to a power backend
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PowerOperation {
+    On,
+    Off,
+    Reset,
+}
+
+impl PowerOperation {
+    /// Power state nodes should end up with once the operation finishes
+    pub fn target_state(&self) -> &'static str {
+        match self {
+            PowerOperation::On | PowerOperation::Reset => "on",
+            PowerOperation::Off => "off",
+        }
+    }
+}
+
+/// Power management API. CSM provides CAPMC up to CSM 1.3 and PCS from CSM 1.4
+pub trait PowerBackend {
+    /// Submits a power operation and returns as soon as the backend accepts it
+    async fn transition(
+        &self,
+        shasta_token: &str,
+        shasta_base_url: &str,
+        shasta_root_cert: &[u8],
+        xname_vec: &[String],
+        operation: PowerOperation,
+        force: bool,
+        reason_opt: Option<&String>,
+    ) -> Result<Value, Box<dyn Error>>;
+
+    /// Submits a power operation and waits until the backend finishes processing it
+    async fn transition_sync(
+        &self,
+        shasta_token: &str,
+        shasta_base_url: &str,
+        shasta_root_cert: &[u8],
+        xname_vec: &[String],
+        operation: PowerOperation,
+        force: bool,
+        reason_opt: Option<&String>,
+    ) -> Result<Value, Box<dyn Error>>;
+
+    /// Returns the power state ('on', 'off', 'undefined', ...) of each xname
+    async fn status(
+        &self,
+        shasta_token: &str,
+        shasta_base_url: &str,
+        shasta_root_cert: &[u8],
+        xname_vec: &[String],
+    ) -> Result<HashMap<String, String>, Box<dyn Error>>;
+}
+
+/// Power backend for CSM releases using CAPMC
+pub struct CapmcBackend;
+
+impl PowerBackend for CapmcBackend {
+    async fn transition(
+        &self,
+        shasta_token: &str,
+        shasta_base_url: &str,
+        shasta_root_cert: &[u8],
+        xname_vec: &[String],
+        operation: PowerOperation,
+        force: bool,
+        reason_opt: Option<&String>,
+    ) -> Result<Value, Box<dyn Error>> {
+        let resp = match operation {
+            PowerOperation::On => {
+                mesa::capmc::http_client::node_power_on::post(
+                    shasta_token,
+                    shasta_base_url,
+                    shasta_root_cert,
+                    xname_vec.to_vec(),
+                    reason_opt.cloned(),
+                )
+                .await?
+            }
+            PowerOperation::Off => {
+                mesa::capmc::http_client::node_power_off::post(
+                    shasta_token,
+                    shasta_base_url,
+                    shasta_root_cert,
+                    xname_vec.to_vec(),
+                    reason_opt.cloned(),
+                    force,
+                )
+                .await?
+            }
+            // CAPMC has no asynchronous node reset
+            PowerOperation::Reset => {
+                mesa::capmc::http_client::node_power_reset::post_sync_vec(
+                    shasta_token,
+                    shasta_base_url,
+                    shasta_root_cert,
+                    xname_vec.to_vec(),
+                    reason_opt.cloned(),
+                    force,
+                )
+                .await?
+            }
+        };
+
+        Ok(resp)
+    }
+
+    async fn transition_sync(
+        &self,
+        shasta_token: &str,
+        shasta_base_url: &str,
+        shasta_root_cert: &[u8],
+        xname_vec: &[String],
+        operation: PowerOperation,
+        force: bool,
+        reason_opt: Option<&String>,
+    ) -> Result<Value, Box<dyn Error>> {
+        let resp = match operation {
+            PowerOperation::On => {
+                mesa::capmc::http_client::node_power_on::post_sync(
+                    shasta_token,
+                    shasta_base_url,
+                    shasta_root_cert,
+                    xname_vec.to_vec(),
+                    reason_opt.cloned(),
+                )
+                .await?
+            }
+            PowerOperation::Off => {
+                mesa::capmc::http_client::node_power_off::post_sync(
+                    shasta_token,
+                    shasta_base_url,
+                    shasta_root_cert,
+                    xname_vec.to_vec(),
+                    reason_opt.cloned(),
+                    force,
+                )
+                .await?
+            }
+            PowerOperation::Reset => {
+                mesa::capmc::http_client::node_power_reset::post_sync_vec(
+                    shasta_token,
+                    shasta_base_url,
+                    shasta_root_cert,
+                    xname_vec.to_vec(),
+                    reason_opt.cloned(),
+                    force,
+                )
+                .await?
+            }
+        };
+
+        Ok(resp)
+    }
+
+    async fn status(
+        &self,
+        shasta_token: &str,
+        shasta_base_url: &str,
+        shasta_root_cert: &[u8],
+        xname_vec: &[String],
+    ) -> Result<HashMap<String, String>, Box<dyn Error>> {
+        let node_status = mesa::capmc::http_client::node_power_status::post(
+            shasta_token,
+            shasta_base_url,
+            shasta_root_cert,
+            &xname_vec.to_vec(),
+        )
+        .await?;
+
+        Ok(get_power_state_map_from_capmc_status(&node_status))
+    }
+}
+
+/// Power backend for CSM releases using PCS
+#[derive(Default)]
+pub struct PcsBackend {
+    client_opt: Option<reqwest::Client>,
+}
+
+impl PcsBackend {
+    /// PCS backend sending requests through 'client' instead of a client trusting the Shasta
+    /// root cert
+    pub fn with_client(client: reqwest::Client) -> Self {
+        PcsBackend {
+            client_opt: Some(client),
+        }
+    }
+
+    fn get_client(&self, shasta_root_cert: &[u8]) -> Result<reqwest::Client, reqwest::Error> {
+        match &self.client_opt {
+            Some(client) => Ok(client.clone()),
+            None => http_client_utils::get_client(shasta_root_cert),
+        }
+    }
+
+    /// Translates a power operation to a PCS transition operation
+    fn get_pcs_operation(operation: PowerOperation, force: bool) -> &'static str {
+        match (operation, force) {
+            (PowerOperation::On, _) => "on",
+            (PowerOperation::Off, false) => "soft-off",
+            (PowerOperation::Off, true) => "force-off",
+            (PowerOperation::Reset, false) => "soft-restart",
+            (PowerOperation::Reset, true) => "hard-restart",
+        }
+    }
+}
+
+impl PowerBackend for PcsBackend {
+    async fn transition(
+        &self,
+        shasta_token: &str,
+        shasta_base_url: &str,
+        shasta_root_cert: &[u8],
+        xname_vec: &[String],
+        operation: PowerOperation,
+        force: bool,
+        reason_opt: Option<&String>,
+    ) -> Result<Value, Box<dyn Error>> {
+        // PCS does not store a reason, it is only kept in the audit file
+        log::info!(
+            "PCS transition {:?} (force: {}) for {:?}. Reason: {:?}",
+            operation,
+            force,
+            xname_vec,
+            reason_opt
+        );
+
+        pcs::http_client::post_transition(
+            &self.get_client(shasta_root_cert)?,
+            shasta_token,
+            shasta_base_url,
+            PcsBackend::get_pcs_operation(operation, force),
+            xname_vec,
+        )
+        .await
+    }
+
+    async fn transition_sync(
+        &self,
+        shasta_token: &str,
+        shasta_base_url: &str,
+        shasta_root_cert: &[u8],
+        xname_vec: &[String],
+        operation: PowerOperation,
+        force: bool,
+        reason_opt: Option<&String>,
+    ) -> Result<Value, Box<dyn Error>> {
+        let transition = self
+            .transition(
+                shasta_token,
+                shasta_base_url,
+                shasta_root_cert,
+                xname_vec,
+                operation,
+                force,
+                reason_opt,
+            )
+            .await?;
+
+        let transition_id = transition["transitionID"]
+            .as_str()
+            .ok_or("PCS transition has no 'transitionID'")?;
+
+        let client = self.get_client(shasta_root_cert)?;
+
+        let start = std::time::Instant::now();
+
+        loop {
+            let transition = pcs::http_client::get_transition(
+                &client,
+                shasta_token,
+                shasta_base_url,
+                transition_id,
+            )
+            .await?;
+
+            let transition_status = transition["transitionStatus"].as_str().unwrap_or_default();
+
+            log::info!(
+                "PCS transition {} status: {}",
+                transition_id,
+                transition_status
+            );
+
+            if ["completed", "aborted"].contains(&transition_status) {
+                return Ok(transition);
+            }
+
+            if start.elapsed().as_secs() > PCS_TRANSITION_TIMEOUT_SECS {
+                return Err(format!(
+                    "PCS transition {} did not finish after {} seconds",
+                    transition_id, PCS_TRANSITION_TIMEOUT_SECS
+                )
+                .into());
+            }
+
+            tokio::time::sleep(std::time::Duration::from_secs(
+                PCS_TRANSITION_POLL_INTERVAL_SECS,
+            ))
+            .await;
+        }
+    }
+
+    async fn status(
+        &self,
+        shasta_token: &str,
+        shasta_base_url: &str,
+        shasta_root_cert: &[u8],
+        xname_vec: &[String],
+    ) -> Result<HashMap<String, String>, Box<dyn Error>> {
+        let power_status = pcs::http_client::get_power_status(
+            &self.get_client(shasta_root_cert)?,
+            shasta_token,
+            shasta_base_url,
+            xname_vec,
+        )
+        .await?;
+
+        Ok(power_status["status"]
+            .as_array()
+            .unwrap_or(&Vec::new())
+            .iter()
+            .filter_map(|node_status| {
+                node_status["xname"].as_str().map(|xname| {
+                    (
+                        xname.to_string(),
+                        node_status["powerState"]
+                            .as_str()
+                            .unwrap_or("undefined")
+                            .to_string(),
+                    )
+                })
+            })
+            .collect())
+    }
+}
+
+/// Power backend configured for the site. Defaults to CAPMC if the site does not define
+/// 'power_backend'
+pub enum SitePowerBackend {
+    Capmc(CapmcBackend),
+    Pcs(PcsBackend),
+}
+
+impl SitePowerBackend {
+    /// Reads 'sites.<site>.power_backend' ('capmc' or 'pcs') from manta configuration
+    pub fn from_settings(settings: &Config) -> Self {
+        let site_name = settings.get_string("site").unwrap_or_default();
+
+        let power_backend = settings
+            .get_string(&format!("sites.{}.power_backend", site_name))
+            .unwrap_or("capmc".to_string());
+
+        match power_backend.to_lowercase().as_str() {
+            "capmc" => SitePowerBackend::Capmc(CapmcBackend),
+            "pcs" => SitePowerBackend::Pcs(PcsBackend::default()),
+            _ => {
+                eprintln!(
+                    "Power backend '{}' not valid, please choose one of 'capmc' or 'pcs'. Exit",
+                    power_backend
+                );
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+impl PowerBackend for SitePowerBackend {
+    async fn transition(
+        &self,
+        shasta_token: &str,
+        shasta_base_url: &str,
+        shasta_root_cert: &[u8],
+        xname_vec: &[String],
+        operation: PowerOperation,
+        force: bool,
+        reason_opt: Option<&String>,
+    ) -> Result<Value, Box<dyn Error>> {
+        match self {
+            SitePowerBackend::Capmc(backend) => {
+                backend
+                    .transition(
+                        shasta_token,
+                        shasta_base_url,
+                        shasta_root_cert,
+                        xname_vec,
+                        operation,
+                        force,
+                        reason_opt,
+                    )
+                    .await
+            }
+            SitePowerBackend::Pcs(backend) => {
+                backend
+                    .transition(
+                        shasta_token,
+                        shasta_base_url,
+                        shasta_root_cert,
+                        xname_vec,
+                        operation,
+                        force,
+                        reason_opt,
+                    )
+                    .await
+            }
+        }
+    }
+
+    async fn transition_sync(
+        &self,
+        shasta_token: &str,
+        shasta_base_url: &str,
+        shasta_root_cert: &[u8],
+        xname_vec: &[String],
+        operation: PowerOperation,
+        force: bool,
+        reason_opt: Option<&String>,
+    ) -> Result<Value, Box<dyn Error>> {
+        match self {
+            SitePowerBackend::Capmc(backend) => {
+                backend
+                    .transition_sync(
+                        shasta_token,
+                        shasta_base_url,
+                        shasta_root_cert,
+                        xname_vec,
+                        operation,
+                        force,
+                        reason_opt,
+                    )
+                    .await
+            }
+            SitePowerBackend::Pcs(backend) => {
+                backend
+                    .transition_sync(
+                        shasta_token,
+                        shasta_base_url,
+                        shasta_root_cert,
+                        xname_vec,
+                        operation,
+                        force,
+                        reason_opt,
+                    )
+                    .await
+            }
+        }
+    }
+
+    async fn status(
+        &self,
+        shasta_token: &str,
+        shasta_base_url: &str,
+        shasta_root_cert: &[u8],
+        xname_vec: &[String],
+    ) -> Result<HashMap<String, String>, Box<dyn Error>> {
+        match self {
+            SitePowerBackend::Capmc(backend) => {
+                backend
+                    .status(shasta_token, shasta_base_url, shasta_root_cert, xname_vec)
+                    .await
+            }
+            SitePowerBackend::Pcs(backend) => {
+                backend
+                    .status(shasta_token, shasta_base_url, shasta_root_cert, xname_vec)
+                    .await
+            }
+        }
+    }
+}
+
+/// CAPMC groups xnames per power state, eg {"e": 0, "err_msg": "", "on": [...], "off": [...]}
+fn get_power_state_map_from_capmc_status(node_status: &Value) -> HashMap<String, String> {
+    node_status
+        .as_object()
+        .map(|node_status_map| {
+            node_status_map
+                .iter()
+                .filter(|(key, _)| !["e", "err_msg"].contains(&key.as_str()))
+                .filter_map(|(power_state, xname_vec)| {
+                    xname_vec.as_array().map(|xname_vec| {
+                        xname_vec
+                            .iter()
+                            .filter_map(|xname| xname.as_str())
+                            .map(|xname| (xname.to_string(), power_state.to_string()))
+                            .collect::<Vec<(String, String)>>()
+                    })
+                })
+                .flatten()
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use hyper::{
+        service::{make_service_fn, service_fn},
+        Body, Method, Request, Response, Server,
+    };
+    use serde_json::json;
+
+    use super::*;
+
+    const MOCK_TRANSITION_ID: &str = "8b2a3ab2-2d3f-4a8e-8b5c-4b3b56f3b7a1";
+
+    /// Minimal PCS server answering transitions and power status requests. Every node is 'on'
+    async fn mock_pcs_handler(req: Request<Body>) -> Result<Response<Body>, Infallible> {
+        let transition_path = format!("/power-control/v1/transitions/{}", MOCK_TRANSITION_ID);
+
+        let resp_body = match (req.method(), req.uri().path()) {
+            (&Method::POST, "/power-control/v1/transitions") => {
+                let body = hyper::body::to_bytes(req.into_body()).await.unwrap();
+                let transition: Value = serde_json::from_slice(&body).unwrap();
+
+                json!({
+                    "transitionID": MOCK_TRANSITION_ID,
+                    "operation": transition["operation"],
+                })
+            }
+            (&Method::GET, path) if path == transition_path => json!({
+                "transitionID": MOCK_TRANSITION_ID,
+                "operation": "soft-restart",
+                "transitionStatus": "completed",
+                "tasks": [
+                    {
+                        "xname": "x1000c0s0b0n0",
+                        "taskStatus": "succeeded",
+                        "taskStatusDescription": "Transition confirmed, on",
+                    }
+                ],
+            }),
+            (&Method::GET, "/power-control/v1/power-status") => {
+                let xname_vec: Vec<&str> = req
+                    .uri()
+                    .query()
+                    .unwrap_or_default()
+                    .split('&')
+                    .filter_map(|param| param.strip_prefix("xname="))
+                    .collect();
+
+                json!({
+                    "status": xname_vec
+                        .iter()
+                        .map(|xname| json!({ "xname": xname, "powerState": "on" }))
+                        .collect::<Vec<Value>>(),
+                })
+            }
+            _ => {
+                return Ok(Response::builder()
+                    .status(404)
+                    .body(Body::from("Not found"))
+                    .unwrap())
+            }
+        };
+
+        Ok(Response::new(Body::from(resp_body.to_string())))
+    }
+
+    /// Starts the mock PCS server in a random port and returns its base URL
+    fn start_mock_pcs_server() -> String {
+        let make_service =
+            make_service_fn(|_conn| async { Ok::<_, Infallible>(service_fn(mock_pcs_handler)) });
+
+        let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+
+        let base_url = format!("http://{}", server.local_addr());
+
+        tokio::spawn(server);
+
+        base_url
+    }
+
+    #[tokio::test]
+    async fn test_pcs_transition() {
+        let base_url = start_mock_pcs_server();
+
+        let transition = PcsBackend::with_client(reqwest::Client::new())
+            .transition(
+                "token",
+                &base_url,
+                &[],
+                &["x1000c0s0b0n0".to_string()],
+                PowerOperation::Off,
+                true,
+                None,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(transition["transitionID"], MOCK_TRANSITION_ID);
+        assert_eq!(transition["operation"], "force-off");
+    }
+
+    #[tokio::test]
+    async fn test_pcs_transition_sync() {
+        let base_url = start_mock_pcs_server();
+
+        let transition = PcsBackend::with_client(reqwest::Client::new())
+            .transition_sync(
+                "token",
+                &base_url,
+                &[],
+                &["x1000c0s0b0n0".to_string()],
+                PowerOperation::Reset,
+                false,
+                Some(&"test".to_string()),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(transition["transitionStatus"], "completed");
+        assert_eq!(transition["tasks"][0]["taskStatus"], "succeeded");
+    }
+
+    #[tokio::test]
+    async fn test_pcs_status() {
+        let base_url = start_mock_pcs_server();
+
+        let power_state_map = PcsBackend::with_client(reqwest::Client::new())
+            .status(
+                "token",
+                &base_url,
+                &[],
+                &["x1000c0s0b0n0".to_string(), "x1000c0s0b0n1".to_string()],
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(power_state_map.len(), 2);
+        assert_eq!(power_state_map["x1000c0s0b0n1"], "on");
+    }
+
+    #[test]
+    fn test_get_power_state_map_from_capmc_status() {
+        let power_state_map = get_power_state_map_from_capmc_status(&json!({
+            "e": 0,
+            "err_msg": "",
+            "on": ["x1000c0s0b0n0"],
+            "off": ["x1000c0s0b0n1", "x1000c0s1b0n0"],
+        }));
+
+        assert_eq!(power_state_map.len(), 3);
+        assert_eq!(power_state_map["x1000c0s0b0n0"], "on");
+        assert_eq!(power_state_map["x1000c0s1b0n0"], "off");
+    }
+}
@@ -0,0 +1,378 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use comfy_table::Table;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::common::{
+    node_map,
+    power_backend::{PowerBackend, PowerOperation},
+};
+
+pub const DEFAULT_POWER_TRANSITION_TIMEOUT_SECS: u64 = 300;
+
+const POWER_STATUS_POLL_INTERVAL_SECS: u64 = 5;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum PowerTransitionResult {
+    Pending,
+    Succeeded,
+    Failed,
+    Timeout,
+}
+
+impl PowerTransitionResult {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PowerTransitionResult::Pending => "pending",
+            PowerTransitionResult::Succeeded => "succeeded",
+            PowerTransitionResult::Failed => "failed",
+            PowerTransitionResult::Timeout => "timeout",
+        }
+    }
+}
+
+/// Outcome of a power transition for a single node
+#[derive(Serialize, Debug, Clone)]
+pub struct NodePowerTransition {
+    pub xname: String,
+    pub hostname: String,
+    pub target_state: String,
+    pub power_state: String,
+    pub result: PowerTransitionResult,
+    pub error: String,
+}
+
+/// Runs a power operation against a list of nodes. The power request is sent once, then the power
+/// state of each node is tracked until it reaches the target state or 'timeout_secs' expires.
+/// A reset is done in 2 steps (power off and power on), only nodes which powered off are powered
+/// on again. Returns the result for each node sorted by xname
+pub async fn exec(
+    power_backend: &impl PowerBackend,
+    shasta_token: &str,
+    shasta_base_url: &str,
+    shasta_root_cert: &[u8],
+    xname_vec: &[String],
+    operation: PowerOperation,
+    force: bool,
+    reason_opt: Option<&String>,
+    timeout_secs: u64,
+) -> Vec<NodePowerTransition> {
+    let deadline = Instant::now() + Duration::from_secs(timeout_secs);
+
+    let mut node_power_transition_vec = if operation == PowerOperation::Reset {
+        let node_power_off_vec = exec_single_transition(
+            power_backend,
+            shasta_token,
+            shasta_base_url,
+            shasta_root_cert,
+            xname_vec,
+            PowerOperation::Off,
+            force,
+            reason_opt,
+            deadline,
+        )
+        .await;
+
+        let (node_off_vec, mut node_power_transition_vec): (Vec<_>, Vec<_>) = node_power_off_vec
+            .into_iter()
+            .partition(|node| node.result == PowerTransitionResult::Succeeded);
+
+        // Nodes which did not power off won't be powered on
+        for node in node_power_transition_vec.iter_mut() {
+            node.target_state = operation.target_state().to_string();
+        }
+
+        node_power_transition_vec.extend(
+            exec_single_transition(
+                power_backend,
+                shasta_token,
+                shasta_base_url,
+                shasta_root_cert,
+                &node_off_vec
+                    .into_iter()
+                    .map(|node| node.xname)
+                    .collect::<Vec<String>>(),
+                PowerOperation::On,
+                false,
+                reason_opt,
+                deadline,
+            )
+            .await,
+        );
+
+        node_power_transition_vec
+    } else {
+        exec_single_transition(
+            power_backend,
+            shasta_token,
+            shasta_base_url,
+            shasta_root_cert,
+            xname_vec,
+            operation,
+            force,
+            reason_opt,
+            deadline,
+        )
+        .await
+    };
+
+    let xname_hostname_map =
+        node_map::get_xname_hostname_map(shasta_token, shasta_base_url, shasta_root_cert).await;
+
+    for node in node_power_transition_vec.iter_mut() {
+        node.hostname = xname_hostname_map
+            .get(&node.xname)
+            .cloned()
+            .unwrap_or_default();
+    }
+
+    node_power_transition_vec.sort_by(|a, b| a.xname.cmp(&b.xname));
+
+    node_power_transition_vec
+}
+
+/// Sends a power request once and tracks the power state of each node until it reaches the target
+/// state or the deadline is reached
+async fn exec_single_transition(
+    power_backend: &impl PowerBackend,
+    shasta_token: &str,
+    shasta_base_url: &str,
+    shasta_root_cert: &[u8],
+    xname_vec: &[String],
+    operation: PowerOperation,
+    force: bool,
+    reason_opt: Option<&String>,
+    deadline: Instant,
+) -> Vec<NodePowerTransition> {
+    let target_state = operation.target_state();
+
+    let mut node_power_transition_map: HashMap<String, NodePowerTransition> = xname_vec
+        .iter()
+        .map(|xname| {
+            (
+                xname.clone(),
+                NodePowerTransition {
+                    xname: xname.clone(),
+                    hostname: "".to_string(),
+                    target_state: target_state.to_string(),
+                    power_state: "unknown".to_string(),
+                    result: PowerTransitionResult::Pending,
+                    error: "".to_string(),
+                },
+            )
+        })
+        .collect();
+
+    if xname_vec.is_empty() {
+        return Vec::new();
+    }
+
+    log::info!("Power {} nodes: {:?}", target_state, xname_vec);
+
+    match power_backend
+        .transition(
+            shasta_token,
+            shasta_base_url,
+            shasta_root_cert,
+            xname_vec,
+            operation,
+            force,
+            reason_opt,
+        )
+        .await
+    {
+        Ok(transition) => {
+            log::debug!("Power transition response:\n{:#?}", transition);
+
+            for (xname, error) in get_node_error_map_from_transition(&transition) {
+                if let Some(node) = node_power_transition_map.get_mut(&xname) {
+                    node.result = PowerTransitionResult::Failed;
+                    node.error = error;
+                }
+            }
+        }
+        Err(error) => {
+            for node in node_power_transition_map.values_mut() {
+                node.result = PowerTransitionResult::Failed;
+                node.error = error.to_string();
+            }
+        }
+    }
+
+    loop {
+        let xname_pending_vec: Vec<String> = node_power_transition_map
+            .values()
+            .filter(|node| node.result == PowerTransitionResult::Pending)
+            .map(|node| node.xname.clone())
+            .collect();
+
+        if xname_pending_vec.is_empty() {
+            break;
+        }
+
+        match power_backend
+            .status(
+                shasta_token,
+                shasta_base_url,
+                shasta_root_cert,
+                &xname_pending_vec,
+            )
+            .await
+        {
+            Ok(power_state_map) => {
+                for xname in &xname_pending_vec {
+                    let node = node_power_transition_map.get_mut(xname).unwrap();
+
+                    node.power_state = power_state_map
+                        .get(xname)
+                        .cloned()
+                        .unwrap_or("undefined".to_string());
+
+                    if node.power_state.eq(target_state) {
+                        node.result = PowerTransitionResult::Succeeded;
+                    }
+                }
+            }
+            // Power status errors may be transient, try again in next iteration
+            Err(error) => log::warn!("Could not get nodes power status: {}", error),
+        }
+
+        if Instant::now() >= deadline {
+            for node in node_power_transition_map
+                .values_mut()
+                .filter(|node| node.result == PowerTransitionResult::Pending)
+            {
+                node.result = PowerTransitionResult::Timeout;
+                node.error = format!("Node did not reach power state '{}' in time", target_state);
+            }
+
+            break;
+        }
+
+        tokio::time::sleep(Duration::from_secs(POWER_STATUS_POLL_INTERVAL_SECS)).await;
+    }
+
+    node_power_transition_map.into_values().collect()
+}
+
+/// Returns the nodes the power backend rejected together with the error. CAPMC lists them in
+/// 'xnames' and PCS in 'tasks'
+fn get_node_error_map_from_transition(transition: &Value) -> HashMap<String, String> {
+    let capmc_node_error_iter = transition["xnames"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|node| node["e"].as_i64().unwrap_or(0) != 0)
+        .filter_map(|node| {
+            node["xname"].as_str().map(|xname| {
+                (
+                    xname.to_string(),
+                    node["err_msg"].as_str().unwrap_or_default().to_string(),
+                )
+            })
+        });
+
+    let pcs_node_error_iter = transition["tasks"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|task| task["taskStatus"].eq("failed"))
+        .filter_map(|task| {
+            task["xname"].as_str().map(|xname| {
+                (
+                    xname.to_string(),
+                    task["error"]
+                        .as_str()
+                        .or(task["taskStatusDescription"].as_str())
+                        .unwrap_or_default()
+                        .to_string(),
+                )
+            })
+        });
+
+    capmc_node_error_iter.chain(pcs_node_error_iter).collect()
+}
+
+/// Returns true if all nodes reached their target power state
+pub fn is_success(node_power_transition_vec: &[NodePowerTransition]) -> bool {
+    node_power_transition_vec
+        .iter()
+        .all(|node| node.result == PowerTransitionResult::Succeeded)
+}
+
+pub fn print_result(
+    node_power_transition_vec: &[NodePowerTransition],
+    output_opt: Option<&String>,
+) {
+    if output_opt.is_some() && output_opt.unwrap().eq("json") {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(node_power_transition_vec).unwrap()
+        );
+    } else {
+        let mut table = Table::new();
+
+        table.set_header(vec![
+            "XNAME",
+            "Hostname",
+            "Target State",
+            "Power State",
+            "Result",
+            "Error",
+        ]);
+
+        for node in node_power_transition_vec {
+            table.add_row(vec![
+                node.xname.clone(),
+                node.hostname.clone(),
+                node.target_state.clone(),
+                node.power_state.clone(),
+                node.result.as_str().to_string(),
+                node.error.clone(),
+            ]);
+        }
+
+        println!("{table}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_get_node_error_map_from_capmc_transition() {
+        let node_error_map = get_node_error_map_from_transition(&json!({
+            "e": -1,
+            "err_msg": "Errors encountered with 1/2 Xnames issued Off",
+            "xnames": [
+                { "xname": "x1000c0s0b0n0", "e": -1, "err_msg": "NodeBMC unreachable" },
+                { "xname": "x1000c0s0b0n1", "e": 0, "err_msg": "" },
+            ],
+        }));
+
+        assert_eq!(node_error_map.len(), 1);
+        assert_eq!(node_error_map["x1000c0s0b0n0"], "NodeBMC unreachable");
+    }
+
+    #[test]
+    fn test_get_node_error_map_from_pcs_transition() {
+        let node_error_map = get_node_error_map_from_transition(&json!({
+            "transitionID": "8b2a3ab2-2d3f-4a8e-8b5c-4b3b56f3b7a1",
+            "tasks": [
+                { "xname": "x1000c0s0b0n0", "taskStatus": "succeeded" },
+                { "xname": "x1000c0s0b0n1", "taskStatus": "failed", "error": "Redfish timeout" },
+            ],
+        }));
+
+        assert_eq!(node_error_map.len(), 1);
+        assert_eq!(node_error_map["x1000c0s0b0n1"], "Redfish timeout");
+    }
+}
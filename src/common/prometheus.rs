@@ -0,0 +1,86 @@
+/// Prometheus gauge with all its samples
+#[derive(Debug, Clone)]
+pub struct Gauge {
+    name: String,
+    help: String,
+    sample_vec: Vec<(Vec<(String, String)>, f64)>,
+}
+
+impl Gauge {
+    pub fn new(name: &str, help: &str) -> Self {
+        Gauge {
+            name: name.to_string(),
+            help: help.to_string(),
+            sample_vec: Vec::new(),
+        }
+    }
+
+    pub fn add_sample(&mut self, label_vec: &[(&str, &str)], value: f64) {
+        self.sample_vec.push((
+            label_vec
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            value,
+        ));
+    }
+}
+
+/// Renders gauges in Prometheus text exposition format
+pub fn render(gauge_vec: &[Gauge]) -> String {
+    let mut output = String::new();
+
+    for gauge in gauge_vec {
+        output.push_str(&format!("# HELP {} {}\n", gauge.name, gauge.help));
+        output.push_str(&format!("# TYPE {} gauge\n", gauge.name));
+
+        for (label_vec, value) in &gauge.sample_vec {
+            if label_vec.is_empty() {
+                output.push_str(&format!("{} {}\n", gauge.name, value));
+            } else {
+                let labels = label_vec
+                    .iter()
+                    .map(|(name, value)| format!("{}=\"{}\"", name, escape_label_value(value)))
+                    .collect::<Vec<String>>()
+                    .join(",");
+
+                output.push_str(&format!("{}{{{}}} {}\n", gauge.name, labels, value));
+            }
+        }
+    }
+
+    output
+}
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let mut up = Gauge::new("manta_up", "Whether the last collection succeeded");
+        up.add_sample(&[], 1.0);
+
+        let mut nodes = Gauge::new("manta_nodes", "Number of nodes per power status");
+        nodes.add_sample(&[("hsm_group", "zinal"), ("power_status", "ON")], 30.0);
+        nodes.add_sample(&[("hsm_group", "zi\"nal"), ("power_status", "OFF")], 2.0);
+
+        assert_eq!(
+            render(&[up, nodes]),
+            "# HELP manta_up Whether the last collection succeeded\n\
+             # TYPE manta_up gauge\n\
+             manta_up 1\n\
+             # HELP manta_nodes Number of nodes per power status\n\
+             # TYPE manta_nodes gauge\n\
+             manta_nodes{hsm_group=\"zinal\",power_status=\"ON\"} 30\n\
+             manta_nodes{hsm_group=\"zi\\\"nal\",power_status=\"OFF\"} 2\n"
+        );
+    }
+}
@@ -0,0 +1,332 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+
+use crate::common::{
+    power_backend::{PowerBackend, PowerOperation},
+    power_transition,
+};
+
+const HEALTH_CHECK_POLL_INTERVAL_SECS: u64 = 30;
+
+/// Number of nodes rebooted at once, either a number of nodes or a percentage of the nodes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BatchSize {
+    Count(usize),
+    Percentage(usize),
+}
+
+impl BatchSize {
+    /// Returns the number of nodes in each batch, at least 1
+    pub fn get_batch_len(&self, node_count: usize) -> usize {
+        match self {
+            BatchSize::Count(count) => *count,
+            BatchSize::Percentage(percentage) => (node_count * percentage).div_ceil(100),
+        }
+        .max(1)
+    }
+}
+
+/// Parses a batch size with format 'N' or 'N%'
+pub fn parse_batch_size(batch_size: &str) -> Result<BatchSize, String> {
+    let batch_size = batch_size.trim();
+
+    if let Some(percentage) = batch_size.strip_suffix('%') {
+        match percentage.parse::<usize>() {
+            Ok(percentage) if (1..=100).contains(&percentage) => {
+                Ok(BatchSize::Percentage(percentage))
+            }
+            _ => Err(format!(
+                "Batch size '{}' not valid, percentage must be between 1% and 100%",
+                batch_size
+            )),
+        }
+    } else {
+        match batch_size.parse::<usize>() {
+            Ok(count) if count > 0 => Ok(BatchSize::Count(count)),
+            _ => Err(format!(
+                "Batch size '{}' not valid, it must be a number of nodes (eg 4) or a percentage (eg 10%)",
+                batch_size
+            )),
+        }
+    }
+}
+
+/// Rolling reboot settings
+#[derive(Debug, Clone)]
+pub struct RollingOptions {
+    pub batch_size: BatchSize,
+    pub pause_secs: u64,
+    pub power_timeout_secs: u64,
+    pub health_timeout_secs: u64,
+}
+
+/// Reboots nodes in batches. Before moving to the next batch, all nodes in the current batch must
+/// be powered on, their CFS component configured and their CFS error count must not increase.
+/// The rollout stops as soon as a batch fails. Returns the reason the batch failed
+pub async fn exec(
+    power_backend: &impl PowerBackend,
+    shasta_token: &str,
+    shasta_base_url: &str,
+    shasta_root_cert: &[u8],
+    xname_vec: &[String],
+    force: bool,
+    reason_opt: Option<&String>,
+    rolling_options: &RollingOptions,
+) -> Result<(), String> {
+    let batch_len = rolling_options.batch_size.get_batch_len(xname_vec.len());
+    let batch_count = xname_vec.len().div_ceil(batch_len);
+
+    for (batch_index, batch_xname_vec) in xname_vec.chunks(batch_len).enumerate() {
+        println!(
+            "Batch {}/{}: rebooting nodes {}",
+            batch_index + 1,
+            batch_count,
+            batch_xname_vec.join(", ")
+        );
+
+        let error_count_before_map = get_cfs_error_count_map(
+            shasta_token,
+            shasta_base_url,
+            shasta_root_cert,
+            batch_xname_vec,
+        )
+        .await;
+
+        let reboot_start = Utc::now();
+
+        let node_power_transition_vec = power_transition::exec(
+            power_backend,
+            shasta_token,
+            shasta_base_url,
+            shasta_root_cert,
+            batch_xname_vec,
+            PowerOperation::Reset,
+            force,
+            reason_opt,
+            rolling_options.power_timeout_secs,
+        )
+        .await;
+
+        power_transition::print_result(&node_power_transition_vec, None);
+
+        if !power_transition::is_success(&node_power_transition_vec) {
+            return Err(format!(
+                "Batch {}/{} failed, some nodes did not reach power state 'on'",
+                batch_index + 1,
+                batch_count
+            ));
+        }
+
+        let unhealthy_node_vec = wait_for_healthy_nodes(
+            shasta_token,
+            shasta_base_url,
+            shasta_root_cert,
+            batch_xname_vec,
+            &error_count_before_map,
+            reboot_start,
+            rolling_options.health_timeout_secs,
+        )
+        .await;
+
+        if !unhealthy_node_vec.is_empty() {
+            return Err(format!(
+                "Batch {}/{} failed health check:\n{}",
+                batch_index + 1,
+                batch_count,
+                unhealthy_node_vec
+                    .iter()
+                    .map(|(xname, reason)| format!("Node {} not healthy: {}", xname, reason))
+                    .collect::<Vec<String>>()
+                    .join("\n")
+            ));
+        }
+
+        println!("Batch {}/{} healthy", batch_index + 1, batch_count);
+
+        if batch_index + 1 < batch_count && rolling_options.pause_secs > 0 {
+            println!(
+                "Waiting {} seconds before next batch",
+                rolling_options.pause_secs
+            );
+
+            tokio::time::sleep(Duration::from_secs(rolling_options.pause_secs)).await;
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the CFS error count of each node
+async fn get_cfs_error_count_map(
+    shasta_token: &str,
+    shasta_base_url: &str,
+    shasta_root_cert: &[u8],
+    xname_vec: &[String],
+) -> HashMap<String, u64> {
+    let mut error_count_map = HashMap::new();
+
+    for xname in xname_vec {
+        if let Ok(cfs_component) = mesa::cfs::component::shasta::http_client::get_single_component(
+            shasta_token,
+            shasta_base_url,
+            shasta_root_cert,
+            xname,
+        )
+        .await
+        {
+            error_count_map.insert(
+                xname.clone(),
+                cfs_component["errorCount"].as_u64().unwrap_or(0),
+            );
+        }
+    }
+
+    error_count_map
+}
+
+/// Waits until the CFS component of each node is configured after the reboot. A CFS component
+/// is considered configured after the reboot if it was seen not configured or if its layers were
+/// applied after the reboot started. Nodes without desired configuration are healthy once powered
+/// on. Returns the list of unhealthy nodes together with the reason
+async fn wait_for_healthy_nodes(
+    shasta_token: &str,
+    shasta_base_url: &str,
+    shasta_root_cert: &[u8],
+    xname_vec: &[String],
+    error_count_before_map: &HashMap<String, u64>,
+    reboot_start: DateTime<Utc>,
+    timeout_secs: u64,
+) -> Vec<(String, String)> {
+    let start = Instant::now();
+
+    let mut xname_pending_vec = xname_vec.to_vec();
+    let mut xname_not_configured_set: HashSet<String> = HashSet::new();
+    let mut unhealthy_node_vec = Vec::new();
+
+    while !xname_pending_vec.is_empty() {
+        let mut xname_still_pending_vec = Vec::new();
+
+        for xname in xname_pending_vec {
+            let cfs_component =
+                match mesa::cfs::component::shasta::http_client::get_single_component(
+                    shasta_token,
+                    shasta_base_url,
+                    shasta_root_cert,
+                    &xname,
+                )
+                .await
+                {
+                    Ok(cfs_component) => cfs_component,
+                    Err(error) => {
+                        log::warn!("Could not get CFS component {}: {:?}", xname, error);
+                        xname_still_pending_vec.push(xname);
+                        continue;
+                    }
+                };
+
+            let configuration_status = cfs_component["configurationStatus"]
+                .as_str()
+                .unwrap_or_default();
+
+            let error_count = cfs_component["errorCount"].as_u64().unwrap_or(0);
+            let error_count_before = error_count_before_map.get(&xname).copied().unwrap_or(0);
+
+            if error_count > error_count_before {
+                unhealthy_node_vec.push((
+                    xname,
+                    format!(
+                        "CFS error count increased from {} to {}",
+                        error_count_before, error_count
+                    ),
+                ));
+            } else if configuration_status.eq("failed") {
+                unhealthy_node_vec.push((xname, "CFS configuration failed".to_string()));
+            } else if cfs_component["desiredConfig"]
+                .as_str()
+                .unwrap_or_default()
+                .is_empty()
+            {
+                log::info!("Node {} has no desired configuration", xname);
+            } else if configuration_status.eq("configured")
+                && (xname_not_configured_set.contains(&xname)
+                    || is_cfs_component_updated_after(&cfs_component, reboot_start))
+            {
+                log::info!("Node {} configured", xname);
+            } else {
+                if !configuration_status.eq("configured") {
+                    xname_not_configured_set.insert(xname.clone());
+                }
+
+                xname_still_pending_vec.push(xname);
+            }
+        }
+
+        xname_pending_vec = xname_still_pending_vec;
+
+        if xname_pending_vec.is_empty() {
+            break;
+        }
+
+        if start.elapsed().as_secs() > timeout_secs {
+            for xname in xname_pending_vec {
+                unhealthy_node_vec.push((
+                    xname,
+                    format!(
+                        "CFS component not configured after {} seconds",
+                        timeout_secs
+                    ),
+                ));
+            }
+
+            break;
+        }
+
+        log::info!(
+            "Waiting for CFS to configure nodes: {:?}",
+            xname_pending_vec
+        );
+
+        tokio::time::sleep(Duration::from_secs(HEALTH_CHECK_POLL_INTERVAL_SECS)).await;
+    }
+
+    unhealthy_node_vec
+}
+
+/// Returns true if any layer of the CFS component was applied after 'date'
+fn is_cfs_component_updated_after(cfs_component: &Value, date: DateTime<Utc>) -> bool {
+    cfs_component["state"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|layer_state| layer_state["lastUpdated"].as_str())
+        .filter_map(|last_updated| DateTime::parse_from_rfc3339(last_updated).ok())
+        .any(|last_updated| last_updated.with_timezone(&Utc) > date)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_batch_size() {
+        assert_eq!(parse_batch_size("4"), Ok(BatchSize::Count(4)));
+        assert_eq!(parse_batch_size("25%"), Ok(BatchSize::Percentage(25)));
+        assert!(parse_batch_size("0").is_err());
+        assert!(parse_batch_size("0%").is_err());
+        assert!(parse_batch_size("101%").is_err());
+        assert!(parse_batch_size("four").is_err());
+    }
+
+    #[test]
+    fn test_get_batch_len() {
+        assert_eq!(BatchSize::Count(4).get_batch_len(10), 4);
+        assert_eq!(BatchSize::Percentage(25).get_batch_len(10), 3);
+        assert_eq!(BatchSize::Percentage(1).get_batch_len(10), 1);
+        assert_eq!(BatchSize::Percentage(100).get_batch_len(10), 10);
+    }
+}
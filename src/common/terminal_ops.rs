@@ -1,3 +1,5 @@
+use std::sync::Once;
+
 use futures::{channel::mpsc::Sender, SinkExt};
 
 use kube::api::TerminalSize;
@@ -19,3 +21,127 @@ pub async fn handle_terminal_size(mut channel: Sender<TerminalSize>) -> Result<(
         channel.send(TerminalSize { height, width }).await?;
     }
 }
+
+/// Enables terminal raw mode and restores the terminal when dropped, this way the terminal is
+/// restored on every exit path of the console, including errors. Panics are covered by a panic
+/// hook since drop is not guaranteed to run
+pub struct RawModeGuard;
+
+impl RawModeGuard {
+    pub fn new() -> Result<Self, std::io::Error> {
+        static RESTORE_TERMINAL_PANIC_HOOK: Once = Once::new();
+
+        RESTORE_TERMINAL_PANIC_HOOK.call_once(|| {
+            let default_panic_hook = std::panic::take_hook();
+
+            std::panic::set_hook(Box::new(move |panic_info| {
+                let _ = crossterm::terminal::disable_raw_mode();
+                default_panic_hook(panic_info);
+            }));
+        });
+
+        crossterm::terminal::enable_raw_mode()?;
+
+        Ok(RawModeGuard)
+    }
+}
+
+impl Drop for RawModeGuard {
+    fn drop(&mut self) {
+        if let Err(error) = crossterm::terminal::disable_raw_mode() {
+            log::error!("Could not restore terminal: {:?}", error);
+        }
+    }
+}
+
+/// Returns once the process receives SIGTERM or SIGHUP so consoles can restore the terminal
+/// before exiting. If the signal handlers can't be registered it never returns, so the console
+/// stays attached
+pub async fn wait_for_termination_signal() {
+    let signal_vec =
+        signal::unix::signal(signal::unix::SignalKind::terminate()).and_then(|sigterm| {
+            signal::unix::signal(signal::unix::SignalKind::hangup()).map(|sighup| (sigterm, sighup))
+        });
+
+    let (mut sigterm, mut sighup) = match signal_vec {
+        Ok(signal_vec) => signal_vec,
+        Err(error) => {
+            log::error!(
+                "Could not register termination signal handlers: {:?}",
+                error
+            );
+            return std::future::pending().await;
+        }
+    };
+
+    tokio::select! {
+        _ = sigterm.recv() => log::info!("SIGTERM received"),
+        _ = sighup.recv() => log::info!("SIGHUP received"),
+    }
+}
+
+/// Splits a console byte stream into lines. Bytes after the last new line are kept until the
+/// line is complete
+#[derive(Debug, Default)]
+pub struct LineBuffer {
+    pending_byte_vec: Vec<u8>,
+}
+
+impl LineBuffer {
+    /// Adds bytes to the buffer and returns the lines completed, without line terminators
+    pub fn push(&mut self, data: &[u8]) -> Vec<String> {
+        self.pending_byte_vec.extend_from_slice(data);
+
+        let mut line_vec = Vec::new();
+
+        while let Some(position) = self.pending_byte_vec.iter().position(|byte| *byte == b'\n') {
+            let line_byte_vec: Vec<u8> = self.pending_byte_vec.drain(..=position).collect();
+
+            line_vec.push(
+                String::from_utf8_lossy(&line_byte_vec)
+                    .trim_end_matches(['\r', '\n'])
+                    .to_string(),
+            );
+        }
+
+        line_vec
+    }
+
+    /// Incomplete line, eg a login prompt waiting for user input
+    pub fn pending(&self) -> String {
+        String::from_utf8_lossy(&self.pending_byte_vec).to_string()
+    }
+}
+
+/// Returns the regex matching ANSI escape sequences (colors, cursor movements, etc). Compile it
+/// once and reuse it for every console line
+pub fn get_ansi_escape_regex() -> regex::Regex {
+    regex::Regex::new(r"\x1b(\[[0-9;?]*[ -/]*[@-~]|\][^\x07]*\x07|[@-Z\\-_])").unwrap()
+}
+
+/// Removes ANSI escape sequences from a console line
+pub fn strip_ansi_escape_codes(ansi_escape_regex: &regex::Regex, line: &str) -> String {
+    ansi_escape_regex.replace_all(line, "").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_buffer() {
+        let mut line_buffer = LineBuffer::default();
+
+        assert!(line_buffer.push(b"Booting ").is_empty());
+        assert_eq!(
+            line_buffer.push(b"kernel\r\n[  \x1b[32mOK\x1b[0m  ] Started\r\nnid001000 login: "),
+            vec!["Booting kernel", "[  \x1b[32mOK\x1b[0m  ] Started"]
+        );
+        assert_eq!(line_buffer.pending(), "nid001000 login: ");
+
+        assert_eq!(
+            strip_ansi_escape_codes(&get_ansi_escape_regex(), "[  \x1b[32mOK\x1b[0m  ] Started"),
+            "[  OK  ] Started"
+        );
+    }
+}